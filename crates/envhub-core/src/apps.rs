@@ -1,21 +1,145 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::{CoreError, ErrorCode, load_state_from_path, save_state_to_path};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::observe::save_and_notify;
+use crate::{
+    AppConfig, CoreError, EnvHistoryEntry, EnvOrder, ErrorCode, InstallMode, ProfileConfig,
+    RelativeTargets, ResolvedTarget, State, StateChange, StateStore, detect_platform,
+    is_shim_installed_in,
+};
+
+/// Everything a list view needs to know about one app.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppSummary {
+    pub name: String,
+    pub target_binary: String,
+    pub active_profile: Option<String>,
+    pub profile_count: usize,
+    pub installed: bool,
+}
+
+/// How `register_app_with` treats a target that does not resolve yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetCheck {
+    /// Don't try to resolve the target.
+    Skip,
+    /// Register anyway and report the problem in `RegisterOutcome`.
+    #[default]
+    Warn,
+    /// Refuse to register with `TargetNotFound`.
+    Require,
+}
+
+/// Non-fatal findings from registering an app.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RegisterOutcome {
+    /// Why the target could not be resolved, when it couldn't, or that it
+    /// names the app's own shim. The target may simply not be installed yet,
+    /// so this is a warning only.
+    pub target_warning: Option<String>,
+}
 
 pub fn register_app(name: &str, target: &str) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
     register_app_in(&path, name, target)
 }
 
-pub fn register_app_in(path: &Path, name: &str, target: &str) -> Result<(), CoreError> {
+pub fn register_app_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    target: &str,
+) -> Result<(), CoreError> {
+    register_app_with_in(store, name, target, TargetCheck::Skip).map(|_| ())
+}
+
+/// Like `register_app`, but tries to resolve the target and reports what it
+/// found in the returned `RegisterOutcome`.
+pub fn register_app_checked(name: &str, target: &str) -> Result<RegisterOutcome, CoreError> {
+    let path = crate::default_state_path()?;
+    register_app_checked_in(&path, name, target)
+}
+
+pub fn register_app_checked_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    target: &str,
+) -> Result<RegisterOutcome, CoreError> {
+    register_app_with_in(store, name, target, TargetCheck::Warn)
+}
+
+pub fn register_app_with(
+    name: &str,
+    target: &str,
+    check: TargetCheck,
+) -> Result<RegisterOutcome, CoreError> {
+    let path = crate::default_state_path()?;
+    register_app_with_in(&path, name, target, check)
+}
+
+pub fn register_app_with_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    target: &str,
+    check: TargetCheck,
+) -> Result<RegisterOutcome, CoreError> {
+    register(store, name, target, check).map(|(outcome, _)| outcome)
+}
+
+/// Like `register_app`, but also returns the state as saved, so callers can
+/// refresh their view without loading it again.
+pub fn register_app_returning_state(
+    name: &str,
+    target: &str,
+) -> Result<(RegisterOutcome, State), CoreError> {
+    let path = crate::default_state_path()?;
+    register_app_returning_state_in(&path, name, target)
+}
+
+pub fn register_app_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    target: &str,
+) -> Result<(RegisterOutcome, State), CoreError> {
+    register(store, name, target, TargetCheck::Warn)
+}
+
+fn register(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    target: &str,
+    check: TargetCheck,
+) -> Result<(RegisterOutcome, State), CoreError> {
     if name.trim().is_empty() || target.trim().is_empty() {
         return Err(CoreError::new(
             ErrorCode::InvalidState,
             "App name and target must be non-empty".to_string(),
         ));
     }
-    let mut state = load_state_from_path(path)?;
+    let target_warning = match check {
+        TargetCheck::Skip => None,
+        TargetCheck::Warn | TargetCheck::Require => crate::resolve_target_binary(target)
+            .err()
+            .map(|err| err.message),
+    };
+    if check == TargetCheck::Require
+        && let Some(warning) = &target_warning
+    {
+        return Err(CoreError::new(ErrorCode::TargetNotFound, warning.clone()));
+    }
+    let mut state = store.load()?;
+    let is_new = !state.apps.contains_key(name);
+    if is_new && let Some((owner, _)) = state.lookup_app(name) {
+        return Err(CoreError::new(
+            ErrorCode::AlreadyExists,
+            format!("\"{name}\" already launches app \"{owner}\""),
+        ));
+    }
     let app = state.apps.entry(name.to_string()).or_default();
+    if is_new {
+        app.created_at = Some(crate::env::utc_timestamp_now());
+    }
     app.target_binary = target.to_string();
     if app.active_profile.is_none() {
         app.active_profile = Some("default".to_string());
@@ -25,8 +149,20 @@ pub fn register_app_in(path: &Path, name: &str, target: &str) -> Result<(), Core
             .insert("default".to_string(), Default::default());
     }
     app.installed = false;
+    app.resolved_target = None;
+    let target_warning = match (crate::lint::self_target_warning(name, app), target_warning) {
+        (Some(own), Some(resolve)) => Some(format!("{own}; {resolve}")),
+        (own, resolve) => own.or(resolve),
+    };
     crate::validate_state(&mut state)?;
-    save_state_to_path(path, &state)
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppRegistered {
+            app: name.to_string(),
+        },
+    )?;
+    Ok((RegisterOutcome { target_warning }, state))
 }
 
 pub fn set_active_profile(name: &str, profile: &str) -> Result<(), CoreError> {
@@ -34,8 +170,25 @@ pub fn set_active_profile(name: &str, profile: &str) -> Result<(), CoreError> {
     set_active_profile_in(&path, name, profile)
 }
 
-pub fn set_active_profile_in(path: &Path, name: &str, profile: &str) -> Result<(), CoreError> {
-    let mut state = load_state_from_path(path)?;
+pub fn set_active_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    set_active_profile_returning_state_in(store, name, profile).map(|_| ())
+}
+
+pub fn set_active_profile_returning_state(name: &str, profile: &str) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    set_active_profile_returning_state_in(&path, name, profile)
+}
+
+pub fn set_active_profile_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<State, CoreError> {
+    let mut state = store.load()?;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
@@ -49,276 +202,3227 @@ pub fn set_active_profile_in(path: &Path, name: &str, profile: &str) -> Result<(
         ));
     }
     app.active_profile = Some(profile.to_string());
-    save_state_to_path(path, &state)
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileActivated {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )?;
+    Ok(state)
 }
 
-pub fn list_apps() -> Result<Vec<String>, CoreError> {
+/// Per-app outcome of an operation applied to several apps, by app name.
+pub type BatchResults = Vec<(String, Result<(), CoreError>)>;
+
+/// Activates `profile` on every app that has a profile by that name, with a
+/// single write. Apps without it are skipped and left out of the result; the
+/// rest are reported per app.
+pub fn set_active_profile_all(profile: &str) -> Result<BatchResults, CoreError> {
     let path = crate::default_state_path()?;
-    list_apps_in(&path)
+    set_active_profile_all_in(&path, profile)
 }
 
-pub fn list_apps_in(path: &Path) -> Result<Vec<String>, CoreError> {
-    let state = load_state_from_path(path)?;
-    Ok(state.apps.keys().cloned().collect())
+pub fn set_active_profile_all_in(
+    store: &(impl StateStore + ?Sized),
+    profile: &str,
+) -> Result<BatchResults, CoreError> {
+    let mut state = store.load()?;
+    let mut switched = Vec::new();
+    for (name, app) in state.apps.iter_mut() {
+        if app.profiles.contains_key(profile) {
+            app.active_profile = Some(profile.to_string());
+            switched.push(name.clone());
+        }
+    }
+    if switched.is_empty() {
+        return Ok(Vec::new());
+    }
+    store.save(&state)?;
+    for name in &switched {
+        crate::observe::notify(&StateChange::ProfileActivated {
+            app: name.clone(),
+            profile: profile.to_string(),
+        });
+    }
+    Ok(switched.into_iter().map(|name| (name, Ok(()))).collect())
 }
 
-pub fn list_profiles(name: &str) -> Result<Vec<String>, CoreError> {
+pub fn set_app_disabled(name: &str, disabled: bool) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    list_profiles_in(&path, name)
+    set_app_disabled_in(&path, name, disabled)
 }
 
-pub fn list_profiles_in(path: &Path, name: &str) -> Result<Vec<String>, CoreError> {
-    let state = load_state_from_path(path)?;
-    let app = state.apps.get(name).ok_or_else(|| {
+pub fn set_app_disabled_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    disabled: bool,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    Ok(app.profiles.keys().cloned().collect())
+    app.disabled = disabled;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
 }
 
-pub fn add_profile(name: &str, profile: &str) -> Result<(), CoreError> {
+/// Turns the launcher's "Run <app> with profile <profile>? [y/N]" prompt on or
+/// off for `name`.
+pub fn set_app_confirm(name: &str, confirm: bool) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    add_profile_in(&path, name, profile)
+    set_app_confirm_in(&path, name, confirm)
 }
 
-pub fn add_profile_in(path: &Path, name: &str, profile: &str) -> Result<(), CoreError> {
-    if profile.trim().is_empty() {
-        return Err(CoreError::new(
-            ErrorCode::InvalidState,
-            "Profile name must be non-empty".to_string(),
-        ));
-    }
-    let mut state = load_state_from_path(path)?;
+pub fn set_app_confirm_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    confirm: bool,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    app.profiles.entry(profile.to_string()).or_default();
-    if app.active_profile.is_none() {
-        app.active_profile = Some(profile.to_string());
-    }
-    save_state_to_path(path, &state)
+    app.confirm_before_run = confirm;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
 }
 
-pub fn remove_profile(name: &str, profile: &str) -> Result<(), CoreError> {
+/// Sets (or with `None` clears) the directory this app's shim is installed
+/// into. The shim at the previous location is left alone, so the app is marked
+/// not installed until it is installed again.
+pub fn set_app_install_path(name: &str, install_path: Option<PathBuf>) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    remove_profile_in(&path, name, profile)
+    set_app_install_path_in(&path, name, install_path)
+}
+
+pub fn set_app_install_path_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    install_path: Option<PathBuf>,
+) -> Result<(), CoreError> {
+    set_app_install_path_returning_state_in(store, name, install_path).map(|_| ())
+}
+
+pub fn set_app_install_path_returning_state(
+    name: &str,
+    install_path: Option<PathBuf>,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    set_app_install_path_returning_state_in(&path, name, install_path)
 }
 
-pub fn remove_profile_in(path: &Path, name: &str, profile: &str) -> Result<(), CoreError> {
-    let mut state = load_state_from_path(path)?;
+pub fn set_app_install_path_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    install_path: Option<PathBuf>,
+) -> Result<State, CoreError> {
+    let mut state = store.load()?;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    if app.profiles.shift_remove(profile).is_none() {
-        return Err(CoreError::new(
-            ErrorCode::ProfileNotFound,
-            format!("Profile \"{profile}\" not found for app \"{name}\""),
-        ));
-    }
-    if app.active_profile.as_deref() == Some(profile) {
-        app.active_profile = app.profiles.keys().next().cloned();
+    let install_path = install_path.map(|dir| dir.to_string_lossy().to_string());
+    if app.install_path != install_path {
+        app.install_path = install_path;
+        app.installed = false;
+        app.resolved_target = None;
     }
-    save_state_to_path(path, &state)
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )?;
+    Ok(state)
 }
 
-pub fn set_profile_env(name: &str, profile: &str, key: &str, value: &str) -> Result<(), CoreError> {
+/// Sets the directory bare target names are resolved in before `PATH`, or
+/// clears it with `None`.
+pub fn set_target_root(name: &str, root: Option<PathBuf>) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    set_profile_env_in(&path, name, profile, key, value)
+    set_target_root_in(&path, name, root)
 }
 
-pub fn set_command_args(
+pub fn set_target_root_in(
+    store: &(impl StateStore + ?Sized),
     name: &str,
-    profile: &str,
-    args: Vec<String>,
+    root: Option<PathBuf>,
 ) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    app.target_root = root.map(|dir| dir.to_string_lossy().to_string());
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
+
+/// Sets what `./` and `../` targets are resolved against.
+pub fn set_relative_targets(name: &str, relative: RelativeTargets) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    set_command_args_in(&path, name, profile, args)
+    set_relative_targets_in(&path, name, relative)
 }
 
-pub fn set_command_args_in(
-    path: &Path,
+pub fn set_relative_targets_in(
+    store: &(impl StateStore + ?Sized),
     name: &str,
-    profile: &str,
-    args: Vec<String>,
+    relative: RelativeTargets,
 ) -> Result<(), CoreError> {
-    let mut state = load_state_from_path(path)?;
+    let mut state = store.load()?;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+    app.relative_targets = relative;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
+
+/// Turns caching of the app's resolved target on or off. Turning it on
+/// resolves the target right away and returns where it was found, or `None`
+/// if it does not resolve yet; launches then scan `PATH` as usual until
+/// [`refresh_resolved_target`] succeeds.
+pub fn set_target_cache(name: &str, enabled: bool) -> Result<Option<PathBuf>, CoreError> {
+    let path = crate::default_state_path()?;
+    set_target_cache_in(&path, name, enabled)
+}
+
+pub fn set_target_cache_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    enabled: bool,
+) -> Result<Option<PathBuf>, CoreError> {
+    let mut state = store.load()?;
+    let mode = state.default_install_mode;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
-            ErrorCode::ProfileNotFound,
-            format!("Profile \"{profile}\" not found for app \"{name}\""),
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
         )
     })?;
-    profile_cfg.command_args = args;
-    save_state_to_path(path, &state)
+    app.cache_target = enabled;
+    app.resolved_target = enabled.then(|| resolve_for_cache(app, mode).ok()).flatten();
+    let resolved = app
+        .resolved_target
+        .as_ref()
+        .map(|cached| PathBuf::from(&cached.path));
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )?;
+    Ok(resolved)
 }
 
-pub fn set_profile_env_in(
-    path: &Path,
+/// Resolves the app's targets again and caches the result, e.g. after the
+/// binary moved. Fails like a launch would when nothing resolves, leaving
+/// the cache empty, and when caching is off for the app.
+pub fn refresh_resolved_target(name: &str) -> Result<PathBuf, CoreError> {
+    let path = crate::default_state_path()?;
+    refresh_resolved_target_in(&path, name)
+}
+
+pub fn refresh_resolved_target_in(
+    store: &(impl StateStore + ?Sized),
     name: &str,
-    profile: &str,
-    key: &str,
-    value: &str,
-) -> Result<(), CoreError> {
-    if key.trim().is_empty() {
+) -> Result<PathBuf, CoreError> {
+    let mut state = store.load()?;
+    let mode = state.default_install_mode;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    if !app.cache_target {
         return Err(CoreError::new(
             ErrorCode::InvalidState,
-            "Environment key must be non-empty".to_string(),
+            format!("Target caching is off for app \"{name}\""),
         ));
     }
-    let mut state = load_state_from_path(path)?;
+    let resolved = resolve_for_cache(app, mode);
+    app.resolved_target = resolved.as_ref().ok().cloned();
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )?;
+    resolved.map(|cached| PathBuf::from(cached.path))
+}
+
+/// Caches `path` as what the app's `targets` resolved to, for the launcher
+/// after a cache miss so the next launch skips the scan. Returns whether the
+/// cache was written: nothing is when caching is off, the cache already
+/// holds `path`, or `targets` are not the app's own list (a profile
+/// override) or are relative to the working directory. Not undoable, since
+/// the user did not ask for it.
+pub fn store_resolved_target(
+    name: &str,
+    targets: &[String],
+    path: &Path,
+) -> Result<bool, CoreError> {
+    let state_path = crate::default_state_path()?;
+    store_resolved_target_in(&state_path, name, targets, path)
+}
+
+pub fn store_resolved_target_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    targets: &[String],
+    path: &Path,
+) -> Result<bool, CoreError> {
+    let mut state = store.load()?;
+    let mode = state.default_install_mode;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+    let resolved = ResolvedTarget {
+        targets: targets.to_vec(),
+        path: path.to_string_lossy().to_string(),
+    };
+    if !app.cache_target
+        || app.resolved_target.as_ref() == Some(&resolved)
+        || app.target_list(mode) != targets
+        || targets
+            .iter()
+            .any(|target| crate::state::is_dot_relative(target))
+    {
+        return Ok(false);
+    }
+    app.resolved_target = Some(resolved);
+    store.save(&state)?;
+    crate::observe::notify(&StateChange::AppUpdated {
+        app: name.to_string(),
+    });
+    Ok(true)
+}
+
+/// Resolves the app's own target list for the cache. Targets relative to
+/// the working directory mean something different on every launch, so they
+/// are never cached.
+fn resolve_for_cache(app: &AppConfig, mode: InstallMode) -> Result<ResolvedTarget, CoreError> {
+    let targets = app.target_list(mode);
+    if targets
+        .iter()
+        .any(|target| crate::state::is_dot_relative(target))
+    {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Targets relative to the working directory cannot be cached".to_string(),
+        ));
+    }
+    let resolved = crate::resolve_target_candidates(&targets)?;
+    Ok(ResolvedTarget {
+        targets,
+        path: resolved.to_string_lossy().to_string(),
+    })
+}
+
+/// Sets the directories prepended to the target's `PATH` at launch. Blank
+/// entries are dropped; an empty list clears them.
+pub fn set_path_prepend(name: &str, dirs: Vec<String>) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_path_prepend_in(&path, name, dirs)
+}
+
+pub fn set_path_prepend_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    dirs: Vec<String>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
-            ErrorCode::ProfileNotFound,
-            format!("Profile \"{profile}\" not found for app \"{name}\""),
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
         )
     })?;
-    profile_env.env.insert(key.to_string(), value.to_string());
-    save_state_to_path(path, &state)
+    app.path_prepend = dirs
+        .iter()
+        .map(|dir| dir.trim())
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_string)
+        .collect();
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
 }
 
-pub fn clone_profile(name: &str, from_profile: &str, to_profile: &str) -> Result<(), CoreError> {
+/// Lets the app also be launched as `alias`. The alias must not be another
+/// app's name or alias. Install the app again to create the alias's shim.
+pub fn add_app_alias(name: &str, alias: &str) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    clone_profile_in(&path, name, from_profile, to_profile)
+    add_app_alias_in(&path, name, alias)
 }
 
-pub fn clone_profile_in(
-    path: &Path,
+pub fn add_app_alias_in(
+    store: &(impl StateStore + ?Sized),
     name: &str,
-    from_profile: &str,
-    to_profile: &str,
+    alias: &str,
 ) -> Result<(), CoreError> {
-    if to_profile.trim().is_empty() {
+    let alias = alias.trim();
+    if alias.is_empty() || alias.contains(['/', '\\']) {
         return Err(CoreError::new(
             ErrorCode::InvalidState,
-            "Target profile name must be non-empty".to_string(),
+            format!("\"{alias}\" is not a valid alias"),
         ));
     }
-    let mut state = load_state_from_path(path)?;
-    let app = state.apps.get_mut(name).ok_or_else(|| {
-        CoreError::new(
+    let mut state = store.load()?;
+    if !state.apps.contains_key(name) {
+        return Err(CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
-        )
-    })?;
-
-    if !app.profiles.contains_key(from_profile) {
-        return Err(CoreError::new(
-            ErrorCode::ProfileNotFound,
-            format!("Source profile \"{from_profile}\" not found for app \"{name}\""),
         ));
     }
-
-    if app.profiles.contains_key(to_profile) {
+    if let Some((owner, _)) = state.lookup_app(alias) {
+        if owner == name && alias != name {
+            return Ok(());
+        }
         return Err(CoreError::new(
-            ErrorCode::InvalidState,
-            format!("Target profile \"{to_profile}\" already exists"),
+            ErrorCode::AlreadyExists,
+            format!("\"{alias}\" already launches app \"{owner}\""),
         ));
     }
-
-    let source_profile = app.profiles.get(from_profile).unwrap().clone();
-    app.profiles.insert(to_profile.to_string(), source_profile);
-
-    if app.active_profile.is_none() {
-        app.active_profile = Some(to_profile.to_string());
+    if let Some(app) = state.apps.get_mut(name) {
+        app.aliases.push(alias.to_string());
     }
-
-    save_state_to_path(path, &state)
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
 }
 
-pub fn remove_profile_env(name: &str, profile: &str, key: &str) -> Result<(), CoreError> {
+/// Drops `alias` from the app and, if the app is installed, deletes the
+/// alias's shim. A file there that isn't an envhub shim is left alone.
+pub fn remove_app_alias(name: &str, alias: &str) -> Result<(), CoreError> {
     let path = crate::default_state_path()?;
-    remove_profile_env_in(&path, name, profile, key)
+    remove_app_alias_in(&path, name, alias)
 }
 
-pub fn remove_profile_env_in(
-    path: &Path,
+pub fn remove_app_alias_in(
+    store: &(impl StateStore + ?Sized),
     name: &str,
-    profile: &str,
-    key: &str,
+    alias: &str,
 ) -> Result<(), CoreError> {
-    let mut state = load_state_from_path(path)?;
+    let mut state = store.load()?;
     let app = state.apps.get_mut(name).ok_or_else(|| {
         CoreError::new(
             ErrorCode::AppNotFound,
             format!("App \"{name}\" is not registered"),
         )
     })?;
-    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
-        CoreError::new(
-            ErrorCode::ProfileNotFound,
-            format!("Profile \"{profile}\" not found for app \"{name}\""),
-        )
-    })?;
-    if profile_env.env.shift_remove(key).is_none() {
+    let before = app.aliases.len();
+    app.aliases.retain(|existing| existing != alias.trim());
+    if app.aliases.len() == before {
         return Err(CoreError::new(
             ErrorCode::InvalidState,
-            format!("Environment key \"{key}\" not found in profile \"{profile}\""),
+            format!("\"{alias}\" is not an alias of app \"{name}\""),
         ));
     }
-    save_state_to_path(path, &state)
+    let install_dir = match &app.install_path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => detect_platform(state.default_install_mode)
+            .ok()
+            .map(|platform| platform.install_dir),
+    };
+    if app.installed
+        && let Some(dir) = install_dir
+    {
+        crate::remove_shim_in(&dir, alias.trim())?;
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+/// Puts the app in a group (folder), or ungroups it with `None` or a blank
+/// name.
+pub fn set_app_group(name: &str, group: Option<&str>) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_app_group_in(&path, name, group)
+}
 
-    #[test]
-    fn register_app_creates_default_profile() {
-        let dir = TempDir::new().expect("temp dir");
-        let path = dir.path().join("state.json");
+pub fn set_app_group_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    group: Option<&str>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    app.group = group
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+        .map(str::to_string);
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
 
-        register_app_in(&path, "tool", "tool-bin").expect("register");
-        let state = load_state_from_path(&path).expect("load");
-        let app = state.apps.get("tool").expect("app exists");
-        assert_eq!(app.target_binary, "tool-bin");
-        assert!(app.profiles.contains_key("default"));
-        assert_eq!(app.active_profile.as_deref(), Some("default"));
-    }
+/// Bucket `list_groups` puts apps without a group in.
+pub const UNGROUPED: &str = "Ungrouped";
 
-    #[test]
-    fn set_active_profile_requires_existing_profile() {
-        let dir = TempDir::new().expect("temp dir");
-        let path = dir.path().join("state.json");
-        register_app_in(&path, "tool", "tool-bin").expect("register");
+/// App names bucketed by group, groups in order of first appearance and
+/// ungrouped apps last under [`UNGROUPED`].
+pub fn list_groups() -> Result<Vec<(String, Vec<String>)>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_groups_in(&path)
+}
 
-        let err = set_active_profile_in(&path, "tool", "missing").unwrap_err();
-        assert_eq!(err.code, ErrorCode::ProfileNotFound);
-    }
+pub fn list_groups_in(
+    store: &(impl StateStore + ?Sized),
+) -> Result<Vec<(String, Vec<String>)>, CoreError> {
+    let state = store.load()?;
+    Ok(group_apps(&state))
+}
 
-    #[test]
-    fn add_and_remove_profile_updates_active() {
-        let dir = TempDir::new().expect("temp dir");
-        let path = dir.path().join("state.json");
-        register_app_in(&path, "tool", "tool-bin").expect("register");
+pub fn group_apps(state: &State) -> Vec<(String, Vec<String>)> {
+    let mut groups: IndexMap<&str, Vec<String>> = IndexMap::new();
+    let mut ungrouped = Vec::new();
+    for (name, app) in &state.apps {
+        match app.group.as_deref() {
+            Some(group) => groups.entry(group).or_default().push(name.clone()),
+            None => ungrouped.push(name.clone()),
+        }
+    }
+    let mut buckets: Vec<(String, Vec<String>)> = groups
+        .into_iter()
+        .map(|(group, apps)| (group.to_string(), apps))
+        .collect();
+    if !ungrouped.is_empty() {
+        buckets.push((UNGROUPED.to_string(), ungrouped));
+    }
+    buckets
+}
 
-        add_profile_in(&path, "tool", "work").expect("add");
+pub fn list_apps() -> Result<Vec<String>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_apps_in(&path)
+}
+
+pub fn list_apps_in(store: &(impl StateStore + ?Sized)) -> Result<Vec<String>, CoreError> {
+    let state = store.load()?;
+    Ok(state.apps.keys().cloned().collect())
+}
+
+pub fn list_apps_detailed(mode: InstallMode) -> Result<Vec<AppSummary>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_apps_detailed_in(&path, mode)
+}
+
+pub fn list_apps_detailed_in(
+    store: &(impl StateStore + ?Sized),
+    mode: InstallMode,
+) -> Result<Vec<AppSummary>, CoreError> {
+    let state = store.load()?;
+    Ok(summarize_apps(&state, mode))
+}
+
+/// Builds an `AppSummary` for every app, resolving the default install
+/// directory once instead of per app.
+pub fn summarize_apps(state: &State, mode: InstallMode) -> Vec<AppSummary> {
+    let default_dir = detect_platform(mode)
+        .ok()
+        .map(|platform| platform.install_dir);
+    state
+        .apps
+        .iter()
+        .map(|(name, app)| {
+            let install_dir = app
+                .install_path
+                .as_ref()
+                .map(PathBuf::from)
+                .or_else(|| default_dir.clone());
+            AppSummary {
+                name: name.clone(),
+                target_binary: app.target_binary.clone(),
+                active_profile: app.active_profile.clone(),
+                profile_count: app.profiles.len(),
+                installed: install_dir.is_some_and(|dir| is_shim_installed_in(&dir, name)),
+            }
+        })
+        .collect()
+}
+
+/// How app lists are ordered for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    #[default]
+    Alphabetical,
+    /// Newest `created_at` first; apps without one come last, by name.
+    RecentlyAdded,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Alphabetical => SortMode::RecentlyAdded,
+            SortMode::RecentlyAdded => SortMode::Alphabetical,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Alphabetical => "name",
+            SortMode::RecentlyAdded => "recently added",
+        }
+    }
+}
+
+/// The names of `state`'s apps ordered by `mode`.
+pub fn sorted_app_names(state: &State, mode: SortMode) -> Vec<String> {
+    let mut apps: Vec<(&String, &AppConfig)> = state.apps.iter().collect();
+    match mode {
+        SortMode::Alphabetical => apps.sort_by(|a, b| a.0.cmp(b.0)),
+        SortMode::RecentlyAdded => apps.sort_by(|a, b| {
+            b.1.created_at
+                .cmp(&a.1.created_at)
+                .then_with(|| a.0.cmp(b.0))
+        }),
+    }
+    apps.into_iter().map(|(name, _)| name.clone()).collect()
+}
+
+pub fn list_profiles(name: &str) -> Result<Vec<String>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_profiles_in(&path, name)
+}
+
+pub fn list_profiles_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+) -> Result<Vec<String>, CoreError> {
+    let state = store.load()?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    Ok(app.profiles.keys().cloned().collect())
+}
+
+/// Profile names in order, each paired with whether it's the one a launch
+/// would use, e.g. for completion scripts that render `* prod`. The flag
+/// follows [`AppConfig::resolve_active_profile`], so with any profiles exactly
+/// one is marked.
+pub fn list_profiles_marked(name: &str) -> Result<Vec<(String, bool)>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_profiles_marked_in(&path, name)
+}
+
+pub fn list_profiles_marked_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+) -> Result<Vec<(String, bool)>, CoreError> {
+    let state = store.load()?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let active = app.resolve_active_profile().map(|(name, _)| name);
+    Ok(app
+        .profiles
+        .keys()
+        .map(|profile| (profile.clone(), Some(profile) == active))
+        .collect())
+}
+
+/// The keys a profile sets, in their stored order, without the values, e.g.
+/// for autocomplete that shouldn't see secrets.
+pub fn list_env_keys(name: &str, profile: &str) -> Result<Vec<String>, CoreError> {
+    let path = crate::default_state_path()?;
+    list_env_keys_in(&path, name, profile)
+}
+
+pub fn list_env_keys_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<Vec<String>, CoreError> {
+    let state = store.load()?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_config = app.profiles.get(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    Ok(profile_config.env.keys().cloned().collect())
+}
+
+/// The values `key` held before its recent overwrites, oldest first, with
+/// when each was replaced. Empty unless `env_history_limit` was set when the
+/// key changed.
+pub fn get_env_history(
+    name: &str,
+    profile: &str,
+    key: &str,
+) -> Result<Vec<EnvHistoryEntry>, CoreError> {
+    let path = crate::default_state_path()?;
+    get_env_history_in(&path, name, profile, key)
+}
+
+pub fn get_env_history_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    key: &str,
+) -> Result<Vec<EnvHistoryEntry>, CoreError> {
+    let state = store.load()?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_config = app.profiles.get(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    Ok(profile_config
+        .env_history
+        .get(key)
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Keeps the last `limit` values of each env key on overwrite from now on.
+/// Existing histories are trimmed to fit, so 0 turns history off and drops
+/// what was kept.
+pub fn set_env_history_limit(limit: usize) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_env_history_limit_in(&path, limit)
+}
+
+pub fn set_env_history_limit_in(
+    store: &(impl StateStore + ?Sized),
+    limit: usize,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    state.env_history_limit = limit;
+    for profile in state
+        .apps
+        .values_mut()
+        .flat_map(|app| app.profiles.values_mut())
+    {
+        for history in profile.env_history.values_mut() {
+            trim_env_history(history, limit);
+        }
+        profile.env_history.retain(|_, history| !history.is_empty());
+    }
+    save_and_notify(store, &state, StateChange::StateSaved)
+}
+
+/// Sets the description and maintainer shown in the state file's `_meta`
+/// block. Values are trimmed and an empty one removes the field; `None`
+/// leaves it as it is. Other keys in the block are kept.
+pub fn set_state_meta(
+    description: Option<&str>,
+    maintainer: Option<&str>,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_state_meta_in(&path, description, maintainer)
+}
+
+pub fn set_state_meta_in(
+    store: &(impl StateStore + ?Sized),
+    description: Option<&str>,
+    maintainer: Option<&str>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let normalize = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+    if let Some(description) = description {
+        state.meta.description = normalize(description);
+    }
+    if let Some(maintainer) = maintainer {
+        state.meta.maintainer = normalize(maintainer);
+    }
+    save_and_notify(store, &state, StateChange::StateSaved)
+}
+
+/// Records that `key` changed from `previous` to `value`, when history is on
+/// and the value really changed.
+fn record_env_history(
+    profile: &mut ProfileConfig,
+    key: &str,
+    previous: &str,
+    value: &str,
+    limit: usize,
+) {
+    if limit == 0 || previous == value {
+        return;
+    }
+    let history = profile.env_history.entry(key.to_string()).or_default();
+    history.push(EnvHistoryEntry {
+        value: previous.to_string(),
+        replaced_at: crate::env::utc_timestamp_now(),
+    });
+    trim_env_history(history, limit);
+}
+
+/// Drops the oldest entries beyond `limit`.
+fn trim_env_history(history: &mut Vec<EnvHistoryEntry>, limit: usize) {
+    let excess = history.len().saturating_sub(limit);
+    history.drain(..excess);
+}
+
+/// Says in a sentence which profile a launch of the app uses and why, e.g.
+/// "profile 'default' is active as a fallback because active_profile 'old'
+/// no longer exists".
+pub fn explain_active_profile(name: &str) -> Result<String, CoreError> {
+    let path = crate::default_state_path()?;
+    explain_active_profile_in(&path, name)
+}
+
+pub fn explain_active_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+) -> Result<String, CoreError> {
+    let state = store.load()?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    Ok(active_profile_reason(app))
+}
+
+fn active_profile_reason(app: &AppConfig) -> String {
+    if app.disabled {
+        return "no profile is applied because the app is disabled".to_string();
+    }
+    let Some((chosen, _)) = app.resolve_active_profile() else {
+        return "no profile is active because the app has no profiles".to_string();
+    };
+    let is_default = app.default_profile.as_ref() == Some(chosen);
+    match &app.active_profile {
+        Some(active) if active == chosen => {
+            format!("profile '{chosen}' is active because active_profile is set and exists")
+        }
+        Some(active) if is_default => format!(
+            "profile '{chosen}' is active as a fallback because active_profile '{active}' no \
+             longer exists; it is the default_profile"
+        ),
+        Some(active) => format!(
+            "profile '{chosen}' is active as a fallback because active_profile '{active}' no \
+             longer exists"
+        ),
+        None if is_default => format!(
+            "profile '{chosen}' is active as a fallback because no active_profile is set; it is \
+             the default_profile"
+        ),
+        None => format!(
+            "profile '{chosen}' is active as a fallback because no active_profile is set; it is \
+             the first profile"
+        ),
+    }
+}
+
+/// Sets the profile launches fall back to when `active_profile` is unset or
+/// missing, or clears it with `None` so the first profile is used.
+pub fn set_default_profile(name: &str, profile: Option<&str>) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_default_profile_in(&path, name, profile)
+}
+
+pub fn set_default_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: Option<&str>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    if let Some(profile) = profile
+        && !app.profiles.contains_key(profile)
+    {
+        return Err(CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        ));
+    }
+    app.default_profile = profile.map(str::to_string);
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
+
+pub fn add_profile(name: &str, profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    add_profile_in(&path, name, profile)
+}
+
+pub fn add_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    add_profile_returning_state_in(store, name, profile).map(|_| ())
+}
+
+pub fn add_profile_returning_state(name: &str, profile: &str) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    add_profile_returning_state_in(&path, name, profile)
+}
+
+pub fn add_profile_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<State, CoreError> {
+    add_profile_activating(store, name, profile, false)
+}
+
+/// Adds `profile` like [`add_profile`] and makes it the active profile, even
+/// when another one is already active.
+pub fn add_profile_and_activate(name: &str, profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    add_profile_and_activate_in(&path, name, profile)
+}
+
+pub fn add_profile_and_activate_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    add_profile_activating(store, name, profile, true).map(|_| ())
+}
+
+pub fn add_profile_and_activate_returning_state(
+    name: &str,
+    profile: &str,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    add_profile_activating(&path, name, profile, true)
+}
+
+pub fn add_profile_and_activate_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<State, CoreError> {
+    add_profile_activating(store, name, profile, true)
+}
+
+fn add_profile_activating(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    activate: bool,
+) -> Result<State, CoreError> {
+    if profile.trim().is_empty() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Profile name must be non-empty".to_string(),
+        ));
+    }
+    let mut state = store.load()?;
+    ensure_profile_allowed(&state, name, profile)?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    app.profiles.entry(profile.to_string()).or_default();
+    if activate || app.active_profile.is_none() {
+        app.active_profile = Some(profile.to_string());
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileAdded {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )?;
+    Ok(state)
+}
+
+/// Rejects `profile` when the app's `allowed_profiles` (or, if that is
+/// empty, the state's) is set and does not list it.
+fn ensure_profile_allowed(state: &State, name: &str, profile: &str) -> Result<(), CoreError> {
+    let allowed = match state.apps.get(name) {
+        Some(app) if !app.allowed_profiles.is_empty() => &app.allowed_profiles,
+        _ => &state.allowed_profiles,
+    };
+    if allowed.is_empty() || allowed.iter().any(|entry| entry == profile) {
+        return Ok(());
+    }
+    Err(CoreError::new(
+        ErrorCode::InvalidState,
+        format!(
+            "Profile name \"{profile}\" is not allowed for app \"{name}\" (allowed: {})",
+            allowed.join(", ")
+        ),
+    ))
+}
+
+/// Removes `profile` from the app. The app's only profile is kept (see
+/// [`remove_profile_forced`]), since `validate_state` would otherwise bring
+/// back an empty "default" in its place.
+pub fn remove_profile(name: &str, profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    remove_profile_in(&path, name, profile)
+}
+
+pub fn remove_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    remove_profile_checked(store, name, profile, false)
+}
+
+/// Like [`remove_profile`], but also removes the app's only profile, leaving
+/// it with none and no active profile until one is added.
+pub fn remove_profile_forced(name: &str, profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    remove_profile_forced_in(&path, name, profile)
+}
+
+pub fn remove_profile_forced_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    remove_profile_checked(store, name, profile, true)
+}
+
+fn remove_profile_checked(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    force: bool,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    if !app.profiles.contains_key(profile) {
+        return Err(CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        ));
+    }
+    if app.profiles.len() == 1 && !force {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Cannot remove the only profile of app \"{name}\""),
+        ));
+    }
+    app.profiles.shift_remove(profile);
+    if app.default_profile.as_deref() == Some(profile) {
+        app.default_profile = None;
+    }
+    if app.active_profile.as_deref() == Some(profile) {
+        app.active_profile = None;
+        app.active_profile = app.resolve_active_profile().map(|(name, _)| name.clone());
+    }
+    for other in app.profiles.values_mut() {
+        other.include.retain(|include| include != profile);
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileRemoved {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Sets `key` on the profile after checking the pair with
+/// [`crate::validate_env_pair`]. An empty value is kept as such and the
+/// launcher injects `KEY=`, unlike removing the key.
+pub fn set_profile_env(name: &str, profile: &str, key: &str, value: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_env_in(&path, name, profile, key, value)
+}
+
+/// Like `set_profile_env`, but returns the value `key` had before, if any, so
+/// callers can report "changed FOO from X to Y" or undo a single edit.
+pub fn set_profile_env_returning(
+    name: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<Option<String>, CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_env_returning_in(&path, name, profile, key, value)
+}
+
+pub fn set_command_args(
+    name: &str,
+    profile: &str,
+    args: Vec<String>,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_command_args_in(&path, name, profile, args)
+}
+
+pub fn set_command_args_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    args: Vec<String>,
+) -> Result<(), CoreError> {
+    set_command_args_returning_state_in(store, name, profile, args).map(|_| ())
+}
+
+pub fn set_command_args_returning_state(
+    name: &str,
+    profile: &str,
+    args: Vec<String>,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    set_command_args_returning_state_in(&path, name, profile, args)
+}
+
+pub fn set_command_args_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    args: Vec<String>,
+) -> Result<State, CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_cfg.command_args = args;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::CommandArgsSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )?;
+    Ok(state)
+}
+
+/// Splits a line of user input into `command_args` the way a POSIX shell
+/// splits words: whitespace separates arguments, single quotes keep text
+/// literally, double quotes keep spaces but honour `\"` and `\\`, and a
+/// backslash outside quotes escapes the next character. No expansion happens.
+/// An unterminated quote runs to the end of the line.
+///
+/// `command_args` are stored already split, one entry per argument, so the
+/// launcher passes `--flag value` through as two arguments only when it was
+/// parsed into two.
+pub fn parse_command_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                current.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(next @ ('"' | '\\')) => current.push(next),
+                            Some(next) => {
+                                current.push('\\');
+                                current.push(next);
+                            }
+                            None => current.push('\\'),
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                current.push(chars.next().unwrap_or('\\'));
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    args
+}
+
+/// Joins `args` into one line that [`parse_command_args`] splits back into
+/// the same arguments, quoting only where needed. Used to prefill editors.
+pub fn format_command_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| crate::install::shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Points `profile` at its own target binary, used instead of the app's
+/// targets while it is active. `None` or a blank target clears it.
+pub fn set_profile_target(
+    name: &str,
+    profile: &str,
+    target: Option<&str>,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_target_in(&path, name, profile, target)
+}
+
+pub fn set_profile_target_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    target: Option<&str>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_cfg.target_binary = target
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(str::to_string);
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileTargetSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Turns isolation on or off for `profile`. When on, launches start from an
+/// empty environment keeping only `inherit_env` (or [`crate::DEFAULT_ISOLATED_ENV`]
+/// when empty) from the parent.
+pub fn set_profile_isolation(
+    name: &str,
+    profile: &str,
+    isolated: bool,
+    inherit_env: Vec<String>,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_isolation_in(&path, name, profile, isolated, inherit_env)
+}
+
+pub fn set_profile_isolation_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    isolated: bool,
+    inherit_env: Vec<String>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_cfg.isolated = isolated;
+    profile_cfg.inherit_env = inherit_env
+        .iter()
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect();
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
+
+/// Attaches a note to `profile`, shown alongside it and at the top of its
+/// dotenv export. `None` or blank text clears it.
+pub fn set_profile_notes(name: &str, profile: &str, notes: Option<&str>) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_notes_in(&path, name, profile, notes)
+}
+
+pub fn set_profile_notes_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    notes: Option<&str>,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_cfg.notes = notes
+        .map(str::trim)
+        .filter(|notes| !notes.is_empty())
+        .map(str::to_string);
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileNotesSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Marks `profile` as dangerous, so the TUI asks before activating it and
+/// shows its notes, or clears the mark.
+pub fn set_profile_dangerous(name: &str, profile: &str, dangerous: bool) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_dangerous_in(&path, name, profile, dangerous)
+}
+
+pub fn set_profile_dangerous_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    dangerous: bool,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_cfg.dangerous = dangerous;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileDangerousSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Layers `included`'s env beneath `profile`'s, after any it already
+/// includes. Both must be profiles of the app, and the include must not make
+/// a cycle. Including a profile twice is a no-op.
+pub fn add_profile_include(name: &str, profile: &str, included: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    add_profile_include_in(&path, name, profile, included)
+}
+
+pub fn add_profile_include_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    included: &str,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    for existing in [profile, included] {
+        if !app.profiles.contains_key(existing) {
+            return Err(CoreError::new(
+                ErrorCode::ProfileNotFound,
+                format!("Profile \"{existing}\" not found for app \"{name}\""),
+            ));
+        }
+    }
+    let includes = &mut app.profiles[profile].include;
+    if includes.iter().any(|existing| existing == included) {
+        return Ok(());
+    }
+    includes.push(included.to_string());
+    app.layered_env(profile)?;
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileIncludesSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Stops layering `included`'s env beneath `profile`'s.
+pub fn remove_profile_include(name: &str, profile: &str, included: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    remove_profile_include_in(&path, name, profile, included)
+}
+
+pub fn remove_profile_include_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    included: &str,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_cfg = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    let before = profile_cfg.include.len();
+    profile_cfg.include.retain(|existing| existing != included);
+    if profile_cfg.include.len() == before {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Profile \"{profile}\" does not include \"{included}\""),
+        ));
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileIncludesSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+pub fn set_profile_env_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), CoreError> {
+    set_profile_env_returning_in(store, name, profile, key, value).map(|_| ())
+}
+
+pub fn set_profile_env_returning_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<Option<String>, CoreError> {
+    set_profile_env_returning_state_in(store, name, profile, key, value)
+        .map(|(previous, _)| previous)
+}
+
+/// Like `set_profile_env_returning`, and also returns the state as saved.
+pub fn set_profile_env_returning_state(
+    name: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<(Option<String>, State), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_env_returning_state_in(&path, name, profile, key, value)
+}
+
+pub fn set_profile_env_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<(Option<String>, State), CoreError> {
+    crate::validate_env_pair(key, value)?;
+    let mut state = store.load()?;
+    let limit = state.env_history_limit;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let env_order = app.env_order;
+    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    let previous = profile_env.env.insert(key.to_string(), value.to_string());
+    if let Some(previous) = &previous {
+        record_env_history(profile_env, key, previous, value, limit);
+    }
+    if env_order == EnvOrder::Sorted {
+        profile_env.env.sort_keys();
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::EnvSet {
+            app: name.to_string(),
+            profile: profile.to_string(),
+            key: key.to_string(),
+        },
+    )?;
+    Ok((previous, state))
+}
+
+/// Sets every variable in `env` on the profile in one write, e.g. from a
+/// pasted `.env` block. Existing keys are overwritten in place.
+pub fn set_profile_env_bulk(
+    name: &str,
+    profile: &str,
+    env: &IndexMap<String, String>,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_env_bulk_in(&path, name, profile, env)
+}
+
+pub fn set_profile_env_bulk_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    env: &IndexMap<String, String>,
+) -> Result<(), CoreError> {
+    set_profile_env_bulk_returning_state_in(store, name, profile, env).map(|_| ())
+}
+
+pub fn set_profile_env_bulk_returning_state(
+    name: &str,
+    profile: &str,
+    env: &IndexMap<String, String>,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    set_profile_env_bulk_returning_state_in(&path, name, profile, env)
+}
+
+pub fn set_profile_env_bulk_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    env: &IndexMap<String, String>,
+) -> Result<State, CoreError> {
+    for (key, value) in env {
+        crate::validate_env_pair(key, value)?;
+    }
+    let mut state = store.load()?;
+    let limit = state.env_history_limit;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let env_order = app.env_order;
+    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    for (key, value) in env {
+        if let Some(previous) = profile_env.env.insert(key.clone(), value.clone()) {
+            record_env_history(profile_env, key, &previous, value, limit);
+        }
+    }
+    if env_order == EnvOrder::Sorted {
+        profile_env.env.sort_keys();
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::EnvImported {
+            app: name.to_string(),
+            profile: profile.to_string(),
+            keys: env.keys().cloned().collect(),
+        },
+    )?;
+    Ok(state)
+}
+
+/// Reorders a profile's env alphabetically by key, once. Later inserts follow
+/// the app's `env_order`.
+pub fn sort_profile_env(name: &str, profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    sort_profile_env_in(&path, name, profile)
+}
+
+pub fn sort_profile_env_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    profile_env.env.sort_keys();
+    save_and_notify(
+        store,
+        &state,
+        StateChange::EnvReordered {
+            app: name.to_string(),
+            profile: profile.to_string(),
+        },
+    )
+}
+
+/// Sets how new env keys are placed for an app. Switching to `Sorted` also
+/// sorts every existing profile so the invariant holds from then on.
+pub fn set_env_order(name: &str, order: EnvOrder) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    set_env_order_in(&path, name, order)
+}
+
+pub fn set_env_order_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    order: EnvOrder,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    app.env_order = order;
+    if order == EnvOrder::Sorted {
+        for profile in app.profiles.values_mut() {
+            profile.env.sort_keys();
+        }
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )
+}
+
+/// Controls which parts of a profile `clone_profile_with` copies. Anything not
+/// covered by a flag (output redirection, unknown fields) is always copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloneOptions {
+    /// The env and its `env_history`.
+    pub copy_env: bool,
+    pub copy_args: bool,
+    /// The profile's `notes` and a hand-written `_comment` field.
+    pub copy_comments: bool,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        Self {
+            copy_env: true,
+            copy_args: true,
+            copy_comments: true,
+        }
+    }
+}
+
+pub fn clone_profile(name: &str, from_profile: &str, to_profile: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_in(&path, name, from_profile, to_profile)
+}
+
+pub fn clone_profile_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+) -> Result<(), CoreError> {
+    clone_profile_with_in(
+        store,
+        name,
+        from_profile,
+        to_profile,
+        CloneOptions::default(),
+    )
+}
+
+pub fn clone_profile_with(
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_with_in(&path, name, from_profile, to_profile, options)
+}
+
+pub fn clone_profile_with_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<(), CoreError> {
+    clone_profile_returning_state_in(store, name, from_profile, to_profile, options).map(|_| ())
+}
+
+pub fn clone_profile_returning_state(
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_returning_state_in(&path, name, from_profile, to_profile, options)
+}
+
+pub fn clone_profile_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<State, CoreError> {
+    clone_profile_activating(store, name, from_profile, to_profile, options, false)
+}
+
+/// Copies a profile like [`clone_profile_with`] and makes the copy the
+/// active profile, even when another one is already active.
+pub fn clone_profile_and_activate(
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_and_activate_in(&path, name, from_profile, to_profile, options)
+}
+
+pub fn clone_profile_and_activate_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<(), CoreError> {
+    clone_profile_activating(store, name, from_profile, to_profile, options, true).map(|_| ())
+}
+
+pub fn clone_profile_and_activate_returning_state(
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<State, CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_activating(&path, name, from_profile, to_profile, options, true)
+}
+
+pub fn clone_profile_and_activate_returning_state_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+) -> Result<State, CoreError> {
+    clone_profile_activating(store, name, from_profile, to_profile, options, true)
+}
+
+fn clone_profile_activating(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    from_profile: &str,
+    to_profile: &str,
+    options: CloneOptions,
+    activate: bool,
+) -> Result<State, CoreError> {
+    if to_profile.trim().is_empty() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Target profile name must be non-empty".to_string(),
+        ));
+    }
+    let mut state = store.load()?;
+    ensure_profile_allowed(&state, name, to_profile)?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+
+    if !app.profiles.contains_key(from_profile) {
+        return Err(CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Source profile \"{from_profile}\" not found for app \"{name}\""),
+        ));
+    }
+
+    if app.profiles.contains_key(to_profile) {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Target profile \"{to_profile}\" already exists"),
+        ));
+    }
+
+    let mut source_profile = app.profiles.get(from_profile).unwrap().clone();
+    if !options.copy_env {
+        source_profile.env.clear();
+        source_profile.env_history.clear();
+    }
+    if !options.copy_args {
+        source_profile.command_args.clear();
+    }
+    if !options.copy_comments {
+        source_profile.notes = None;
+        source_profile.extra.shift_remove("_comment");
+    }
+    app.profiles.insert(to_profile.to_string(), source_profile);
+
+    if activate || app.active_profile.is_none() {
+        app.active_profile = Some(to_profile.to_string());
+    }
+
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileAdded {
+            app: name.to_string(),
+            profile: to_profile.to_string(),
+        },
+    )?;
+    Ok(state)
+}
+
+/// Copies `from_profile` of `from_app` into `to_app` as `to_profile`, for
+/// tools that share an environment setup. The copy is independent of the
+/// source afterwards; its `include` keeps only profiles `to_app` also has.
+pub fn clone_profile_cross(
+    from_app: &str,
+    from_profile: &str,
+    to_app: &str,
+    to_profile: &str,
+) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    clone_profile_cross_in(&path, from_app, from_profile, to_app, to_profile)
+}
+
+pub fn clone_profile_cross_in(
+    store: &(impl StateStore + ?Sized),
+    from_app: &str,
+    from_profile: &str,
+    to_app: &str,
+    to_profile: &str,
+) -> Result<(), CoreError> {
+    if to_profile.trim().is_empty() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Target profile name must be non-empty".to_string(),
+        ));
+    }
+    let mut state = store.load()?;
+    let source = state.apps.get(from_app).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{from_app}\" is not registered"),
+        )
+    })?;
+    let mut profile = source.profiles.get(from_profile).cloned().ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Source profile \"{from_profile}\" not found for app \"{from_app}\""),
+        )
+    })?;
+    ensure_profile_allowed(&state, to_app, to_profile)?;
+    let destination = state.apps.get_mut(to_app).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{to_app}\" is not registered"),
+        )
+    })?;
+    if destination.profiles.contains_key(to_profile) {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Target profile \"{to_profile}\" already exists in app \"{to_app}\""),
+        ));
+    }
+    profile
+        .include
+        .retain(|included| destination.profiles.contains_key(included));
+    // Old values, often rotated secrets, stay with the app they were set on.
+    profile.env_history.clear();
+    destination.profiles.insert(to_profile.to_string(), profile);
+    if destination.active_profile.is_none() {
+        destination.active_profile = Some(to_profile.to_string());
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::ProfileAdded {
+            app: to_app.to_string(),
+            profile: to_profile.to_string(),
+        },
+    )
+}
+
+pub fn remove_profile_env(name: &str, profile: &str, key: &str) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    remove_profile_env_in(&path, name, profile, key)
+}
+
+pub fn remove_profile_env_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    key: &str,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    if profile_env.env.shift_remove(key).is_none() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Environment key \"{key}\" not found in profile \"{profile}\""),
+        ));
+    }
+    save_and_notify(
+        store,
+        &state,
+        StateChange::EnvRemoved {
+            app: name.to_string(),
+            profile: profile.to_string(),
+            keys: vec![key.to_string()],
+        },
+    )
+}
+
+/// Removes several keys from a profile with a single write. Keys that are not
+/// present are skipped; returns how many were removed.
+pub fn remove_profile_env_bulk(
+    name: &str,
+    profile: &str,
+    keys: &[String],
+) -> Result<usize, CoreError> {
+    let path = crate::default_state_path()?;
+    remove_profile_env_bulk_in(&path, name, profile, keys)
+}
+
+pub fn remove_profile_env_bulk_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+    profile: &str,
+    keys: &[String],
+) -> Result<usize, CoreError> {
+    let mut state = store.load()?;
+    let app = state.apps.get_mut(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_env = app.profiles.get_mut(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    let removed: Vec<String> = keys
+        .iter()
+        .filter(|key| profile_env.env.shift_remove(key.as_str()).is_some())
+        .cloned()
+        .collect();
+    let count = removed.len();
+    if count > 0 {
+        save_and_notify(
+            store,
+            &state,
+            StateChange::EnvRemoved {
+                app: name.to_string(),
+                profile: profile.to_string(),
+                keys: removed,
+            },
+        )?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_INCLUDE_DEPTH, load_state_from_path};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn register_app_creates_default_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let state = load_state_from_path(&path).expect("load");
+        let app = state.apps.get("tool").expect("app exists");
+        assert_eq!(app.target_binary, "tool-bin");
+        assert!(app.profiles.contains_key("default"));
+        assert_eq!(app.active_profile.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn register_app_warns_when_target_is_its_own_name() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let outcome =
+            register_app_with_in(&path, "node", "node", TargetCheck::Skip).expect("register");
+        let warning = outcome.target_warning.expect("warning");
+        assert!(
+            warning.starts_with("target \"node\" is the app's own shim name"),
+            "{warning}"
+        );
+
+        register_app_with_in(&path, "inode", "node", TargetCheck::Skip).expect("register");
+        add_app_alias_in(&path, "inode", "nodex").expect("alias");
+        let outcome =
+            register_app_with_in(&path, "inode", "nodex", TargetCheck::Skip).expect("register");
+        assert!(
+            outcome
+                .target_warning
+                .expect("warning")
+                .contains("\"nodex\"")
+        );
+        let outcome = register_app_with_in(&path, "inode", "/usr/bin/node", TargetCheck::Skip)
+            .expect("register");
+        assert_eq!(outcome.target_warning, None);
+    }
+
+    #[test]
+    fn register_app_warns_about_missing_absolute_target() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let existing = dir.path().join("tool-bin");
+        std::fs::write(&existing, b"binary").expect("target");
+        let missing = dir.path().join("not-installed-yet");
+
+        let outcome =
+            register_app_checked_in(&path, "tool", &existing.to_string_lossy()).expect("register");
+        assert_eq!(outcome.target_warning, None);
+
+        let outcome =
+            register_app_checked_in(&path, "later", &missing.to_string_lossy()).expect("register");
+        assert!(outcome.target_warning.is_some());
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps.contains_key("later"));
+
+        let err = register_app_with_in(
+            &path,
+            "strict",
+            &missing.to_string_lossy(),
+            TargetCheck::Require,
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::TargetNotFound);
+        let state = load_state_from_path(&path).expect("load");
+        assert!(!state.apps.contains_key("strict"));
+    }
+
+    #[test]
+    fn set_active_profile_requires_existing_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        let err = set_active_profile_in(&path, "tool", "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn add_and_remove_profile_updates_active() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        add_profile_in(&path, "tool", "work").expect("add");
         set_active_profile_in(&path, "tool", "work").expect("active");
         remove_profile_in(&path, "tool", "work").expect("remove");
 
         let state = load_state_from_path(&path).expect("load");
-        let app = state.apps.get("tool").expect("app");
-        assert_ne!(app.active_profile.as_deref(), Some("work"));
+        let app = state.apps.get("tool").expect("app");
+        assert_ne!(app.active_profile.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn allowed_profiles_restrict_new_profile_names() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        register_app_in(&path, "other", "other-bin").expect("register");
+        let mut state = load_state_from_path(&path).expect("load");
+        state.allowed_profiles = vec!["default".to_string(), "dev".to_string()];
+        state.apps.get_mut("other").expect("app").allowed_profiles = vec!["prod".to_string()];
+        crate::save_state_to_path(&path, &state).expect("save");
+
+        add_profile_in(&path, "tool", "dev").expect("allowed by state");
+        let err = add_profile_in(&path, "tool", "scratch").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        let err = clone_profile_in(&path, "tool", "default", "prod").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+
+        add_profile_in(&path, "other", "prod").expect("allowed by app");
+        let err = clone_profile_in(&path, "other", "default", "dev").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+
+        let state = load_state_from_path(&path).expect("load");
+        let tool = state.apps.get("tool").expect("app");
+        assert!(tool.profiles.contains_key("dev"));
+        assert!(!tool.profiles.contains_key("scratch"));
+        assert!(!tool.profiles.contains_key("prod"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn target_root_is_preferred_over_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let root = dir.path().join("versions").join("v18").join("bin");
+        std::fs::create_dir_all(&root).expect("root");
+        let pinned = root.join("sh");
+        std::fs::write(&pinned, b"#!/bin/sh\n").expect("pinned");
+        std::fs::set_permissions(&pinned, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        register_app_in(&path, "tool", "sh").expect("register");
+
+        set_target_root_in(&path, "tool", Some(root.clone())).expect("set root");
+        let state = load_state_from_path(&path).expect("load");
+        let targets = state.apps["tool"].target_list(InstallMode::User);
+        assert_eq!(
+            targets,
+            [pinned.to_string_lossy().to_string(), "sh".to_string()]
+        );
+        let self_path = std::env::current_exe().expect("self");
+        let resolved =
+            crate::resolve_target_candidates_from(&targets, &self_path).expect("resolve");
+        assert_eq!(resolved, pinned);
+
+        // Without the binary under the root, PATH is used as before.
+        std::fs::remove_file(&pinned).expect("remove");
+        let resolved =
+            crate::resolve_target_candidates_from(&targets, &self_path).expect("resolve");
+        assert_ne!(resolved, pinned);
+
+        set_target_root_in(&path, "tool", None).expect("clear root");
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["tool"].target_list(InstallMode::User), ["sh"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_target_resolves_against_base_not_cwd() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let base = dir.path().join("tools");
+        std::fs::create_dir_all(&base).expect("base");
+        let tool = base.join("envhub-relative-tool");
+        std::fs::write(&tool, b"#!/bin/sh\n").expect("tool");
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        let canonical = tool.canonicalize().expect("canonicalize");
+        register_app_in(&path, "tool", "./envhub-relative-tool").expect("register");
+        let self_path = std::env::current_exe().expect("self");
+
+        // Relative to the working directory (the crate dir), it isn't there.
+        let targets =
+            load_state_from_path(&path).expect("load").apps["tool"].target_list(InstallMode::User);
+        assert!(crate::resolve_target_candidates_from(&targets, &self_path).is_err());
+
+        let relative = RelativeTargets::Base(base.to_string_lossy().to_string());
+        set_relative_targets_in(&path, "tool", relative).expect("set base");
+        let targets =
+            load_state_from_path(&path).expect("load").apps["tool"].target_list(InstallMode::User);
+        let resolved =
+            crate::resolve_target_candidates_from(&targets, &self_path).expect("resolve");
+        assert_eq!(resolved.canonicalize().unwrap(), canonical);
+
+        crate::set_app_install_path_in(&path, "tool", Some(base.clone())).expect("install path");
+        set_relative_targets_in(&path, "tool", RelativeTargets::InstallPath).expect("set");
+        let targets =
+            load_state_from_path(&path).expect("load").apps["tool"].target_list(InstallMode::User);
+        let resolved =
+            crate::resolve_target_candidates_from(&targets, &self_path).expect("resolve");
+        assert_eq!(resolved.canonicalize().unwrap(), canonical);
+    }
+
+    #[test]
+    fn clone_profile_cross_copies_between_apps_independently() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        register_app_in(&path, "codex", "codex-bin").expect("register");
+        add_profile_in(&path, "claude", "proxy").expect("add");
+        set_profile_env_in(&path, "claude", "proxy", "HTTPS_PROXY", "http://proxy:8080")
+            .expect("set");
+        set_command_args_in(&path, "claude", "proxy", vec!["--verbose".to_string()]).expect("args");
+        set_env_history_limit_in(&path, 5).expect("limit");
+        set_profile_env_in(&path, "claude", "proxy", "HTTPS_PROXY", "http://proxy:8080")
+            .expect("set again");
+
+        clone_profile_cross_in(&path, "claude", "proxy", "codex", "proxy").expect("clone");
+        set_profile_env_in(&path, "claude", "proxy", "HTTPS_PROXY", "changed").expect("set");
+
+        let state = load_state_from_path(&path).expect("load");
+        let copy = &state.apps["codex"].profiles["proxy"];
+        assert_eq!(copy.env["HTTPS_PROXY"], "http://proxy:8080");
+        assert_eq!(copy.command_args, ["--verbose"]);
+        assert!(copy.env_history.is_empty());
+        assert!(
+            !state.apps["claude"].profiles["proxy"]
+                .env_history
+                .is_empty()
+        );
+        assert_eq!(
+            state.apps["codex"].active_profile.as_deref(),
+            Some("default")
+        );
+
+        let err = clone_profile_cross_in(&path, "claude", "proxy", "codex", "proxy").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        let err = clone_profile_cross_in(&path, "claude", "missing", "codex", "x").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+        let err = clone_profile_cross_in(&path, "nope", "proxy", "codex", "x").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+        let err = clone_profile_cross_in(&path, "claude", "proxy", "nope", "x").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn clone_profile_cross_drops_includes_missing_from_the_target_app() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        register_app_in(&path, "codex", "codex-bin").expect("register");
+        add_profile_in(&path, "claude", "base").expect("add");
+        add_profile_in(&path, "claude", "work").expect("add");
+        add_profile_include_in(&path, "claude", "work", "default").expect("include");
+        add_profile_include_in(&path, "claude", "work", "base").expect("include");
+
+        clone_profile_cross_in(&path, "claude", "work", "codex", "work").expect("clone");
+
+        let mut state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["codex"].profiles["work"].include, ["default"]);
+        assert_eq!(
+            state.apps["claude"].profiles["work"].include,
+            ["default", "base"]
+        );
+        crate::validate_state(&mut state).expect("no dangling includes");
+    }
+
+    #[test]
+    fn explain_active_profile_covers_each_branch() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        add_profile_in(&path, "tool", "prod").expect("profile");
+        set_active_profile_in(&path, "tool", "prod").expect("activate");
+        assert_eq!(
+            explain_active_profile_in(&path, "tool").expect("explain"),
+            "profile 'prod' is active because active_profile is set and exists"
+        );
+
+        let mut app = load_state_from_path(&path).expect("load").apps["tool"].clone();
+        app.active_profile = Some("old".to_string());
+        assert_eq!(
+            active_profile_reason(&app),
+            "profile 'default' is active as a fallback because active_profile 'old' no longer \
+             exists"
+        );
+        app.active_profile = None;
+        assert!(
+            active_profile_reason(&app)
+                .starts_with("profile 'default' is active as a fallback because no active_profile")
+        );
+        app.profiles.clear();
+        assert_eq!(
+            active_profile_reason(&app),
+            "no profile is active because the app has no profiles"
+        );
+        app.disabled = true;
+        assert!(active_profile_reason(&app).ends_with("the app is disabled"));
+
+        let err = explain_active_profile_in(&path, "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn launches_fall_back_from_active_to_default_to_first_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        add_profile_in(&path, "tool", "work").expect("profile");
+        add_profile_in(&path, "tool", "home").expect("profile");
+        set_active_profile_in(&path, "tool", "work").expect("activate");
+        set_default_profile_in(&path, "tool", Some("home")).expect("default");
+        let chosen = |app: &AppConfig| app.resolve_active_profile().map(|(name, _)| name.clone());
+
+        let mut app = load_state_from_path(&path).expect("load").apps["tool"].clone();
+        assert_eq!(chosen(&app).as_deref(), Some("work"));
+        app.active_profile = Some("gone".to_string());
+        assert_eq!(chosen(&app).as_deref(), Some("home"));
+        assert!(active_profile_reason(&app).ends_with("it is the default_profile"));
+        app.active_profile = None;
+        assert_eq!(chosen(&app).as_deref(), Some("home"));
+        app.default_profile = Some("gone".to_string());
+        assert_eq!(chosen(&app).as_deref(), Some("default"));
+        app.default_profile = None;
+        assert_eq!(chosen(&app).as_deref(), Some("default"));
+
+        remove_profile_in(&path, "tool", "work").expect("remove");
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert_eq!(app.active_profile.as_deref(), Some("home"));
+        remove_profile_in(&path, "tool", "home").expect("remove");
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert_eq!(app.default_profile, None);
+        assert_eq!(app.active_profile.as_deref(), Some("default"));
+
+        let err = set_default_profile_in(&path, "tool", Some("missing")).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+        set_default_profile_in(&path, "tool", None).expect("clear");
+    }
+
+    #[test]
+    fn aliases_resolve_to_their_app() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        register_app_in(&path, "node", "node-bin").expect("register");
+        add_app_alias_in(&path, "claude", "claudex").expect("alias");
+        add_app_alias_in(&path, "claude", "claudex").expect("repeat is a no-op");
+
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["claude"].aliases, ["claudex"]);
+        let (owner, app) = state.lookup_app("claudex").expect("alias lookup");
+        assert_eq!(owner, "claude");
+        assert_eq!(app.target_binary, "claude-bin");
+        assert_eq!(state.lookup_app("node").expect("exact").0, "node");
+        assert!(state.lookup_app("missing").is_none());
+
+        let taken = add_app_alias_in(&path, "node", "claudex").unwrap_err();
+        assert_eq!(taken.code, ErrorCode::AlreadyExists);
+        let taken = add_app_alias_in(&path, "claude", "node").unwrap_err();
+        assert_eq!(taken.code, ErrorCode::AlreadyExists);
+
+        remove_app_alias_in(&path, "claude", "claudex").expect("remove");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.lookup_app("claudex").is_none());
+        let err = remove_app_alias_in(&path, "claude", "claudex").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn new_apps_cannot_shadow_an_alias() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        add_app_alias_in(&path, "claude", "claudex").expect("alias");
+
+        let err = register_app_in(&path, "claudex", "other-bin").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+        let state = load_state_from_path(&path).expect("load");
+        assert!(!state.apps.contains_key("claudex"));
+        register_app_in(&path, "claude", "claude-bin").expect("re-register");
+    }
+
+    #[test]
+    fn removing_an_alias_removes_its_shim() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let bin = dir.path().join("bin");
+        let launcher = dir.path().join(crate::launcher_file_name());
+        std::fs::write(&launcher, crate::LAUNCHER_MAGIC).expect("launcher");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        set_app_install_path_in(&path, "claude", Some(bin.clone())).expect("path");
+        add_app_alias_in(&path, "claude", "claudex").expect("alias");
+        add_app_alias_in(&path, "claude", "cc").expect("alias");
+        crate::install_shim_and_record_in(&path, "claude", InstallMode::User, &launcher)
+            .expect("install");
+        // Not ours, so it stays.
+        let cc = crate::shim_path_in(&bin, "cc");
+        std::fs::remove_file(&cc).expect("remove");
+        std::fs::write(&cc, b"real cc").expect("cc");
+
+        remove_app_alias_in(&path, "claude", "claudex").expect("remove");
+        remove_app_alias_in(&path, "claude", "cc").expect("remove");
+        assert!(!crate::shim_path_in(&bin, "claudex").exists());
+        assert_eq!(std::fs::read(&cc).expect("cc"), b"real cc");
+        assert!(crate::is_shim_installed_in(&bin, "claude"));
+    }
+
+    #[test]
+    fn set_profile_env_bulk_overwrites_and_appends() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "HOST", "old").expect("set");
+
+        let (env, _) = crate::parse_env_lines("PORT=8080\nHOST=new\n");
+        set_profile_env_bulk_in(&path, "tool", "default", &env).expect("bulk");
+        let state = load_state_from_path(&path).expect("load");
+        let profile_env = &state.apps["tool"].profiles["default"].env;
+        assert_eq!(profile_env.keys().collect::<Vec<_>>(), ["HOST", "PORT"]);
+        assert_eq!(profile_env["HOST"], "new");
+
+        let err = set_profile_env_bulk_in(&path, "tool", "missing", &env).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn sort_modes_order_apps() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let created = load_state_from_path(&path).expect("load").apps["tool"]
+            .created_at
+            .clone()
+            .expect("created_at");
+        register_app_in(&path, "tool", "other-bin").expect("re-register");
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["tool"].created_at.as_ref(), Some(&created));
+
+        let mut state = State::default();
+        for (name, created_at) in [
+            ("beta", Some("2024-03-01T00:00:00Z")),
+            ("legacy", None),
+            ("alpha", Some("2024-01-01T00:00:00Z")),
+            ("gamma", Some("2024-03-01T00:00:00Z")),
+        ] {
+            let app = AppConfig {
+                created_at: created_at.map(str::to_string),
+                ..AppConfig::default()
+            };
+            state.apps.insert(name.to_string(), app);
+        }
+        assert_eq!(
+            sorted_app_names(&state, SortMode::Alphabetical),
+            ["alpha", "beta", "gamma", "legacy"]
+        );
+        assert_eq!(
+            sorted_app_names(&state, SortMode::RecentlyAdded),
+            ["beta", "gamma", "alpha", "legacy"]
+        );
+    }
+
+    #[test]
+    fn set_profile_env_returning_reports_previous_value() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        let first = set_profile_env_returning_in(&path, "tool", "default", "HOST", "a");
+        assert_eq!(first.expect("set"), None);
+        let second = set_profile_env_returning_in(&path, "tool", "default", "HOST", "b");
+        assert_eq!(second.expect("set").as_deref(), Some("a"));
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["tool"].profiles["default"].env["HOST"], "b");
+    }
+
+    #[test]
+    fn empty_values_are_kept_distinct_from_unset_keys() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        set_profile_env_in(&path, "tool", "default", "EMPTY", "").expect("set empty");
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(
+            state.apps["tool"].profiles["default"].env.get("EMPTY"),
+            Some(&String::new())
+        );
+        let previous = set_profile_env_returning_in(&path, "tool", "default", "EMPTY", "x");
+        assert_eq!(previous.expect("set").as_deref(), Some(""));
+
+        let err = set_profile_env_in(&path, "tool", "default", " ", "x").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn returning_state_variants_match_the_saved_state() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let (_, state) =
+            register_app_returning_state_in(&path, "tool", "tool-bin").expect("register");
+        assert!(state.apps.contains_key("tool"));
+
+        let state = add_profile_returning_state_in(&path, "tool", "work").expect("add");
+        assert!(state.apps["tool"].profiles.contains_key("work"));
+        let state = set_active_profile_returning_state_in(&path, "tool", "work").expect("activate");
+        assert_eq!(state.apps["tool"].active_profile.as_deref(), Some("work"));
+        let (previous, state) =
+            set_profile_env_returning_state_in(&path, "tool", "work", "HOST", "a").expect("set");
+        assert_eq!(previous, None);
+        assert_eq!(state.apps["tool"].profiles["work"].env["HOST"], "a");
+        let state = clone_profile_returning_state_in(
+            &path,
+            "tool",
+            "work",
+            "copy",
+            CloneOptions::default(),
+        )
+        .expect("clone");
+        assert_eq!(state.apps["tool"].profiles["copy"].env["HOST"], "a");
+        let state = set_command_args_returning_state_in(&path, "tool", "copy", vec!["-v".into()])
+            .expect("args");
+        assert_eq!(state.apps["tool"].profiles["copy"].command_args, ["-v"]);
+        let env = IndexMap::from([("PORT".to_string(), "80".to_string())]);
+        let state =
+            set_profile_env_bulk_returning_state_in(&path, "tool", "copy", &env).expect("bulk");
+        assert_eq!(state.apps["tool"].profiles["copy"].env["PORT"], "80");
+        let state = set_app_install_path_returning_state_in(&path, "tool", Some("/opt/bin".into()))
+            .expect("install path");
+        assert_eq!(state.apps["tool"].install_path.as_deref(), Some("/opt/bin"));
+
+        let loaded = load_state_from_path(&path).expect("load");
+        assert_eq!(
+            serde_json::to_value(&loaded.apps).unwrap(),
+            serde_json::to_value(&state.apps).unwrap()
+        );
+        let err = add_profile_returning_state_in(&path, "missing", "work").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn env_history_keeps_the_last_n_overwritten_values() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let values = |path: &Path| -> Vec<String> {
+            get_env_history_in(path, "tool", "default", "TOKEN")
+                .expect("history")
+                .into_iter()
+                .map(|entry| entry.value)
+                .collect()
+        };
+
+        set_profile_env_in(&path, "tool", "default", "TOKEN", "v1").expect("set");
+        set_profile_env_in(&path, "tool", "default", "TOKEN", "v2").expect("set");
+        assert!(values(&path).is_empty(), "history is off by default");
+
+        set_env_history_limit_in(&path, 2).expect("limit");
+        for value in ["v3", "v3", "v4", "v5"] {
+            set_profile_env_in(&path, "tool", "default", "TOKEN", value).expect("set");
+        }
+        assert_eq!(values(&path), ["v3", "v4"]);
+        let env = IndexMap::from([("TOKEN".to_string(), "v6".to_string())]);
+        set_profile_env_bulk_in(&path, "tool", "default", &env).expect("bulk");
+        assert_eq!(values(&path), ["v4", "v5"]);
+        let entry = &get_env_history_in(&path, "tool", "default", "TOKEN").unwrap()[1];
+        assert!(entry.replaced_at.ends_with('Z'), "{}", entry.replaced_at);
+
+        set_env_history_limit_in(&path, 1).expect("limit");
+        assert_eq!(values(&path), ["v5"]);
+        set_env_history_limit_in(&path, 0).expect("limit");
+        assert!(values(&path).is_empty());
+        let state = load_state_from_path(&path).expect("load");
+        assert!(
+            state.apps["tool"].profiles["default"]
+                .env_history
+                .is_empty()
+        );
+
+        let err = get_env_history_in(&path, "tool", "missing", "TOKEN").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn list_profiles_marked_flags_only_the_active_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        add_profile_in(&path, "tool", "prod").expect("add");
+        add_profile_in(&path, "tool", "staging").expect("add");
+        set_active_profile_in(&path, "tool", "prod").expect("activate");
+
+        let marked = list_profiles_marked_in(&path, "tool").expect("marked");
+        assert_eq!(
+            marked,
+            [
+                ("default".to_string(), false),
+                ("prod".to_string(), true),
+                ("staging".to_string(), false)
+            ]
+        );
+        assert_eq!(marked.iter().filter(|(_, active)| *active).count(), 1);
+
+        let err = list_profiles_marked_in(&path, "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn parse_command_args_honours_quotes_and_escapes() {
+        assert_eq!(parse_command_args("--flag value"), ["--flag", "value"]);
+        assert_eq!(parse_command_args("  -a\t -b  "), ["-a", "-b"]);
+        assert!(parse_command_args("   ").is_empty());
+        assert_eq!(
+            parse_command_args(r#"--name "two words" --path '/tmp/my dir'"#),
+            ["--name", "two words", "--path", "/tmp/my dir"]
+        );
+        assert_eq!(parse_command_args(r"one\ arg \'x"), ["one arg", "'x"]);
+        assert_eq!(
+            parse_command_args(r#""say \"hi\"" 'a\b' "c\d""#),
+            [r#"say "hi""#, r"a\b", r"c\d"]
+        );
+        assert_eq!(
+            parse_command_args(r#"--msg="a b"c '' """#),
+            ["--msg=a bc", "", ""]
+        );
+        assert_eq!(
+            parse_command_args("'unterminated arg"),
+            ["unterminated arg"]
+        );
+        assert_eq!(parse_command_args("$HOME ~/x"), ["$HOME", "~/x"]);
+    }
+
+    #[test]
+    fn format_command_args_round_trips_through_the_parser() {
+        let args: Vec<String> = ["--flag", "two words", "it's", "", r"back\slash", "--k=v"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let line = format_command_args(&args);
+        assert_eq!(
+            line,
+            r#"--flag 'two words' 'it'\''s' '' 'back\slash' --k=v"#
+        );
+        assert_eq!(parse_command_args(&line), args);
+    }
+
+    #[test]
+    fn list_env_keys_keeps_order_and_reports_missing() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for key in ["ZED", "API_TOKEN", "ALPHA"] {
+            set_profile_env_in(&path, "tool", "default", key, "secret").expect("set");
+        }
+
+        let keys = list_env_keys_in(&path, "tool", "default").expect("keys");
+        assert_eq!(keys, ["ZED", "API_TOKEN", "ALPHA"]);
+
+        let err = list_env_keys_in(&path, "missing", "default").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+        let err = list_env_keys_in(&path, "tool", "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn list_groups_buckets_apps_and_ungrouped_last() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        for name in ["loose", "claude", "aws", "codex", "gcloud"] {
+            register_app_in(&path, name, &format!("{name}-bin")).expect("register");
+        }
+        set_app_group_in(&path, "claude", Some("AI tools")).expect("group");
+        set_app_group_in(&path, "aws", Some("Cloud")).expect("group");
+        set_app_group_in(&path, "codex", Some(" AI tools ")).expect("group");
+        set_app_group_in(&path, "gcloud", Some("Cloud")).expect("group");
+        set_app_group_in(&path, "gcloud", Some("  ")).expect("ungroup");
+
+        let groups = list_groups_in(&path).expect("groups");
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    "AI tools".to_string(),
+                    vec!["claude".to_string(), "codex".to_string()]
+                ),
+                ("Cloud".to_string(), vec!["aws".to_string()]),
+                (
+                    UNGROUPED.to_string(),
+                    vec!["loose".to_string(), "gcloud".to_string()]
+                ),
+            ]
+        );
+
+        let err = set_app_group_in(&path, "missing", Some("Cloud")).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn profile_target_overrides_app_target() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "node", "node").expect("register");
+        add_profile_in(&path, "node", "v18").expect("add");
+        set_profile_target_in(
+            &path,
+            "node",
+            "v18",
+            Some("~/.nvm/versions/node/v18/bin/node"),
+        )
+        .expect("set target");
+
+        let state = load_state_from_path(&path).expect("load");
+        let app = &state.apps["node"];
+        assert_eq!(
+            app.target_list_for(app.profiles.get("v18"), InstallMode::User),
+            ["~/.nvm/versions/node/v18/bin/node"]
+        );
+        assert_eq!(
+            app.target_list_for(app.profiles.get("default"), InstallMode::User),
+            ["node"]
+        );
+        assert_eq!(app.target_list_for(None, InstallMode::User), ["node"]);
+
+        set_profile_target_in(&path, "node", "v18", None).expect("clear target");
+        let state = load_state_from_path(&path).expect("load");
+        let app = &state.apps["node"];
+        assert_eq!(
+            app.target_list_for(app.profiles.get("v18"), InstallMode::User),
+            ["node"]
+        );
+
+        let err = set_profile_target_in(&path, "node", "missing", Some("node")).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn set_active_profile_all_skips_apps_without_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        for name in ["api", "web", "cli"] {
+            register_app_in(&path, name, &format!("{name}-bin")).expect("register");
+        }
+        add_profile_in(&path, "api", "staging").expect("add");
+        add_profile_in(&path, "cli", "staging").expect("add");
+
+        let results = set_active_profile_all_in(&path, "staging").expect("switch");
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["api", "cli"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.apps["api"].active_profile.as_deref(), Some("staging"));
+        assert_eq!(state.apps["cli"].active_profile.as_deref(), Some("staging"));
+        assert_eq!(state.apps["web"].active_profile.as_deref(), Some("default"));
+
+        assert!(
+            set_active_profile_all_in(&path, "nowhere")
+                .expect("switch")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn set_app_disabled_toggles_flag() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        set_app_disabled_in(&path, "tool", true).expect("disable");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps.get("tool").expect("app").disabled);
+
+        set_app_disabled_in(&path, "tool", false).expect("enable");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(!state.apps.get("tool").expect("app").disabled);
+        let raw = std::fs::read_to_string(&path).expect("read");
+        assert!(!raw.contains("disabled"), "enabled apps omit the flag");
+
+        let err = set_app_disabled_in(&path, "missing", true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn set_profile_dangerous_persists_per_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        add_profile_in(&path, "tool", "prod").expect("add");
+
+        set_profile_dangerous_in(&path, "tool", "prod", true).expect("mark");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps["tool"].profiles["prod"].dangerous);
+        assert!(!state.apps["tool"].profiles["default"].dangerous);
+
+        set_profile_dangerous_in(&path, "tool", "prod", false).expect("unmark");
+        assert!(
+            !std::fs::read_to_string(&path)
+                .expect("read")
+                .contains("dangerous")
+        );
+
+        let err = set_profile_dangerous_in(&path, "tool", "missing", true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+        let err = set_profile_dangerous_in(&path, "missing", "prod", true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn profile_includes_layer_in_order_beneath_the_profile() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for profile in ["base", "eu", "work"] {
+            add_profile_in(&path, "tool", profile).expect("add");
+        }
+        set_profile_env_in(&path, "tool", "base", "REGION", "us").expect("set");
+        set_profile_env_in(&path, "tool", "base", "ENDPOINT", "api").expect("set");
+        set_profile_env_in(&path, "tool", "eu", "REGION", "eu").expect("set");
+        set_profile_env_in(&path, "tool", "work", "ENDPOINT", "work-api").expect("set");
+        set_profile_env_in(&path, "tool", "work", "TEAM", "infra").expect("set");
+        add_profile_include_in(&path, "tool", "eu", "base").expect("include");
+        add_profile_include_in(&path, "tool", "work", "eu").expect("include");
+        add_profile_include_in(&path, "tool", "work", "eu").expect("include twice");
+
+        let state = load_state_from_path(&path).expect("load");
+        let app = &state.apps["tool"];
+        assert_eq!(app.profiles["work"].include, ["eu"]);
+        let env = app.layered_env("work").expect("env");
+        let env: Vec<(&str, &str)> = env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            env,
+            [
+                ("REGION", "eu"),
+                ("ENDPOINT", "work-api"),
+                ("TEAM", "infra")
+            ]
+        );
+
+        remove_profile_include_in(&path, "tool", "eu", "base").expect("remove");
+        let state = load_state_from_path(&path).expect("load");
+        let eu = state.apps["tool"].layered_env("eu").expect("env");
+        assert_eq!(eu.keys().collect::<Vec<_>>(), ["REGION"]);
+        let err = remove_profile_include_in(&path, "tool", "eu", "base").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+
+        remove_profile_in(&path, "tool", "eu").expect("remove profile");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps["tool"].profiles["work"].include.is_empty());
+    }
+
+    #[test]
+    fn profile_include_cycles_are_rejected() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for profile in ["a", "b", "c"] {
+            add_profile_in(&path, "tool", profile).expect("add");
+        }
+        add_profile_include_in(&path, "tool", "a", "b").expect("include");
+        add_profile_include_in(&path, "tool", "b", "c").expect("include");
+
+        let err = add_profile_include_in(&path, "tool", "c", "a").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("c -> a -> b -> c"), "{}", err.message);
+        let err = add_profile_include_in(&path, "tool", "a", "a").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps["tool"].profiles["c"].include.is_empty());
+        assert_eq!(state.apps["tool"].profiles["a"].include, ["b"]);
+
+        let err = add_profile_include_in(&path, "tool", "a", "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn profile_includes_stop_past_the_depth_limit() {
+        let mut app = AppConfig::default();
+        for level in 0..=MAX_INCLUDE_DEPTH + 1 {
+            let profile = ProfileConfig {
+                include: vec![format!("p{}", level + 1)],
+                ..ProfileConfig::default()
+            };
+            app.profiles.insert(format!("p{level}"), profile);
+        }
+        app.profiles.insert(
+            format!("p{}", MAX_INCLUDE_DEPTH + 2),
+            ProfileConfig::default(),
+        );
+        let err = app.layered_env("p0").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("deeper than"), "{}", err.message);
+        assert!(app.layered_env("p2").is_ok());
+    }
+
+    #[test]
+    fn removing_the_only_profile_needs_force() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "KEY", "kept").expect("set");
+
+        let err = remove_profile_in(&path, "tool", "default").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("only profile"), "{}", err.message);
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert_eq!(app.profiles["default"].env["KEY"], "kept");
+
+        let err = remove_profile_forced_in(&path, "tool", "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+        remove_profile_forced_in(&path, "tool", "default").expect("forced");
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert!(app.profiles.is_empty());
+        assert_eq!(app.active_profile, None);
+    }
+
+    #[test]
+    fn cached_target_hits_until_the_binary_or_targets_change() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let binary = dir.path().join("tool-bin");
+        std::fs::write(&binary, b"#!/bin/sh\n").expect("binary");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))
+                .expect("chmod");
+        }
+        let target = binary.to_string_lossy().to_string();
+        register_app_in(&path, "tool", &target).expect("register");
+
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert_eq!(
+            crate::cached_target(app, &app.target_list(InstallMode::User)),
+            None
+        );
+
+        let cached = set_target_cache_in(&path, "tool", true).expect("enable");
+        assert_eq!(cached.as_deref(), Some(binary.as_path()));
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert_eq!(
+            crate::cached_target(app, &app.target_list(InstallMode::User)).as_deref(),
+            Some(binary.as_path())
+        );
+        assert_eq!(crate::cached_target(app, &["other".to_string()]), None);
+
+        std::fs::remove_file(&binary).expect("remove");
+        assert_eq!(
+            crate::cached_target(app, &app.target_list(InstallMode::User)),
+            None
+        );
+        let err = refresh_resolved_target_in(&path, "tool").unwrap_err();
+        assert_eq!(err.code, ErrorCode::TargetNotFound);
+        let app = &load_state_from_path(&path).expect("load").apps["tool"];
+        assert!(app.cache_target);
+        assert_eq!(app.resolved_target, None);
+
+        set_target_cache_in(&path, "tool", false).expect("disable");
+        let err = refresh_resolved_target_in(&path, "tool").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(
+            !std::fs::read_to_string(&path)
+                .expect("read")
+                .contains("cache_target")
+        );
+    }
+
+    #[test]
+    fn store_resolved_target_only_caches_the_apps_own_targets() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let targets = vec!["tool-bin".to_string()];
+        let binary = dir.path().join("tool-bin");
+
+        assert!(!store_resolved_target_in(&path, "tool", &targets, &binary).expect("off"));
+        set_target_cache_in(&path, "tool", true).expect("enable");
+        let other = vec!["other-bin".to_string()];
+        assert!(!store_resolved_target_in(&path, "tool", &other, &binary).expect("override"));
+        assert_eq!(
+            load_state_from_path(&path).expect("load").apps["tool"].resolved_target,
+            None
+        );
+
+        assert!(store_resolved_target_in(&path, "tool", &targets, &binary).expect("store"));
+        assert!(!store_resolved_target_in(&path, "tool", &targets, &binary).expect("same"));
+        let cached = load_state_from_path(&path).expect("load").apps["tool"]
+            .resolved_target
+            .clone()
+            .expect("cached");
+        assert_eq!(cached.targets, targets);
+        assert_eq!(cached.path, binary.to_string_lossy());
+
+        let err = store_resolved_target_in(&path, "missing", &targets, &binary).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn new_profiles_are_activated_only_when_asked() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let active = || {
+            load_state_from_path(&path).expect("load").apps["tool"]
+                .active_profile
+                .clone()
+        };
+
+        add_profile_in(&path, "tool", "plain").expect("add");
+        assert_eq!(active().as_deref(), Some("default"));
+        add_profile_and_activate_in(&path, "tool", "work").expect("add");
+        assert_eq!(active().as_deref(), Some("work"));
+
+        clone_profile_in(&path, "tool", "work", "copy").expect("clone");
+        assert_eq!(active().as_deref(), Some("work"));
+        clone_profile_and_activate_in(&path, "tool", "work", "switched", CloneOptions::default())
+            .expect("clone");
+        assert_eq!(active().as_deref(), Some("switched"));
+    }
+
+    #[test]
+    fn set_app_confirm_is_saved_only_when_on() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        set_app_confirm_in(&path, "tool", true).expect("confirm");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps.get("tool").expect("app").confirm_before_run);
+        assert!(
+            std::fs::read_to_string(&path)
+                .expect("read")
+                .contains("\"confirm_before_run\": true")
+        );
+
+        set_app_confirm_in(&path, "tool", false).expect("unconfirm");
+        assert!(
+            !std::fs::read_to_string(&path)
+                .expect("read")
+                .contains("confirm_before_run")
+        );
+
+        let err = set_app_confirm_in(&path, "missing", true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn list_apps_detailed_matches_state() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let install_dir = dir.path().join("bin");
+        std::fs::create_dir_all(&install_dir).expect("bin dir");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        add_profile_in(&path, "tool", "work").expect("add");
+        set_active_profile_in(&path, "tool", "work").expect("active");
+        register_app_in(&path, "other", "other-bin").expect("register");
+
+        let mut state = load_state_from_path(&path).expect("load");
+        state.apps.get_mut("tool").expect("app").install_path =
+            Some(install_dir.to_string_lossy().to_string());
+        crate::save_state_to_path(&path, &state).expect("save");
+        let launcher = install_dir.join(crate::launcher_file_name());
+        std::fs::write(&launcher, b"launcher").expect("launcher");
+        crate::install_shim_in("tool", &install_dir, &launcher).expect("shim");
+
+        let summaries = list_apps_detailed_in(&path, InstallMode::User).expect("list");
+        assert_eq!(
+            summaries[0],
+            AppSummary {
+                name: "tool".to_string(),
+                target_binary: "tool-bin".to_string(),
+                active_profile: Some("work".to_string()),
+                profile_count: 2,
+                installed: true,
+            }
+        );
+        assert_eq!(summaries[1].name, "other");
+        assert_eq!(summaries[1].profile_count, 1);
+        assert_eq!(summaries[1].active_profile.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn clone_profile_with_respects_options() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_env_history_limit_in(&path, 5).expect("limit");
+        set_profile_env_in(&path, "tool", "default", "KEY", "OLD").expect("set");
+        set_profile_env_in(&path, "tool", "default", "KEY", "VALUE").expect("set");
+        set_command_args_in(&path, "tool", "default", vec!["--flag".to_string()]).expect("args");
+        set_profile_notes_in(&path, "tool", "default", Some("uses prod creds")).expect("notes");
+        let mut state = load_state_from_path(&path).expect("load");
+        let default = state.apps["tool"].profiles.get_mut("default").unwrap();
+        default
+            .extra
+            .insert("_comment".to_string(), "hand-written".into());
+        crate::save_state_to_path(&path, &state).expect("save");
+
+        for bits in 0..8u8 {
+            let (copy_env, copy_args, copy_comments) =
+                (bits & 1 != 0, bits & 2 != 0, bits & 4 != 0);
+            let to = format!("copy-{copy_env}-{copy_args}-{copy_comments}");
+            clone_profile_with_in(
+                &path,
+                "tool",
+                "default",
+                &to,
+                CloneOptions {
+                    copy_env,
+                    copy_args,
+                    copy_comments,
+                },
+            )
+            .expect("clone");
+
+            let state = load_state_from_path(&path).expect("load");
+            let profile = state
+                .apps
+                .get("tool")
+                .and_then(|app| app.profiles.get(&to))
+                .expect("cloned profile");
+            assert_eq!(profile.env.contains_key("KEY"), copy_env, "{to}");
+            assert_eq!(profile.env_history.contains_key("KEY"), copy_env, "{to}");
+            assert_eq!(!profile.command_args.is_empty(), copy_args, "{to}");
+            assert_eq!(profile.notes.is_some(), copy_comments, "{to}");
+            assert_eq!(
+                profile.extra.contains_key("_comment"),
+                copy_comments,
+                "{to}"
+            );
+        }
     }
 
     #[test]
@@ -347,4 +3451,89 @@ mod tests {
                 .is_none()
         );
     }
+
+    #[test]
+    fn remove_profile_env_bulk_skips_missing_keys() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for key in ["A", "B", "C"] {
+            set_profile_env_in(&path, "tool", "default", key, "1").expect("set");
+        }
+
+        let keys = ["A", "MISSING", "C", "A"].map(String::from);
+        let removed = remove_profile_env_bulk_in(&path, "tool", "default", &keys).expect("bulk");
+        assert_eq!(removed, 2);
+        let state = load_state_from_path(&path).expect("load");
+        let env = &state.apps["tool"].profiles["default"].env;
+        assert_eq!(env.keys().collect::<Vec<_>>(), ["B"]);
+
+        let err = remove_profile_env_bulk_in(&path, "tool", "missing", &keys).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+    }
+
+    #[test]
+    fn install_uses_app_install_path() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let custom_dir = dir.path().join("custom-bin");
+        let launcher = dir.path().join("launcher");
+        std::fs::write(&launcher, b"binary").expect("launcher");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        set_app_install_path_in(&path, "tool", Some(custom_dir.clone())).expect("set path");
+        let state = load_state_from_path(&path).expect("load");
+        let shim = crate::install_shim_for_state(&state, "tool", InstallMode::User, &launcher)
+            .expect("install");
+        assert_eq!(shim, crate::shim_path_in(&custom_dir, "tool"));
+        assert!(shim.exists());
+
+        set_app_install_path_in(&path, "tool", None).expect("clear path");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps["tool"].install_path.is_none());
+    }
+
+    fn env_keys(path: &Path) -> Vec<String> {
+        let state = load_state_from_path(path).expect("load");
+        state.apps["tool"].profiles["default"]
+            .env
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn append_order_keeps_insertion_order() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for key in ["ZED", "ALPHA", "MID"] {
+            set_profile_env_in(&path, "tool", "default", key, "1").expect("set");
+        }
+        assert_eq!(env_keys(&path), ["ZED", "ALPHA", "MID"]);
+
+        sort_profile_env_in(&path, "tool", "default").expect("sort");
+        assert_eq!(env_keys(&path), ["ALPHA", "MID", "ZED"]);
+        // The one-shot sort does not change where new keys go.
+        set_profile_env_in(&path, "tool", "default", "BETA", "1").expect("set");
+        assert_eq!(env_keys(&path), ["ALPHA", "MID", "ZED", "BETA"]);
+    }
+
+    #[test]
+    fn sorted_order_sorts_existing_and_new_keys() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for key in ["ZED", "ALPHA"] {
+            set_profile_env_in(&path, "tool", "default", key, "1").expect("set");
+        }
+
+        set_env_order_in(&path, "tool", EnvOrder::Sorted).expect("order");
+        assert_eq!(env_keys(&path), ["ALPHA", "ZED"]);
+        set_profile_env_in(&path, "tool", "default", "MID", "1").expect("set");
+        assert_eq!(env_keys(&path), ["ALPHA", "MID", "ZED"]);
+
+        let raw = std::fs::read_to_string(&path).expect("read");
+        assert!(raw.contains("\"env_order\": \"sorted\""));
+    }
 }
@@ -0,0 +1,426 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::{AppConfig, ProfileConfig, State};
+
+/// What changes when going from one state to another, e.g. the current
+/// config and the result of an import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    pub added_apps: Vec<String>,
+    pub removed_apps: Vec<String>,
+    pub changed_apps: Vec<AppDiff>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AppDiff {
+    pub name: String,
+    /// App-level settings that differ, by field name (`target_binary`, ...).
+    pub changed_fields: Vec<String>,
+    pub added_profiles: Vec<String>,
+    pub removed_profiles: Vec<String>,
+    pub changed_profiles: Vec<ProfileDiff>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProfileDiff {
+    pub name: String,
+    /// Profile settings other than `env` that differ, by field name.
+    pub changed_fields: Vec<String>,
+    pub added_env: Vec<String>,
+    pub removed_env: Vec<String>,
+    pub changed_env: Vec<EnvChange>,
+}
+
+/// A changed env value. Values of secret-looking keys or values are
+/// [`crate::REDACTED`], so a diff is safe to print or send to the GUI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvChange {
+    pub key: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_apps.is_empty() && self.removed_apps.is_empty() && self.changed_apps.is_empty()
+    }
+}
+
+impl AppDiff {
+    fn is_empty(&self) -> bool {
+        self.changed_fields.is_empty()
+            && self.added_profiles.is_empty()
+            && self.removed_profiles.is_empty()
+            && self.changed_profiles.is_empty()
+    }
+}
+
+impl ProfileDiff {
+    fn is_empty(&self) -> bool {
+        self.changed_fields.is_empty()
+            && self.added_env.is_empty()
+            && self.removed_env.is_empty()
+            && self.changed_env.is_empty()
+    }
+}
+
+/// Compares two states. `installed` and the cached `resolved_target` are
+/// ignored because they describe the local machine rather than the
+/// configuration.
+pub fn diff_states(before: &State, after: &State) -> StateDiff {
+    let (added_apps, removed_apps) = added_removed(&before.apps, &after.apps);
+    let changed_apps = before
+        .apps
+        .iter()
+        .filter_map(|(name, old)| {
+            let new = after.apps.get(name)?;
+            let diff = diff_app(name, old, new);
+            (!diff.is_empty()).then_some(diff)
+        })
+        .collect();
+    StateDiff {
+        added_apps,
+        removed_apps,
+        changed_apps,
+    }
+}
+
+fn diff_app(name: &str, before: &AppConfig, after: &AppConfig) -> AppDiff {
+    let mut changed_fields = Vec::new();
+    let mut field = |field: &str, changed: bool| {
+        if changed {
+            changed_fields.push(field.to_string());
+        }
+    };
+    field("target_binary", before.target_binary != after.target_binary);
+    field(
+        "target_candidates",
+        before.target_candidates != after.target_candidates,
+    );
+    field("target_root", before.target_root != after.target_root);
+    field(
+        "relative_targets",
+        before.relative_targets != after.relative_targets,
+    );
+    field("path_prepend", before.path_prepend != after.path_prepend);
+    field("cache_target", before.cache_target != after.cache_target);
+    field("install_path", before.install_path != after.install_path);
+    field("aliases", before.aliases != after.aliases);
+    field("created_at", before.created_at != after.created_at);
+    field(
+        "active_profile",
+        before.active_profile != after.active_profile,
+    );
+    field(
+        "default_profile",
+        before.default_profile != after.default_profile,
+    );
+    field("disabled", before.disabled != after.disabled);
+    field(
+        "confirm_before_run",
+        before.confirm_before_run != after.confirm_before_run,
+    );
+    field("group", before.group != after.group);
+    field("env_order", before.env_order != after.env_order);
+    field(
+        "allowed_profiles",
+        before.allowed_profiles != after.allowed_profiles,
+    );
+    field("extra", before.extra != after.extra);
+
+    let (added_profiles, removed_profiles) = added_removed(&before.profiles, &after.profiles);
+    let changed_profiles = before
+        .profiles
+        .iter()
+        .filter_map(|(profile, old)| {
+            let new = after.profiles.get(profile)?;
+            let diff = diff_profile(profile, old, new);
+            (!diff.is_empty()).then_some(diff)
+        })
+        .collect();
+    AppDiff {
+        name: name.to_string(),
+        changed_fields,
+        added_profiles,
+        removed_profiles,
+        changed_profiles,
+    }
+}
+
+fn diff_profile(name: &str, before: &ProfileConfig, after: &ProfileConfig) -> ProfileDiff {
+    let mut changed_fields = Vec::new();
+    let mut field = |field: &str, changed: bool| {
+        if changed {
+            changed_fields.push(field.to_string());
+        }
+    };
+    field("target_binary", before.target_binary != after.target_binary);
+    field("command_args", before.command_args != after.command_args);
+    field("stdout_file", before.stdout_file != after.stdout_file);
+    field("stderr_file", before.stderr_file != after.stderr_file);
+    field("env_file", before.env_file != after.env_file);
+    field("notes", before.notes != after.notes);
+    field("dangerous", before.dangerous != after.dangerous);
+    field("include", before.include != after.include);
+    field("isolated", before.isolated != after.isolated);
+    field("inherit_env", before.inherit_env != after.inherit_env);
+    field("env_history", before.env_history != after.env_history);
+    field("extra", before.extra != after.extra);
+
+    let (added_env, removed_env) = added_removed(&before.env, &after.env);
+    let changed_env = before
+        .env
+        .iter()
+        .filter_map(|(key, old)| {
+            let new = after.env.get(key)?;
+            (old != new).then(|| {
+                let secret = crate::is_secret_key(key)
+                    || crate::lint::looks_like_secret(old)
+                    || crate::lint::looks_like_secret(new);
+                let shown = |value: &String| {
+                    if secret {
+                        crate::REDACTED.to_string()
+                    } else {
+                        value.clone()
+                    }
+                };
+                EnvChange {
+                    key: key.clone(),
+                    before: shown(old),
+                    after: shown(new),
+                }
+            })
+        })
+        .collect();
+    ProfileDiff {
+        name: name.to_string(),
+        changed_fields,
+        added_env,
+        removed_env,
+        changed_env,
+    }
+}
+
+fn added_removed<V>(
+    before: &IndexMap<String, V>,
+    after: &IndexMap<String, V>,
+) -> (Vec<String>, Vec<String>) {
+    let added = after
+        .keys()
+        .filter(|key| !before.contains_key(*key))
+        .cloned()
+        .collect();
+    let removed = before
+        .keys()
+        .filter(|key| !after.contains_key(*key))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// One line per change, `+` added, `-` removed, `~` changed, indented by
+/// app and profile.
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for name in &self.added_apps {
+            writeln!(f, "+ app {name}")?;
+        }
+        for name in &self.removed_apps {
+            writeln!(f, "- app {name}")?;
+        }
+        for app in &self.changed_apps {
+            writeln!(f, "~ app {}", app.name)?;
+            for field in &app.changed_fields {
+                writeln!(f, "    ~ {field}")?;
+            }
+            for profile in &app.added_profiles {
+                writeln!(f, "    + profile {profile}")?;
+            }
+            for profile in &app.removed_profiles {
+                writeln!(f, "    - profile {profile}")?;
+            }
+            for profile in &app.changed_profiles {
+                writeln!(f, "    ~ profile {}", profile.name)?;
+                for field in &profile.changed_fields {
+                    writeln!(f, "        ~ {field}")?;
+                }
+                for key in &profile.added_env {
+                    writeln!(f, "        + {key}")?;
+                }
+                for key in &profile.removed_env {
+                    writeln!(f, "        - {key}")?;
+                }
+                for change in &profile.changed_env {
+                    writeln!(
+                        f,
+                        "        ~ {}: {} -> {}",
+                        change.key, change.before, change.after
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(target: &str, profiles: &[(&str, &[(&str, &str)])]) -> AppConfig {
+        let mut app = AppConfig {
+            target_binary: target.to_string(),
+            ..AppConfig::default()
+        };
+        for (name, env) in profiles {
+            let mut profile = ProfileConfig::default();
+            for (key, value) in env.iter() {
+                profile.env.insert(key.to_string(), value.to_string());
+            }
+            app.profiles.insert(name.to_string(), profile);
+        }
+        app
+    }
+
+    #[test]
+    fn identical_states_have_no_diff() {
+        let mut state = State::default();
+        state
+            .apps
+            .insert("tool".to_string(), app("tool-bin", &[("default", &[])]));
+        let mut installed = state.clone();
+        let tool = installed.apps.get_mut("tool").expect("app");
+        tool.installed = true;
+        tool.resolved_target = Some(crate::ResolvedTarget {
+            targets: vec!["tool-bin".to_string()],
+            path: "/opt/tool/tool-bin".to_string(),
+        });
+
+        let diff = diff_states(&state, &installed);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No changes\n");
+
+        installed.apps["tool"].cache_target = true;
+        let diff = diff_states(&state, &installed);
+        assert_eq!(diff.changed_apps[0].changed_fields, ["cache_target"]);
+    }
+
+    #[test]
+    fn diff_reports_env_history_changes() {
+        let mut before = State::default();
+        before
+            .apps
+            .insert("tool".to_string(), app("tool-bin", &[("default", &[])]));
+        let mut after = before.clone();
+        after.apps["tool"].profiles["default"].env_history.insert(
+            "KEY".to_string(),
+            vec![crate::EnvHistoryEntry {
+                value: "old".to_string(),
+                replaced_at: "2026-01-01T00:00:00Z".to_string(),
+            }],
+        );
+
+        let diff = diff_states(&before, &after);
+        assert_eq!(
+            diff.changed_apps[0].changed_profiles[0].changed_fields,
+            ["env_history"]
+        );
+    }
+
+    #[test]
+    fn diff_reports_added_app_removed_profile_and_changed_env() {
+        let mut before = State::default();
+        before.apps.insert(
+            "tool".to_string(),
+            app(
+                "tool-bin",
+                &[
+                    ("default", &[("MODE", "old"), ("GONE", "x")]),
+                    ("work", &[]),
+                ],
+            ),
+        );
+        let mut after = State::default();
+        after.apps.insert(
+            "tool".to_string(),
+            app(
+                "tool-bin",
+                &[("default", &[("MODE", "new"), ("ADDED", "y")])],
+            ),
+        );
+        after
+            .apps
+            .insert("other".to_string(), app("other-bin", &[]));
+
+        let diff = diff_states(&before, &after);
+        assert_eq!(diff.added_apps, ["other"]);
+        assert!(diff.removed_apps.is_empty());
+        let tool = &diff.changed_apps[0];
+        assert_eq!(tool.name, "tool");
+        assert!(tool.changed_fields.is_empty());
+        assert_eq!(tool.removed_profiles, ["work"]);
+        let default = &tool.changed_profiles[0];
+        assert_eq!(default.added_env, ["ADDED"]);
+        assert_eq!(default.removed_env, ["GONE"]);
+        assert_eq!(
+            default.changed_env,
+            [EnvChange {
+                key: "MODE".to_string(),
+                before: "old".to_string(),
+                after: "new".to_string(),
+            }]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "+ app other\n~ app tool\n    - profile work\n    ~ profile default\n        + ADDED\n        - GONE\n        ~ MODE: old -> new\n"
+        );
+
+        let reverse = diff_states(&after, &before);
+        assert_eq!(reverse.removed_apps, ["other"]);
+        assert_eq!(reverse.changed_apps[0].added_profiles, ["work"]);
+    }
+
+    #[test]
+    fn diff_masks_secret_values() {
+        let token = "sk-abcdefghijklmnopqrstuvwxyz123456";
+        let mut before = State::default();
+        before.apps.insert(
+            "tool".to_string(),
+            app(
+                "tool-bin",
+                &[(
+                    "default",
+                    &[("API_TOKEN", "old"), ("AUTH", "plain"), ("MODE", "a")],
+                )],
+            ),
+        );
+        let mut after = State::default();
+        after.apps.insert(
+            "tool".to_string(),
+            app(
+                "tool-bin",
+                &[(
+                    "default",
+                    &[("API_TOKEN", "new"), ("AUTH", token), ("MODE", "b")],
+                )],
+            ),
+        );
+
+        let diff = diff_states(&before, &after);
+        let changes = &diff.changed_apps[0].changed_profiles[0].changed_env;
+        assert_eq!(changes[0].before, crate::REDACTED);
+        assert_eq!(changes[0].after, crate::REDACTED);
+        assert_eq!(changes[1].after, crate::REDACTED);
+        assert_eq!(changes[2].after, "b");
+        let shown = diff.to_string();
+        assert!(shown.contains("~ API_TOKEN: *** -> ***"));
+        assert!(shown.contains("~ AUTH: *** -> ***"));
+        assert!(!shown.contains(token) && !shown.contains("old"));
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    AppConfig, ChecksumStatus, LintKind, default_state_path, detect_platform, is_shim_installed_in,
+    launcher_file_name_for, launcher_name_in, lint_state, load_state_checked,
+    resolve_target_candidates_from, shim_path_in,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    /// Something is broken: a launch or an installed shim will fail.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{label}")
+    }
+}
+
+/// What a [`Diagnostic`] is about, stable across message rewording so a UI
+/// can map it to a fix, e.g. `path_not_configured` to `configure_user_path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticCode {
+    /// The state path can't be determined or the file can't be loaded.
+    StateUnreadable,
+    /// The user-mode install directory can't be determined.
+    InstallDirUnknown,
+    NoStateFile,
+    /// The checksum doesn't match, so the file was edited by hand.
+    EditedOutside,
+    LauncherInstalled,
+    LauncherMissing,
+    /// The install directory is not on `PATH`.
+    PathNotConfigured,
+    /// The shim is a symlink to a launcher that no longer exists.
+    BrokenShim,
+    /// The app is marked installed but has no shim.
+    ShimMissing,
+    /// A file that isn't an envhub shim sits at the shim's path.
+    NotAShim,
+    /// The shim runs an older launcher.
+    StaleShim,
+    TargetUnresolved,
+    EmptyProfiles,
+    NoActiveProfile,
+    DuplicateTarget,
+    SelfTarget,
+    UnmarkedSecret,
+}
+
+impl From<LintKind> for DiagnosticCode {
+    fn from(kind: LintKind) -> Self {
+        match kind {
+            LintKind::EmptyProfiles => DiagnosticCode::EmptyProfiles,
+            LintKind::NoActiveProfile => DiagnosticCode::NoActiveProfile,
+            LintKind::DuplicateTarget => DiagnosticCode::DuplicateTarget,
+            LintKind::SelfTarget => DiagnosticCode::SelfTarget,
+            LintKind::UnmarkedSecret => DiagnosticCode::UnmarkedSecret,
+        }
+    }
+}
+
+/// One finding of [`diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// The app it concerns, or `None` for machine-wide findings.
+    pub app: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.app {
+            Some(app) => write!(f, "[{}] {app}: {}", self.severity, self.message),
+            None => write!(f, "[{}] {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Checks the state file, the user-mode install and every registered app,
+/// most severe findings first. Never fails: problems reaching the state are
+/// themselves reported.
+pub fn diagnose() -> Vec<Diagnostic> {
+    let path = match default_state_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return vec![machine(
+                Severity::Error,
+                DiagnosticCode::StateUnreadable,
+                err.message,
+            )];
+        }
+    };
+    let mode = crate::default_install_mode_in(&path).unwrap_or_default();
+    match detect_platform(mode) {
+        Ok(platform) => diagnose_in(&path, &platform.install_dir),
+        Err(err) => vec![machine(
+            Severity::Error,
+            DiagnosticCode::InstallDirUnknown,
+            err.message,
+        )],
+    }
+}
+
+pub fn diagnose_in(path: &Path, install_dir: &Path) -> Vec<Diagnostic> {
+    let mut found = Vec::new();
+    let (state, checksum) = match load_state_checked(path) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            return vec![machine(
+                Severity::Error,
+                DiagnosticCode::StateUnreadable,
+                format!("Cannot load {}: {}", path.display(), err.message),
+            )];
+        }
+    };
+    if !path.exists() {
+        found.push(machine(
+            Severity::Info,
+            DiagnosticCode::NoStateFile,
+            format!("No state file at {} yet", path.display()),
+        ));
+    } else if checksum == ChecksumStatus::Mismatch {
+        found.push(machine(
+            Severity::Warning,
+            DiagnosticCode::EditedOutside,
+            format!("{} was edited outside envhub", path.display()),
+        ));
+    }
+
+    let launcher = install_dir.join(launcher_file_name_for(&launcher_name_in(&state)));
+    if launcher.exists() {
+        found.push(machine(
+            Severity::Info,
+            DiagnosticCode::LauncherInstalled,
+            format!("Launcher is installed at {}", launcher.display()),
+        ));
+    } else {
+        found.push(machine(
+            Severity::Warning,
+            DiagnosticCode::LauncherMissing,
+            format!("Launcher is not installed at {}", launcher.display()),
+        ));
+    }
+    let on_path = std::env::var_os("PATH")
+        .is_some_and(|paths| crate::install::dir_on_path(install_dir, &paths));
+    if !on_path {
+        found.push(machine(
+            Severity::Warning,
+            DiagnosticCode::PathNotConfigured,
+            format!("{} is not on PATH", install_dir.display()),
+        ));
+    }
+
+    let self_path = std::env::current_exe().unwrap_or_default();
+    for (name, app) in &state.apps {
+        let dir = app
+            .install_path
+            .as_deref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| install_dir.to_path_buf());
+        check_shim(&mut found, name, app, &dir, &launcher);
+        if !app.disabled {
+            let targets = app.target_list_for(
+                app.resolve_active_profile().map(|(_, p)| p),
+                state.default_install_mode,
+            );
+            if let Err(err) = resolve_target_candidates_from(&targets, &self_path) {
+                found.push(for_app(
+                    Severity::Error,
+                    DiagnosticCode::TargetUnresolved,
+                    name,
+                    err.message,
+                ));
+            }
+        }
+    }
+
+    for lint in lint_state(&state) {
+        let severity = match lint.kind {
+            LintKind::EmptyProfiles | LintKind::DuplicateTarget => Severity::Info,
+            LintKind::NoActiveProfile | LintKind::SelfTarget | LintKind::UnmarkedSecret => {
+                Severity::Warning
+            }
+        };
+        found.push(Diagnostic {
+            severity,
+            code: lint.kind.into(),
+            app: Some(lint.app),
+            message: lint.message,
+        });
+    }
+    found.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity));
+    found
+}
+
+fn check_shim(
+    found: &mut Vec<Diagnostic>,
+    name: &str,
+    app: &AppConfig,
+    dir: &Path,
+    launcher: &Path,
+) {
+    let shim = shim_path_in(dir, name);
+    let is_link = fs::symlink_metadata(&shim).is_ok_and(|meta| meta.file_type().is_symlink());
+    if is_link && !shim.exists() {
+        let target = fs::read_link(&shim).unwrap_or_default();
+        found.push(for_app(
+            Severity::Error,
+            DiagnosticCode::BrokenShim,
+            name,
+            format!(
+                "shim {} points to missing {}",
+                shim.display(),
+                target.display()
+            ),
+        ));
+    } else if !shim.exists() {
+        if app.installed {
+            found.push(for_app(
+                Severity::Error,
+                DiagnosticCode::ShimMissing,
+                name,
+                format!("marked installed, but {} is missing", shim.display()),
+            ));
+        }
+    } else if !is_shim_installed_in(dir, name) {
+        found.push(for_app(
+            Severity::Warning,
+            DiagnosticCode::NotAShim,
+            name,
+            format!("{} is not an envhub shim", shim.display()),
+        ));
+    } else if crate::install::is_stale_shim(&shim, launcher) {
+        found.push(for_app(
+            Severity::Warning,
+            DiagnosticCode::StaleShim,
+            name,
+            format!("{} uses an older launcher; reinstall it", shim.display()),
+        ));
+    }
+}
+
+fn machine(severity: Severity, code: DiagnosticCode, message: String) -> Diagnostic {
+    Diagnostic {
+        severity,
+        code,
+        app: None,
+        message,
+    }
+}
+
+fn for_app(severity: Severity, code: DiagnosticCode, app: &str, message: String) -> Diagnostic {
+    Diagnostic {
+        severity,
+        code,
+        app: Some(app.to_string()),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{launcher_file_name, register_app_in, save_state_to_path};
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn diagnose_reports_broken_shims_and_missing_targets() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let install_dir = dir.path().join("bin");
+        fs::create_dir_all(&install_dir).expect("bin");
+        register_app_in(&path, "flagged", "sh").expect("register");
+        register_app_in(&path, "broken", "sh").expect("register");
+        register_app_in(&path, "gone", "envhub-surely-missing-target").expect("register");
+        let mut state = crate::load_state_from_path(&path).expect("load");
+        state.apps.get_mut("flagged").unwrap().installed = true;
+        save_state_to_path(&path, &state).expect("save");
+        std::os::unix::fs::symlink(dir.path().join("old-launcher"), install_dir.join("broken"))
+            .expect("symlink");
+
+        let found = diagnose_in(&path, &install_dir);
+        let errors: Vec<String> = found
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors[0].starts_with("[error] flagged: marked installed"));
+        assert!(errors[1].starts_with("[error] broken: shim "));
+        assert!(errors[1].ends_with("old-launcher"));
+        assert!(errors[2].starts_with("[error] gone: "));
+        assert!(
+            found
+                .windows(2)
+                .all(|pair| pair[0].severity >= pair[1].severity)
+        );
+        assert!(found.iter().any(|diagnostic| {
+            diagnostic.app.is_none() && diagnostic.message.contains("Launcher is not installed")
+        }));
+        let codes: Vec<DiagnosticCode> = found.iter().map(|diagnostic| diagnostic.code).collect();
+        assert!(codes.starts_with(&[
+            DiagnosticCode::ShimMissing,
+            DiagnosticCode::BrokenShim,
+            DiagnosticCode::TargetUnresolved
+        ]));
+        assert!(codes.contains(&DiagnosticCode::LauncherMissing));
+    }
+
+    #[test]
+    fn diagnose_reports_the_launcher_status_with_a_code() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let install_dir = dir.path().join("bin");
+        fs::create_dir_all(&install_dir).expect("bin");
+        fs::write(install_dir.join(launcher_file_name()), b"launcher").expect("launcher");
+
+        let found = diagnose_in(&path, &install_dir);
+        let installed = found
+            .iter()
+            .find(|diagnostic| diagnostic.code == DiagnosticCode::LauncherInstalled)
+            .expect("launcher status");
+        assert_eq!(installed.severity, Severity::Info);
+        let value = serde_json::to_value(installed).expect("serialize");
+        assert_eq!(value["code"], "launcher_installed");
+        assert!(
+            found
+                .iter()
+                .any(|diagnostic| diagnostic.code == DiagnosticCode::PathNotConfigured)
+        );
+    }
+}
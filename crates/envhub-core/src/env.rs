@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use indexmap::IndexMap;
+
+use crate::{CoreError, ErrorCode};
+
+/// Merges environment layers into the variables to set on the target.
+///
+/// Layers are applied in order and a later layer overrides any key set by an
+/// earlier one, so callers pass the most general layer first and the profile
+/// last. A key keeps the position where it first appeared; only its value is
+/// replaced. The inherited process environment is not a layer here: the
+/// launcher applies the result on top of it, so every layer overrides it.
+pub fn merge_environments(layers: &[&IndexMap<String, String>]) -> IndexMap<OsString, OsString> {
+    merge_string_layers(layers)
+        .into_iter()
+        .map(|(key, value)| (OsString::from(key), OsString::from(value)))
+        .collect()
+}
+
+/// [`merge_environments`] without the conversion to `OsString`, for callers
+/// that keep working with the merged variables as strings.
+pub(crate) fn merge_string_layers(
+    layers: &[&IndexMap<String, String>],
+) -> IndexMap<String, String> {
+    let mut merged = IndexMap::new();
+    for layer in layers {
+        for (key, value) in layer.iter() {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+/// Checks a variable before it is stored, the same way `set_profile_env`
+/// does, so editors can flag bad input as it is typed. The key must be
+/// non-empty, must not start with a digit and must not contain whitespace,
+/// `=` or NUL; the value may be empty but must not contain NUL, which no
+/// process environment can hold.
+pub fn validate_env_pair(key: &str, value: &str) -> Result<(), CoreError> {
+    let invalid = |message: String| Err(CoreError::new(ErrorCode::InvalidState, message));
+    if key.is_empty() {
+        return invalid("Environment key must be non-empty".to_string());
+    }
+    if key.starts_with(|c: char| c.is_ascii_digit()) {
+        return invalid(format!(
+            "Environment key \"{key}\" must not start with a digit"
+        ));
+    }
+    if let Some(c) = key
+        .chars()
+        .find(|c| c.is_whitespace() || *c == '=' || *c == '\0')
+    {
+        let what = match c {
+            '=' => "\"=\"".to_string(),
+            '\0' => "NUL".to_string(),
+            _ => "spaces".to_string(),
+        };
+        return invalid(format!("Environment key \"{key}\" must not contain {what}"));
+    }
+    if value.contains('\0') {
+        return invalid(format!("Value of {key} must not contain NUL"));
+    }
+    Ok(())
+}
+
+/// Reads a dotenv file referenced by a profile's `env_file`. Returns `None`
+/// when the file does not exist so a missing file can be skipped.
+pub fn read_env_file(path: &Path) -> Result<Option<IndexMap<String, String>>, CoreError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to read {}: {err}", path.display()),
+            ));
+        }
+    };
+    parse_dotenv(&contents)
+        .map(Some)
+        .map_err(|err| CoreError::new(err.code, format!("{}: {}", path.display(), err.message)))
+}
+
+/// Parses `KEY=VALUE` lines. Blank lines and `#` comments are skipped, an
+/// `export ` prefix is allowed, single-quoted values are literal and
+/// double-quoted values understand `\n`, `\"` and `\\`. Unquoted values are
+/// trimmed and may end in a ` #` comment.
+pub fn parse_dotenv(contents: &str) -> Result<IndexMap<String, String>, CoreError> {
+    let mut env = IndexMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        match dotenv_line(line) {
+            Ok(Some((key, value))) => {
+                env.insert(key, value);
+            }
+            Ok(None) => {}
+            Err(()) => {
+                return Err(CoreError::new(
+                    ErrorCode::InvalidState,
+                    format!("line {}: expected KEY=VALUE", index + 1),
+                ));
+            }
+        }
+    }
+    Ok(env)
+}
+
+/// Parses a pasted block of `KEY=VALUE` lines like [`parse_dotenv`], but
+/// sets aside lines it cannot read instead of failing. Returns the variables
+/// and the 1-based numbers of the skipped lines; their text is not kept, since
+/// a malformed line may still hold a secret.
+pub fn parse_env_lines(contents: &str) -> (IndexMap<String, String>, Vec<usize>) {
+    let mut env = IndexMap::new();
+    let mut skipped = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        match dotenv_line(line) {
+            Ok(Some((key, value))) => {
+                env.insert(key, value);
+            }
+            Ok(None) => {}
+            Err(()) => skipped.push(index + 1),
+        }
+    }
+    (env, skipped)
+}
+
+/// One dotenv line: `Ok(None)` for blanks and comments, `Err(())` when it is
+/// not `KEY=VALUE`.
+fn dotenv_line(line: &str) -> Result<Option<(String, String)>, ()> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=').ok_or(())?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(());
+    }
+    Ok(Some((key.to_string(), dotenv_value(value.trim()))))
+}
+
+/// Writes `env` as dotenv text that [`parse_dotenv`] reads back unchanged,
+/// with each line of `notes` as a leading `#` comment. Values that need it
+/// are double-quoted.
+pub fn format_dotenv(env: &IndexMap<String, String>, notes: Option<&str>) -> String {
+    let mut out = String::new();
+    if let Some(notes) = notes {
+        for line in notes.lines() {
+            out.push_str(format!("# {line}").trim_end());
+            out.push('\n');
+        }
+    }
+    for (key, value) in env {
+        let plain = !value.is_empty()
+            && !value.contains(['"', '\'', '\\', '#', '\n'])
+            && value.trim() == value;
+        if plain {
+            out.push_str(&format!("{key}={value}\n"));
+            continue;
+        }
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        out.push_str(&format!("{key}=\"{escaped}\"\n"));
+    }
+    out
+}
+
+fn dotenv_value(raw: &str) -> String {
+    if let Some(inner) = raw
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return inner.to_string();
+    }
+    if let Some(inner) = raw
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        let mut value = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some(other) => value.push(other),
+                None => value.push('\\'),
+            }
+        }
+        return value;
+    }
+    match raw.find(" #") {
+        Some(comment) => raw[..comment].trim_end().to_string(),
+        None => raw.to_string(),
+    }
+}
+
+/// Resolves `${KEY}` references in profile values, at launch time.
+///
+/// A reference to another key of the same map uses that key's resolved value,
+/// so `FULL_URL=${SCHEME}://${HOST}` works in any key order. Names the map
+/// doesn't define, and a key referring to itself (`PATH=${PATH}:/opt/bin`),
+/// fall back to `lookup`, normally the inherited process environment. Names
+/// neither defines are left as written. Only the braced form is recognised;
+/// a cycle between keys is an error.
+pub fn resolve_env_references(
+    env: &IndexMap<String, String>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<IndexMap<String, String>, CoreError> {
+    let mut resolved = HashMap::new();
+    let mut resolving = Vec::new();
+    env.keys()
+        .map(|key| {
+            let value = resolve_reference(key, env, &lookup, &mut resolved, &mut resolving)?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+fn resolve_reference<'a>(
+    key: &'a str,
+    env: &'a IndexMap<String, String>,
+    lookup: &impl Fn(&str) -> Option<String>,
+    resolved: &mut HashMap<&'a str, String>,
+    resolving: &mut Vec<&'a str>,
+) -> Result<String, CoreError> {
+    if let Some(value) = resolved.get(key) {
+        return Ok(value.clone());
+    }
+    if let Some(start) = resolving.iter().position(|pending| *pending == key) {
+        let mut cycle = resolving[start..].to_vec();
+        cycle.push(key);
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Reference cycle between env keys: {}", cycle.join(" -> ")),
+        ));
+    }
+    resolving.push(key);
+    let mut out = String::new();
+    let mut rest = env[key].as_str();
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let name = &after[..end];
+        match env.get_key_value(name) {
+            Some((other, _)) if other != key => {
+                out.push_str(&resolve_reference(other, env, lookup, resolved, resolving)?);
+            }
+            _ => match lookup(name) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&rest[start..start + 2 + end + 1]),
+            },
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    resolving.pop();
+    resolved.insert(key, out.clone());
+    Ok(out)
+}
+
+/// Expands `{{name}}` built-ins in an env value. Evaluated by the launcher
+/// each time it starts the target:
+///
+/// - `{{home}}`: the user's home directory.
+/// - `{{date}}`: today's date in UTC, `YYYY-MM-DD`.
+/// - `{{hostname}}`: the machine's host name.
+/// - `{{uuid}}`: a random v4 UUID, fresh for every occurrence.
+///
+/// Whitespace inside the braces is ignored. Unknown names and unterminated
+/// `{{` are left as written, so values meant for another tool's templates
+/// (such as `{{.ID}}` for docker) pass through unchanged.
+pub fn expand_templates(value: &str) -> Result<String, CoreError> {
+    expand_templates_with(value, builtin_template)
+}
+
+/// Like [`expand_templates`] with a custom lookup. `Ok(None)` marks an
+/// unknown name, which is kept literally.
+pub fn expand_templates_with(
+    value: &str,
+    lookup: impl Fn(&str) -> Result<Option<String>, CoreError>,
+) -> Result<String, CoreError> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        match lookup(after[..end].trim())? {
+            Some(expanded) => out.push_str(&expanded),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Applies [`expand_templates`] to every value of an env map.
+pub fn expand_env_templates(
+    env: &IndexMap<String, String>,
+) -> Result<IndexMap<String, String>, CoreError> {
+    env.iter()
+        .map(|(key, value)| Ok((key.clone(), expand_templates(value)?)))
+        .collect()
+}
+
+fn builtin_template(name: &str) -> Result<Option<String>, CoreError> {
+    let value = match name {
+        "home" => dirs::home_dir()
+            .ok_or_else(|| {
+                CoreError::new(
+                    ErrorCode::InstallPath,
+                    "Failed to resolve home directory".to_string(),
+                )
+            })?
+            .to_string_lossy()
+            .to_string(),
+        "date" => {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            utc_date(secs)
+        }
+        "hostname" => hostname()?,
+        "uuid" => uuid::Uuid::new_v4().to_string(),
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// The current UTC time as an RFC 3339 timestamp, e.g.
+/// `2024-05-01T09:30:00Z`. Timestamps in this form sort chronologically.
+pub(crate) fn utc_timestamp_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    utc_timestamp(secs)
+}
+
+fn utc_timestamp(secs: u64) -> String {
+    let time = secs % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        utc_date(secs),
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Formats seconds since the Unix epoch as a UTC `YYYY-MM-DD` date.
+fn utc_date(secs: u64) -> String {
+    // Days-to-civil conversion from Howard Hinnant's date algorithms.
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn hostname() -> Result<String, CoreError> {
+    for var in ["HOSTNAME", "COMPUTERNAME"] {
+        if let Ok(name) = std::env::var(var)
+            && !name.trim().is_empty()
+        {
+            return Ok(name.trim().to_string());
+        }
+    }
+    for file in ["/etc/hostname", "/proc/sys/kernel/hostname"] {
+        if let Ok(name) = fs::read_to_string(file)
+            && !name.trim().is_empty()
+        {
+            return Ok(name.trim().to_string());
+        }
+    }
+    if let Ok(output) = Command::new("hostname").output()
+        && output.status.success()
+    {
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !name.is_empty() {
+            return Ok(name);
+        }
+    }
+    Err(CoreError::new(
+        ErrorCode::Io,
+        "Failed to determine hostname".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn validate_env_pair_accepts_names_and_rejects_malformed_ones() {
+        for key in [
+            "API_KEY",
+            "_private",
+            "lower_case",
+            "PATH2",
+            "Program.Files(x86)",
+        ] {
+            assert!(validate_env_pair(key, "value").is_ok(), "{key}");
+        }
+        assert!(validate_env_pair("EMPTY", "").is_ok());
+
+        let err = validate_env_pair("2FA_CODE", "x").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("digit"), "{}", err.message);
+        for key in ["MY KEY", " KEY", "KEY\t", "A=B", ""] {
+            let err = validate_env_pair(key, "x").unwrap_err();
+            assert_eq!(err.code, ErrorCode::InvalidState, "{key:?}");
+        }
+        assert!(
+            validate_env_pair("MY KEY", "x")
+                .unwrap_err()
+                .message
+                .contains("spaces")
+        );
+        assert!(validate_env_pair("KEY", "a\0b").is_err());
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let global = layer(&[("A", "global"), ("B", "global"), ("C", "global")]);
+        let shared = layer(&[("B", "shared"), ("D", "shared")]);
+        let profile = layer(&[("C", "profile")]);
+
+        let merged = merge_environments(&[&global, &shared, &profile]);
+        let pairs: Vec<(String, String)> = merged
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string_lossy().to_string(),
+                    value.to_string_lossy().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("A".to_string(), "global".to_string()),
+                ("B".to_string(), "shared".to_string()),
+                ("C".to_string(), "profile".to_string()),
+                ("D".to_string(), "shared".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_layers_merge_to_nothing() {
+        assert!(merge_environments(&[]).is_empty());
+    }
+
+    #[test]
+    fn parse_dotenv_handles_quotes_comments_and_export() {
+        let env = parse_dotenv(
+            "# secrets\n\nexport TOKEN=abc123\nPLAIN = value # note\nSINGLE='a #b $HOME'\nDOUBLE=\"line\\nnext \\\"q\\\"\"\nEMPTY=\n",
+        )
+        .expect("parse");
+        assert_eq!(env["TOKEN"], "abc123");
+        assert_eq!(env["PLAIN"], "value");
+        assert_eq!(env["SINGLE"], "a #b $HOME");
+        assert_eq!(env["DOUBLE"], "line\nnext \"q\"");
+        assert_eq!(env["EMPTY"], "");
+
+        let err = parse_dotenv("OK=1\nnot a pair\n").unwrap_err();
+        assert!(err.message.contains("line 2"));
+    }
+
+    #[test]
+    fn parse_env_lines_skips_unreadable_lines() {
+        let (env, skipped) =
+            parse_env_lines("API_URL=\"https://x\"\n\njust text\nexport MODE='fast'\n=oops\n");
+        assert_eq!(env["API_URL"], "https://x");
+        assert_eq!(env["MODE"], "fast");
+        assert_eq!(env.len(), 2);
+        assert_eq!(skipped, [3, 5]);
+    }
+
+    #[test]
+    fn read_env_file_skips_missing_file() {
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        assert!(
+            read_env_file(&dir.path().join("missing.env"))
+                .expect("read")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn home_template_expands_to_home_dir() {
+        let home = dirs::home_dir().expect("home dir");
+        assert_eq!(
+            expand_templates("{{home}}/.cache").expect("expand"),
+            format!("{}/.cache", home.to_string_lossy())
+        );
+        assert_eq!(
+            expand_templates("{{ home }}").expect("expand"),
+            home.to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn unknown_and_unterminated_templates_stay_literal() {
+        assert_eq!(
+            expand_templates("{{.ID}} {{nope}} {{home").expect("expand"),
+            "{{.ID}} {{nope}} {{home"
+        );
+    }
+
+    #[test]
+    fn builtin_templates_expand() {
+        let first = expand_templates("{{uuid}}").expect("expand");
+        let second = expand_templates("{{uuid}}").expect("expand");
+        assert_eq!(first.len(), 36);
+        assert_ne!(first, second);
+        assert_eq!(expand_templates("{{date}}").expect("expand").len(), 10);
+        assert!(!expand_templates("{{hostname}}").expect("expand").is_empty());
+
+        let expanded = expand_templates_with("a-{{x}}-b", |name| {
+            Ok((name == "x").then(|| "X".to_string()))
+        })
+        .expect("expand");
+        assert_eq!(expanded, "a-X-b");
+    }
+
+    #[test]
+    fn utc_date_formats_epoch_seconds() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400), "2000-02-29");
+        assert_eq!(utc_date(1_700_000_000), "2023-11-14");
+        assert_eq!(utc_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn profile_references_resolve_in_any_order() {
+        let env = layer(&[
+            ("FULL_URL", "${SCHEME}://${HOST}${BASE}"),
+            ("BASE", "/api"),
+            ("SCHEME", "https"),
+            ("HOST", "${SUBDOMAIN}.example.com"),
+            ("SUBDOMAIN", "staging"),
+        ]);
+        let resolved = resolve_env_references(&env, |_| None).expect("resolve");
+        assert_eq!(resolved["FULL_URL"], "https://staging.example.com/api");
+        assert_eq!(
+            resolved.keys().collect::<Vec<_>>(),
+            env.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn profile_references_fall_back_to_process_env() {
+        let env = layer(&[
+            ("CACHE", "${XDG_CACHE_HOME}/tool"),
+            ("PATH", "/opt/tool/bin:${PATH}"),
+            ("HOST", "example.com"),
+            ("LEFT", "${UNSET_ANYWHERE} ${HOST"),
+        ]);
+        let process = |name: &str| match name {
+            "XDG_CACHE_HOME" => Some("/home/me/.cache".to_string()),
+            "PATH" => Some("/usr/bin".to_string()),
+            "HOST" => Some("from-process".to_string()),
+            _ => None,
+        };
+        let resolved = resolve_env_references(&env, process).expect("resolve");
+        assert_eq!(resolved["CACHE"], "/home/me/.cache/tool");
+        assert_eq!(resolved["PATH"], "/opt/tool/bin:/usr/bin");
+        assert_eq!(resolved["LEFT"], "${UNSET_ANYWHERE} ${HOST");
+    }
+
+    #[test]
+    fn profile_reference_cycle_is_an_error() {
+        let env = layer(&[("A", "${B}"), ("B", "x${C}"), ("C", "${A}"), ("D", "ok")]);
+        let err = resolve_env_references(&env, |_| None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("A -> B -> C -> A"), "{}", err.message);
+    }
+
+    #[test]
+    fn format_dotenv_round_trips_with_notes_header() {
+        let env = layer(&[
+            ("PLAIN", "value"),
+            ("SPACED", " padded value "),
+            ("QUOTED", r#"say "hi" \ #1"#),
+            ("MULTI", "a\nb"),
+            ("EMPTY", ""),
+        ]);
+        let text = format_dotenv(&env, Some("uses prod creds\nbe careful"));
+        assert!(text.starts_with("# uses prod creds\n# be careful\nPLAIN=value\n"));
+        assert_eq!(parse_dotenv(&text).expect("parse"), env);
+    }
+}
@@ -1,5 +1,6 @@
-use std::fmt;
+use std::{fmt, io};
 
+use serde::{Serialize, Serializer};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -13,6 +14,16 @@ pub enum ErrorCode {
     InstallPath,
     MissingLauncher,
     TargetNotFound,
+    AlreadyExists,
+    Unsupported,
+    /// The launcher was started without an `argv[0]` to pick the app from.
+    MissingArgv0,
+    /// The target resolves to the running launcher itself.
+    SelfReference,
+    /// The target resolved but could not be executed or spawned.
+    ExecFailed,
+    /// The user declined a confirmation, or none could be asked for.
+    Cancelled,
 }
 
 impl fmt::Display for ErrorCode {
@@ -27,12 +38,27 @@ impl fmt::Display for ErrorCode {
             ErrorCode::InstallPath => "install_path_error",
             ErrorCode::MissingLauncher => "missing_launcher",
             ErrorCode::TargetNotFound => "target_not_found",
+            ErrorCode::AlreadyExists => "already_exists",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::MissingArgv0 => "missing_argv0",
+            ErrorCode::SelfReference => "self_reference",
+            ErrorCode::ExecFailed => "exec_failed",
+            ErrorCode::Cancelled => "cancelled",
         };
         write!(f, "{code}")
     }
 }
 
-#[derive(Debug, Error, Clone)]
+/// Serializes as the same code string `Display` prints, e.g. `"invalid_state"`.
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Serializes as `{"code": "...", "message": "..."}` so frontends can branch
+/// on the code instead of parsing the message.
+#[derive(Debug, Error, Clone, Serialize)]
 #[error("{code}: {message}")]
 pub struct CoreError {
     pub code: ErrorCode,
@@ -44,3 +70,71 @@ impl CoreError {
         Self { code, message }
     }
 }
+
+impl ErrorCode {
+    /// The closest `io::ErrorKind`, for callers working in `io::Result`.
+    pub fn io_kind(self) -> io::ErrorKind {
+        match self {
+            ErrorCode::Io | ErrorCode::InstallPath | ErrorCode::ExecFailed => io::ErrorKind::Other,
+            ErrorCode::Json => io::ErrorKind::InvalidData,
+            ErrorCode::InvalidState | ErrorCode::MissingArgv0 | ErrorCode::SelfReference => {
+                io::ErrorKind::InvalidInput
+            }
+            ErrorCode::AppNotFound
+            | ErrorCode::ProfileNotFound
+            | ErrorCode::MissingLauncher
+            | ErrorCode::TargetNotFound => io::ErrorKind::NotFound,
+            ErrorCode::Permission => io::ErrorKind::PermissionDenied,
+            ErrorCode::AlreadyExists => io::ErrorKind::AlreadyExists,
+            ErrorCode::Unsupported => io::ErrorKind::Unsupported,
+            ErrorCode::Cancelled => io::ErrorKind::Interrupted,
+        }
+    }
+}
+
+/// Lets `?` bridge core calls into `io::Result` code such as the TUI event
+/// loop. The `CoreError` stays available through `get_ref`/`downcast`.
+impl From<CoreError> for io::Error {
+    fn from(err: CoreError) -> Self {
+        io::Error::new(err.code.io_kind(), err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_error_serializes_code_and_message() {
+        let err = CoreError::new(ErrorCode::InvalidState, "App \"x\" is bad".to_string());
+        let value = serde_json::to_value(&err).expect("serialize");
+        assert_eq!(
+            value,
+            serde_json::json!({ "code": "invalid_state", "message": "App \"x\" is bad" })
+        );
+    }
+
+    #[test]
+    fn core_error_converts_to_matching_io_kind() {
+        let cases = [
+            (ErrorCode::Permission, io::ErrorKind::PermissionDenied),
+            (ErrorCode::AppNotFound, io::ErrorKind::NotFound),
+            (ErrorCode::TargetNotFound, io::ErrorKind::NotFound),
+            (ErrorCode::AlreadyExists, io::ErrorKind::AlreadyExists),
+            (ErrorCode::Json, io::ErrorKind::InvalidData),
+            (ErrorCode::InvalidState, io::ErrorKind::InvalidInput),
+            (ErrorCode::Unsupported, io::ErrorKind::Unsupported),
+            (ErrorCode::Io, io::ErrorKind::Other),
+            (ErrorCode::SelfReference, io::ErrorKind::InvalidInput),
+            (ErrorCode::ExecFailed, io::ErrorKind::Other),
+            (ErrorCode::Cancelled, io::ErrorKind::Interrupted),
+        ];
+        for (code, kind) in cases {
+            let err = io::Error::from(CoreError::new(code, "boom".to_string()));
+            assert_eq!(err.kind(), kind, "{code}");
+            assert_eq!(err.to_string(), format!("{code}: boom"));
+            let inner = err.into_inner().expect("inner").downcast::<CoreError>();
+            assert_eq!(inner.expect("core error").code, code);
+        }
+    }
+}
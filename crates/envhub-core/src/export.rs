@@ -0,0 +1,622 @@
+use std::fs;
+use std::path::Path;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::observe::save_and_notify;
+use crate::{
+    AppConfig, CoreError, ErrorCode, State, StateChange, StateDiff, diff_states,
+    load_state_from_path,
+};
+
+/// A single app and all of its profiles, detached from the rest of the state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppBundle {
+    pub name: String,
+    pub app: AppConfig,
+}
+
+pub fn export_app(name: &str, out_path: &Path) -> Result<(), CoreError> {
+    let path = crate::default_state_path()?;
+    export_app_in(&path, name, out_path)
+}
+
+pub fn export_app_in(path: &Path, name: &str, out_path: &Path) -> Result<(), CoreError> {
+    let bundle = export_bundle(path, name)?;
+    let data = serde_json::to_vec_pretty(&bundle).map_err(|err| {
+        CoreError::new(ErrorCode::Json, format!("Failed to serialize app: {err}"))
+    })?;
+    fs::write(out_path, data)
+        .map_err(|err| CoreError::new(ErrorCode::Io, format!("Failed to write export: {err}")))
+}
+
+fn export_bundle(path: &Path, name: &str) -> Result<AppBundle, CoreError> {
+    let state = load_state_from_path(path)?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let mut app = app.clone();
+    // Install details describe this machine, not the setup being shared.
+    app.installed = false;
+    app.install_path = None;
+    app.resolved_target = None;
+    // Old values are often rotated secrets; they stay on this machine.
+    for profile in app.profiles.values_mut() {
+        profile.env_history.clear();
+    }
+    Ok(AppBundle {
+        name: name.to_string(),
+        app,
+    })
+}
+
+/// Placeholder that replaces secret values in redacted exports.
+pub const REDACTED: &str = "***";
+
+/// Whether an env key looks like it holds a secret: `*_TOKEN`, `*_KEY`,
+/// anything containing `SECRET` or `PASSWORD`, case-insensitively.
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    key == "TOKEN"
+        || key.ends_with("_TOKEN")
+        || key == "KEY"
+        || key.ends_with("_KEY")
+        || key.contains("SECRET")
+        || key.contains("PASSWORD")
+}
+
+/// The app as `export_app` would write it, with the values of secret-looking
+/// env keys (see [`is_secret_key`]) replaced by [`REDACTED`], for pasting
+/// into bug reports.
+pub fn export_app_redacted(name: &str) -> Result<serde_json::Value, CoreError> {
+    let path = crate::default_state_path()?;
+    export_app_redacted_in(&path, name)
+}
+
+pub fn export_app_redacted_in(path: &Path, name: &str) -> Result<serde_json::Value, CoreError> {
+    let mut bundle = export_bundle(path, name)?;
+    for profile in bundle.app.profiles.values_mut() {
+        for (key, value) in profile.env.iter_mut() {
+            if is_secret_key(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+    serde_json::to_value(&bundle)
+        .map_err(|err| CoreError::new(ErrorCode::Json, format!("Failed to serialize app: {err}")))
+}
+
+pub fn import_app(bundle_path: &Path, as_name: Option<String>) -> Result<String, CoreError> {
+    let path = crate::default_state_path()?;
+    import_app_in(&path, bundle_path, as_name)
+}
+
+pub fn import_app_in(
+    path: &Path,
+    bundle_path: &Path,
+    as_name: Option<String>,
+) -> Result<String, CoreError> {
+    import_app_with_in(path, bundle_path, as_name, false)
+}
+
+/// Imports a bundle. With `merge`, importing over an existing app keeps its
+/// local settings and only adds or updates what the bundle brings (see
+/// `merge_app`); without it a name collision is an `AlreadyExists` error.
+pub fn import_app_with(
+    bundle_path: &Path,
+    as_name: Option<String>,
+    merge: bool,
+) -> Result<String, CoreError> {
+    let path = crate::default_state_path()?;
+    import_app_with_in(&path, bundle_path, as_name, merge)
+}
+
+pub fn import_app_with_in(
+    path: &Path,
+    bundle_path: &Path,
+    as_name: Option<String>,
+    merge: bool,
+) -> Result<String, CoreError> {
+    let bundle = read_bundle(bundle_path)?;
+    let mut state = load_state_from_path(path)?;
+    let (name, _) = apply_bundle(&mut state, bundle, as_name, merge)?;
+    save_and_notify(path, &state, StateChange::AppImported { app: name.clone() })?;
+    Ok(name)
+}
+
+/// What importing a bundle would do, computed without writing anything.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub name: String,
+    pub diff: StateDiff,
+    /// Differences the import leaves alone, such as an existing app pointing
+    /// at a different target, so the user can resolve them by hand.
+    pub conflicts: Vec<String>,
+}
+
+pub fn preview_import(
+    bundle_path: &Path,
+    as_name: Option<String>,
+    merge: bool,
+) -> Result<ImportPreview, CoreError> {
+    let path = crate::default_state_path()?;
+    preview_import_in(&path, bundle_path, as_name, merge)
+}
+
+pub fn preview_import_in(
+    path: &Path,
+    bundle_path: &Path,
+    as_name: Option<String>,
+    merge: bool,
+) -> Result<ImportPreview, CoreError> {
+    let bundle = read_bundle(bundle_path)?;
+    let before = load_state_from_path(path)?;
+    let mut after = before.clone();
+    let (name, conflicts) = apply_bundle(&mut after, bundle, as_name, merge)?;
+    Ok(ImportPreview {
+        name,
+        diff: diff_states(&before, &after),
+        conflicts,
+    })
+}
+
+/// A profile's env as dotenv text, headed by its notes as `#` comments.
+pub fn export_profile_dotenv(name: &str, profile: &str) -> Result<String, CoreError> {
+    let path = crate::default_state_path()?;
+    export_profile_dotenv_in(&path, name, profile)
+}
+
+pub fn export_profile_dotenv_in(
+    path: &Path,
+    name: &str,
+    profile: &str,
+) -> Result<String, CoreError> {
+    let state = load_state_from_path(path)?;
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let profile_config = app.profiles.get(profile).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::ProfileNotFound,
+            format!("Profile \"{profile}\" not found for app \"{name}\""),
+        )
+    })?;
+    Ok(crate::format_dotenv(
+        &profile_config.env,
+        profile_config.notes.as_deref(),
+    ))
+}
+
+/// Merges the variables defined in `file` into an existing profile, keeping
+/// keys the file doesn't mention. The format follows the extension: `.json`
+/// for a JSON object, `.yaml`/`.yml` for a YAML mapping (with the `yaml`
+/// feature). Every value must be a string. Returns the imported keys.
+pub fn import_profile(name: &str, profile: &str, file: &Path) -> Result<Vec<String>, CoreError> {
+    let path = crate::default_state_path()?;
+    import_profile_in(&path, name, profile, file)
+}
+
+pub fn import_profile_in(
+    path: &Path,
+    name: &str,
+    profile: &str,
+    file: &Path,
+) -> Result<Vec<String>, CoreError> {
+    let imported = read_env_definitions(file)?;
+    crate::set_profile_env_bulk_in(path, name, profile, &imported)?;
+    Ok(imported.keys().cloned().collect())
+}
+
+fn read_env_definitions(file: &Path) -> Result<IndexMap<String, String>, CoreError> {
+    let extension = file
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let data = fs::read_to_string(file).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to read {}: {err}", file.display()),
+        )
+    })?;
+    match extension.as_str() {
+        "json" => parse_json_env(&data),
+        "yaml" | "yml" => parse_yaml_env(&data),
+        _ => Err(CoreError::new(
+            ErrorCode::Unsupported,
+            format!(
+                "Cannot import {}: expected a .json, .yaml or .yml file",
+                file.display()
+            ),
+        )),
+    }
+}
+
+fn non_string_value(key: &str) -> CoreError {
+    CoreError::new(
+        ErrorCode::InvalidState,
+        format!("Value for \"{key}\" must be a string"),
+    )
+}
+
+fn parse_json_env(data: &str) -> Result<IndexMap<String, String>, CoreError> {
+    // Straight into an IndexMap: serde_json's own map would sort the keys.
+    let map: IndexMap<String, serde_json::Value> = serde_json::from_str(data).map_err(|err| {
+        if serde_json::from_str::<serde_json::Value>(data).is_ok() {
+            CoreError::new(
+                ErrorCode::InvalidState,
+                "Expected a JSON object of KEY: \"value\" pairs".to_string(),
+            )
+        } else {
+            CoreError::new(ErrorCode::Json, format!("Failed to parse JSON: {err}"))
+        }
+    })?;
+    map.into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => Ok((key, value)),
+            _ => Err(non_string_value(&key)),
+        })
+        .collect()
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml_env(data: &str) -> Result<IndexMap<String, String>, CoreError> {
+    use serde_yaml::Value;
+
+    let value: Value = serde_yaml::from_str(data).map_err(|err| {
+        CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Failed to parse YAML: {err}"),
+        )
+    })?;
+    let Value::Mapping(map) = value else {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Expected a YAML mapping of KEY: value pairs".to_string(),
+        ));
+    };
+    map.into_iter()
+        .map(|(key, value)| {
+            let Value::String(key) = key else {
+                return Err(CoreError::new(
+                    ErrorCode::InvalidState,
+                    format!("Keys must be strings, found {key:?}"),
+                ));
+            };
+            match value {
+                Value::String(value) => Ok((key, value)),
+                _ => Err(non_string_value(&key)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml_env(_data: &str) -> Result<IndexMap<String, String>, CoreError> {
+    Err(CoreError::new(
+        ErrorCode::Unsupported,
+        "YAML import needs envhub-core's `yaml` feature".to_string(),
+    ))
+}
+
+fn read_bundle(bundle_path: &Path) -> Result<AppBundle, CoreError> {
+    let data = fs::read_to_string(bundle_path)
+        .map_err(|err| CoreError::new(ErrorCode::Io, format!("Failed to read export: {err}")))?;
+    serde_json::from_str(&data)
+        .map_err(|err| CoreError::new(ErrorCode::Json, format!("Failed to parse export: {err}")))
+}
+
+/// Adds the bundle to `state`, returning the app name used and any conflicts.
+fn apply_bundle(
+    state: &mut State,
+    bundle: AppBundle,
+    as_name: Option<String>,
+    merge: bool,
+) -> Result<(String, Vec<String>), CoreError> {
+    let name = as_name.unwrap_or(bundle.name);
+    if name.trim().is_empty() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "App name must be non-empty".to_string(),
+        ));
+    }
+    let mut app = bundle.app;
+    app.installed = false;
+    app.resolved_target = None;
+    let mut conflicts = Vec::new();
+    match state.apps.get_mut(&name) {
+        Some(existing) if merge => conflicts = merge_app(&name, existing, app),
+        Some(_) => {
+            return Err(CoreError::new(
+                ErrorCode::AlreadyExists,
+                format!("App \"{name}\" already exists; import it under a different name"),
+            ));
+        }
+        None => {
+            state.apps.insert(name.clone(), app);
+        }
+    }
+    crate::validate_state(state)?;
+    Ok((name, conflicts))
+}
+
+/// Merges an imported app into an existing one without clobbering local
+/// tweaks: new profiles are added, imported env keys are added or updated,
+/// and everything else (target, active profile, install path, local-only
+/// keys and profiles) stays as it is. Returns the settings that differ but
+/// were kept local.
+fn merge_app(name: &str, local: &mut AppConfig, incoming: AppConfig) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    if local.target_binary != incoming.target_binary {
+        conflicts.push(format!(
+            "{name}: target differs (local {}, imported {}); keeping local",
+            local.target_binary, incoming.target_binary
+        ));
+    }
+    for (profile_name, profile) in incoming.profiles {
+        let Some(existing) = local.profiles.get_mut(&profile_name) else {
+            local.profiles.insert(profile_name, profile);
+            continue;
+        };
+        if existing.command_args != profile.command_args {
+            conflicts.push(format!(
+                "{name}/{profile_name}: command args differ; keeping local"
+            ));
+        }
+        existing.env.extend(profile.env);
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_app_in, set_profile_env_in};
+    use tempfile::TempDir;
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let dir = TempDir::new().expect("temp dir");
+        let source = dir.path().join("source.json");
+        let dest = dir.path().join("dest.json");
+        let bundle = dir.path().join("tool.json");
+        register_app_in(&source, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&source, "tool", "default", "KEY", "VALUE").expect("set");
+        let mut state = load_state_from_path(&source).expect("load");
+        let app = state.apps.get_mut("tool").expect("app");
+        app.cache_target = true;
+        app.resolved_target = Some(crate::ResolvedTarget {
+            targets: vec!["tool-bin".to_string()],
+            path: "/opt/tool/tool-bin".to_string(),
+        });
+        crate::save_state_to_path(&source, &state).expect("save");
+
+        export_app_in(&source, "tool", &bundle).expect("export");
+        let exported = fs::read_to_string(&bundle).expect("read export");
+        assert!(!exported.contains("resolved_target"), "{exported}");
+        let name = import_app_in(&dest, &bundle, None).expect("import");
+        assert_eq!(name, "tool");
+
+        let state = load_state_from_path(&dest).expect("load");
+        let app = state.apps.get("tool").expect("app");
+        assert_eq!(app.target_binary, "tool-bin");
+        assert!(app.cache_target);
+        assert!(app.resolved_target.is_none());
+        assert_eq!(
+            app.profiles
+                .get("default")
+                .and_then(|profile| profile.env.get("KEY").map(String::as_str)),
+            Some("VALUE")
+        );
+    }
+
+    #[test]
+    fn redacted_export_masks_secret_keys_only() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        for (key, value) in [
+            ("GITHUB_TOKEN", "ghp_123"),
+            ("openai_api_key", "sk-123"),
+            ("CLIENT_SECRET_FILE", "/secret.json"),
+            ("DB_PASSWORD", "hunter2"),
+            ("KEYBOARD", "us"),
+            ("TOKENIZER", "bpe"),
+            ("HOME_DIR", "/home/me"),
+        ] {
+            set_profile_env_in(&path, "tool", "default", key, value).expect("set");
+        }
+
+        let value = export_app_redacted_in(&path, "tool").expect("export");
+        assert_eq!(value["name"], "tool");
+        let env = &value["app"]["profiles"]["default"]["env"];
+        for key in [
+            "GITHUB_TOKEN",
+            "openai_api_key",
+            "CLIENT_SECRET_FILE",
+            "DB_PASSWORD",
+        ] {
+            assert_eq!(env[key], REDACTED, "{key}");
+        }
+        assert_eq!(env["KEYBOARD"], "us");
+        assert_eq!(env["TOKENIZER"], "bpe");
+        assert_eq!(env["HOME_DIR"], "/home/me");
+
+        // The stored config keeps the real values.
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(
+            state.apps["tool"].profiles["default"].env["GITHUB_TOKEN"],
+            "ghp_123"
+        );
+    }
+
+    #[test]
+    fn import_collision_requires_new_name() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let bundle = dir.path().join("tool.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        export_app_in(&path, "tool", &bundle).expect("export");
+
+        let err = import_app_in(&path, &bundle, None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+
+        let name = import_app_in(&path, &bundle, Some("tool2".to_string())).expect("import");
+        assert_eq!(name, "tool2");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps.contains_key("tool"));
+        assert_eq!(
+            state
+                .apps
+                .get("tool2")
+                .map(|app| app.target_binary.as_str()),
+            Some("tool-bin")
+        );
+    }
+
+    #[test]
+    fn import_rejects_invalid_bundle() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let bundle = dir.path().join("bad.json");
+        fs::write(
+            &bundle,
+            r#"{ "name": "tool", "app": { "target_binary": "" } }"#,
+        )
+        .expect("write");
+
+        let err = import_app_in(&path, &bundle, None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn merge_import_previews_then_keeps_local_settings() {
+        let dir = TempDir::new().expect("temp dir");
+        let shared = dir.path().join("shared.json");
+        let path = dir.path().join("state.json");
+        let bundle = dir.path().join("tool.json");
+        register_app_in(&shared, "tool", "/opt/tool").expect("register");
+        set_profile_env_in(&shared, "tool", "default", "MODE", "shared").expect("set");
+        set_profile_env_in(&shared, "tool", "default", "NEW", "1").expect("set");
+        crate::add_profile_in(&shared, "tool", "team").expect("add");
+        export_app_in(&shared, "tool", &bundle).expect("export");
+
+        register_app_in(&path, "tool", "/usr/local/bin/tool").expect("register");
+        set_profile_env_in(&path, "tool", "default", "MODE", "local").expect("set");
+        set_profile_env_in(&path, "tool", "default", "MINE", "x").expect("set");
+        let before = fs::read_to_string(&path).expect("read");
+
+        let preview = preview_import_in(&path, &bundle, None, true).expect("preview");
+        assert_eq!(fs::read_to_string(&path).expect("read"), before);
+        assert_eq!(preview.name, "tool");
+        assert_eq!(preview.conflicts.len(), 1);
+        assert!(preview.conflicts[0].contains("target differs"));
+        let tool = &preview.diff.changed_apps[0];
+        assert_eq!(tool.added_profiles, ["team"]);
+        assert_eq!(tool.changed_profiles[0].added_env, ["NEW"]);
+        assert_eq!(tool.changed_profiles[0].changed_env[0].after, "shared");
+
+        import_app_with_in(&path, &bundle, None, true).expect("import");
+        let state = load_state_from_path(&path).expect("load");
+        let app = &state.apps["tool"];
+        assert_eq!(app.target_binary, "/usr/local/bin/tool");
+        assert_eq!(app.profiles["default"].env["MODE"], "shared");
+        assert_eq!(app.profiles["default"].env["MINE"], "x");
+        assert!(app.profiles.contains_key("team"));
+    }
+
+    #[test]
+    fn import_profile_merges_json_object() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let file = dir.path().join("env.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "KEEP", "1").expect("set");
+        set_profile_env_in(&path, "tool", "default", "HOST", "old").expect("set");
+        fs::write(&file, r#"{"HOST": "example.com", "PORT": "8080"}"#).expect("write");
+
+        let keys = import_profile_in(&path, "tool", "default", &file).expect("import");
+        assert_eq!(keys, ["HOST", "PORT"]);
+        let env = &load_state_from_path(&path).expect("load").apps["tool"].profiles["default"].env;
+        assert_eq!(env["KEEP"], "1");
+        assert_eq!(env["HOST"], "example.com");
+        assert_eq!(env["PORT"], "8080");
+
+        // The file's key order is kept, not sorted.
+        fs::write(&file, r#"{"ZONE": "eu", "API": "v2"}"#).expect("write");
+        let keys = import_profile_in(&path, "tool", "default", &file).expect("import");
+        assert_eq!(keys, ["ZONE", "API"]);
+
+        fs::write(&file, r#"["HOST"]"#).expect("write");
+        let err = import_profile_in(&path, "tool", "default", &file).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        fs::write(&file, "{").expect("write");
+        let err = import_profile_in(&path, "tool", "default", &file).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Json);
+
+        fs::write(&file, r#"{"OK": "1", "PORT": 8080}"#).expect("write");
+        let err = import_profile_in(&path, "tool", "default", &file).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("\"PORT\""), "{}", err.message);
+        let env = &load_state_from_path(&path).expect("load").apps["tool"].profiles["default"].env;
+        assert!(!env.contains_key("OK"));
+
+        let other = dir.path().join("env.toml");
+        fs::write(&other, "A = \"1\"").expect("write");
+        let err = import_profile_in(&path, "tool", "default", &other).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Unsupported);
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn import_profile_reads_yaml_mapping() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let file = dir.path().join("env.yml");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        fs::write(&file, "HOST: example.com\nMODE: \"debug\"\n").expect("write");
+
+        let keys = import_profile_in(&path, "tool", "default", &file).expect("import");
+        assert_eq!(keys, ["HOST", "MODE"]);
+        let env = &load_state_from_path(&path).expect("load").apps["tool"].profiles["default"].env;
+        assert_eq!(env["HOST"], "example.com");
+
+        fs::write(&file, "HOST: example.com\nRETRIES: 3\n").expect("write");
+        let err = import_profile_in(&path, "tool", "default", &file).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("\"RETRIES\""), "{}", err.message);
+    }
+
+    #[test]
+    fn profile_notes_persist_clone_and_head_dotenv_export() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "HOST", "prod.example.com").expect("set");
+        crate::set_profile_notes_in(&path, "tool", "default", Some(" uses prod creds "))
+            .expect("notes");
+        crate::clone_profile_in(&path, "tool", "default", "copy").expect("clone");
+
+        let state = load_state_from_path(&path).expect("load");
+        let profiles = &state.apps["tool"].profiles;
+        assert_eq!(
+            profiles["default"].notes.as_deref(),
+            Some("uses prod creds")
+        );
+        assert_eq!(profiles["copy"].notes.as_deref(), Some("uses prod creds"));
+
+        let dotenv = export_profile_dotenv_in(&path, "tool", "copy").expect("export");
+        assert_eq!(dotenv, "# uses prod creds\nHOST=prod.example.com\n");
+
+        crate::set_profile_notes_in(&path, "tool", "default", Some("  ")).expect("clear");
+        let dotenv = export_profile_dotenv_in(&path, "tool", "default").expect("export");
+        assert_eq!(dotenv, "HOST=prod.example.com\n");
+    }
+}
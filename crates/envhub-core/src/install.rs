@@ -1,23 +1,65 @@
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::{CoreError, ErrorCode, State, default_state_path, load_state_from_path};
+use serde::{Deserialize, Serialize};
 
-#[cfg(test)]
-use crate::AppConfig;
+use crate::observe::save_and_notify;
+use crate::{
+    AppConfig, CoreError, ErrorCode, State, StateChange, StateStore, default_state_path,
+    load_state_from_path,
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InstallMode {
     Global,
+    #[default]
     User,
 }
 
+impl InstallMode {
+    pub fn is_user(&self) -> bool {
+        *self == InstallMode::User
+    }
+}
+
+/// The mode installs use when the caller doesn't pass one, shared by the TUI,
+/// GUI and launcher. `User` unless changed with [`set_default_install_mode`].
+pub fn default_install_mode() -> Result<InstallMode, CoreError> {
+    let path = default_state_path()?;
+    default_install_mode_in(&path)
+}
+
+pub fn default_install_mode_in(
+    store: &(impl StateStore + ?Sized),
+) -> Result<InstallMode, CoreError> {
+    Ok(store.load()?.default_install_mode)
+}
+
+pub fn set_default_install_mode(mode: InstallMode) -> Result<(), CoreError> {
+    let path = default_state_path()?;
+    set_default_install_mode_in(&path, mode)
+}
+
+pub fn set_default_install_mode_in(
+    store: &(impl StateStore + ?Sized),
+    mode: InstallMode,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    state.default_install_mode = mode;
+    save_and_notify(store, &state, StateChange::StateSaved)
+}
+
 #[derive(Debug, Clone)]
 pub struct PlatformInfo {
     pub is_windows: bool,
     pub install_dir: PathBuf,
 }
 
+/// Where shims and the launcher go. On Linux and macOS, `User` installs into
+/// `~/.envhub/bin` unless `ENVHUB_XDG_BIN` opts into `$XDG_BIN_HOME` or an
+/// existing `~/.local/bin` that is on `PATH`.
 pub fn detect_platform(mode: InstallMode) -> Result<PlatformInfo, CoreError> {
     if cfg!(windows) {
         let base = std::env::var_os("LOCALAPPDATA").ok_or_else(|| {
@@ -42,7 +84,7 @@ pub fn detect_platform(mode: InstallMode) -> Result<PlatformInfo, CoreError> {
                     "Failed to resolve home directory".to_string(),
                 )
             })?;
-            home.join(".envhub").join("bin")
+            user_install_dir_from(|name| std::env::var_os(name), &home)
         }
     };
 
@@ -52,15 +94,69 @@ pub fn detect_platform(mode: InstallMode) -> Result<PlatformInfo, CoreError> {
     })
 }
 
+/// The `User` install dir: with `ENVHUB_XDG_BIN` set (to anything but empty
+/// or `0`), an absolute `$XDG_BIN_HOME`, else `~/.local/bin` when it exists
+/// and is already on `PATH`. Otherwise, and always without the opt-in,
+/// `~/.envhub/bin`, so existing installs don't move.
+fn user_install_dir_from(var: impl Fn(&str) -> Option<OsString>, home: &Path) -> PathBuf {
+    let opted_in = var("ENVHUB_XDG_BIN").is_some_and(|value| !value.is_empty() && value != "0");
+    if opted_in {
+        if let Some(dir) = var("XDG_BIN_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+        {
+            return dir;
+        }
+        let local_bin = home.join(".local").join("bin");
+        let on_path = var("PATH").is_some_and(|paths| dir_on_path(&local_bin, &paths));
+        if on_path && local_bin.is_dir() {
+            return local_bin;
+        }
+    }
+    home.join(".envhub").join("bin")
+}
+
 pub fn install_launcher(mode: InstallMode, launcher_path: &Path) -> Result<PathBuf, CoreError> {
     let platform = detect_platform(mode)?;
+    install_launcher_in(&platform.install_dir, launcher_path)
+}
+
+pub fn install_launcher_in(install_dir: &Path, launcher_path: &Path) -> Result<PathBuf, CoreError> {
+    install_launcher_as_in(install_dir, launcher_path, &launcher_name())
+}
+
+/// Installs the launcher as `name` instead of the configured launcher name,
+/// e.g. when another `envhub-launcher` is already on `PATH`, and records the
+/// name in the state so it is found afterwards.
+pub fn install_launcher_as(
+    mode: InstallMode,
+    launcher_path: &Path,
+    name: &str,
+) -> Result<PathBuf, CoreError> {
+    let platform = detect_platform(mode)?;
+    let installed = install_launcher_as_in(&platform.install_dir, launcher_path, name)?;
+    set_launcher_name(name)?;
+    Ok(installed)
+}
+
+pub fn install_launcher_as_in(
+    install_dir: &Path,
+    launcher_path: &Path,
+    name: &str,
+) -> Result<PathBuf, CoreError> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("\"{name}\" is not a valid launcher name"),
+        ));
+    }
     if !launcher_path.exists() {
         return Err(CoreError::new(
             ErrorCode::MissingLauncher,
             format!("Launcher not found at {}", launcher_path.display()),
         ));
     }
-    fs::create_dir_all(&platform.install_dir).map_err(|err| {
+    fs::create_dir_all(install_dir).map_err(|err| {
         let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
             ErrorCode::Permission
         } else {
@@ -69,12 +165,7 @@ pub fn install_launcher(mode: InstallMode, launcher_path: &Path) -> Result<PathB
         CoreError::new(code, format!("Failed to create install directory: {err}"))
     })?;
 
-    let launcher_name = if platform.is_windows {
-        "envhub-launcher.exe"
-    } else {
-        "envhub-launcher"
-    };
-    let dest = platform.install_dir.join(launcher_name);
+    let dest = install_dir.join(launcher_file_name_for(name.trim()));
     fs::copy(launcher_path, &dest).map_err(|err| {
         let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
             ErrorCode::Permission
@@ -98,8 +189,95 @@ pub fn install_launcher(mode: InstallMode, launcher_path: &Path) -> Result<PathB
     Ok(dest)
 }
 
+/// The name the launcher is built and shipped under.
+pub const DEFAULT_LAUNCHER_NAME: &str = "envhub-launcher";
+
+/// The launcher's name on this machine: `ENVHUB_LAUNCHER_NAME` when set,
+/// else the name recorded by [`install_launcher_as`], else
+/// [`DEFAULT_LAUNCHER_NAME`]. The state is only read when the variable is
+/// unset.
+pub fn launcher_name() -> String {
+    launcher_env_name().unwrap_or_else(|| {
+        let recorded = default_state_path()
+            .and_then(|path| path.load())
+            .ok()
+            .and_then(|state| state.launcher_name);
+        launcher_name_from(None, recorded)
+    })
+}
+
+/// [`launcher_name`] for an already loaded `state`.
+pub fn launcher_name_in(state: &State) -> String {
+    launcher_name_from(launcher_env_name(), state.launcher_name.clone())
+}
+
+fn launcher_env_name() -> Option<String> {
+    std::env::var("ENVHUB_LAUNCHER_NAME")
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn launcher_name_from(value: Option<String>, recorded: Option<String>) -> String {
+    [value, recorded]
+        .into_iter()
+        .flatten()
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_LAUNCHER_NAME.to_string())
+}
+
+/// Records `name` as the launcher's name, as [`install_launcher_as`] does.
+/// The default name clears the record.
+pub fn set_launcher_name(name: &str) -> Result<(), CoreError> {
+    let path = default_state_path()?;
+    set_launcher_name_in(&path, name)
+}
+
+pub fn set_launcher_name_in(
+    store: &(impl StateStore + ?Sized),
+    name: &str,
+) -> Result<(), CoreError> {
+    let mut state = store.load()?;
+    let name = name.trim();
+    state.launcher_name =
+        (!name.is_empty() && name != DEFAULT_LAUNCHER_NAME).then(|| name.to_string());
+    save_and_notify(store, &state, StateChange::StateSaved)
+}
+
+/// The shipped launcher's file name, [`DEFAULT_LAUNCHER_NAME`] with the
+/// platform's executable suffix.
+pub fn launcher_file_name() -> &'static str {
+    if cfg!(windows) {
+        "envhub-launcher.exe"
+    } else {
+        DEFAULT_LAUNCHER_NAME
+    }
+}
+
+/// `name` with the platform's executable suffix.
+pub fn launcher_file_name_for(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{name}.exe")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Whether `file_name` is a launcher's: the default name or, failing that,
+/// the configured or recorded one.
+pub(crate) fn is_launcher_file_name(file_name: &std::ffi::OsStr) -> bool {
+    file_name == launcher_file_name()
+        || file_name == launcher_file_name_for(&launcher_name()).as_str()
+}
+
 pub fn get_launcher_path() -> Option<PathBuf> {
-    which::which("envhub-launcher").ok()
+    find_launcher(&launcher_name(), std::env::var_os("PATH"))
+}
+
+fn find_launcher(name: &str, paths: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    which::which_in(name, paths, cwd).ok()
 }
 
 pub fn is_shim_installed(name: &str, mode: InstallMode) -> bool {
@@ -112,51 +290,476 @@ pub fn is_shim_installed(name: &str, mode: InstallMode) -> bool {
         return false;
     };
 
-    // Construct expected path
-    let shim_path = if cfg!(windows) {
-        platform.install_dir.join(format!("{name}.exe"))
+    is_shim_installed_in(&platform.install_dir, name)
+}
+
+/// Whether `install_dir` holds an envhub shim for `name`, not just any file of
+/// that name. On unix the shim must be a link resolving to `envhub-launcher`;
+/// on Windows it must be a copy of a launcher build, told by
+/// [`LAUNCHER_MAGIC`] wherever that launcher was copied from: shims under a
+/// custom `install_path` have no launcher beside them.
+pub fn is_shim_installed_in(install_dir: &Path, name: &str) -> bool {
+    if name.trim().is_empty() {
+        return false;
+    }
+    let shim = shim_path_in(install_dir, name);
+    if !shim.exists() {
+        return false;
+    }
+    if cfg!(windows) {
+        return is_launcher_copy(&shim);
+    }
+    crate::is_envhub_shim(&shim)
+}
+
+/// Deletes `name`'s shim from `install_dir` when it is an envhub shim, a
+/// link to a moved launcher included; anything else there is left alone.
+/// Returns whether a shim was removed.
+pub fn remove_shim_in(install_dir: &Path, name: &str) -> Result<bool, CoreError> {
+    let shim = shim_path_in(install_dir, name);
+    if !is_shim_installed_in(install_dir, name) && !links_to_launcher(&shim) {
+        return Ok(false);
+    }
+    fs::remove_file(&shim).map_err(|err| {
+        let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
+            ErrorCode::Permission
+        } else {
+            ErrorCode::Io
+        };
+        CoreError::new(code, format!("Failed to remove shim: {err}"))
+    })?;
+    Ok(true)
+}
+
+/// The path a shim for `name` occupies inside `install_dir`.
+pub fn shim_path_in(install_dir: &Path, name: &str) -> PathBuf {
+    if cfg!(windows) {
+        install_dir.join(format!("{name}.exe"))
     } else {
-        platform.install_dir.join(name)
-    };
+        install_dir.join(name)
+    }
+}
+
+/// Where an app's shim is, for showing "installed at ~/.envhub/bin/claudex
+/// (on PATH ✓)".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShimLocation {
+    pub name: String,
+    /// The shim under the app's `install_path`, else under the mode's
+    /// install directory.
+    pub shim_path: PathBuf,
+    pub installed: bool,
+    /// Whether the shim's directory is on `PATH`, so the bare name runs it.
+    pub on_path: bool,
+}
+
+/// [`ShimLocation`]s for `names`, in order, against the current `PATH`.
+pub fn shim_locations(names: &[String], mode: InstallMode) -> Result<Vec<ShimLocation>, CoreError> {
+    let state = load_state_for_install()?;
+    let default_dir = detect_platform(mode)?.install_dir;
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    Ok(shim_locations_in(&state, names, &default_dir, &path_var))
+}
+
+pub fn shim_locations_in(
+    state: &State,
+    names: &[String],
+    default_dir: &Path,
+    path_var: &std::ffi::OsStr,
+) -> Vec<ShimLocation> {
+    names
+        .iter()
+        .map(|name| {
+            let install_dir = state
+                .apps
+                .get(name)
+                .and_then(|app| app.install_path.as_deref())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| default_dir.to_path_buf());
+            ShimLocation {
+                name: name.clone(),
+                shim_path: shim_path_in(&install_dir, name),
+                installed: is_shim_installed_in(&install_dir, name),
+                on_path: dir_on_path(&install_dir, path_var),
+            }
+        })
+        .collect()
+}
+
+/// Whether `dir` is one of the entries of `path_var`. An entry may spell it
+/// differently, with a trailing separator, through `~` or `$HOME`, or via a
+/// symlink, so both sides are expanded and canonicalized where possible.
+pub(crate) fn dir_on_path(dir: &Path, path_var: &std::ffi::OsStr) -> bool {
+    let dir = normalize_dir(dir);
+    std::env::split_paths(path_var)
+        .filter(|entry| !entry.as_os_str().is_empty())
+        .any(|entry| {
+            let entry = entry
+                .to_str()
+                .and_then(|entry| crate::expand_path(entry).ok())
+                .map(PathBuf::from)
+                .unwrap_or(entry);
+            normalize_dir(&entry) == dir
+        })
+}
 
-    shim_path.exists()
+/// `path` with symlinks resolved if it exists, else just with `.` and
+/// repeated or trailing separators dropped.
+fn normalize_dir(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.components().collect())
 }
 
 pub fn is_launcher_installed() -> bool {
-    which::which("envhub-launcher").is_ok()
+    get_launcher_path().is_some()
 }
 
 pub fn is_user_path_configured() -> bool {
-    let Ok(platform) = detect_platform(InstallMode::User) else {
+    is_install_dir_on_path(InstallMode::User)
+}
+
+/// Whether `mode`'s install directory is on `PATH`, so its shims run by name.
+pub fn is_install_dir_on_path(mode: InstallMode) -> bool {
+    let Ok(platform) = detect_platform(mode) else {
         return false;
     };
-    let install_dir = platform.install_dir.to_string_lossy().to_string();
+    std::env::var_os("PATH").is_some_and(|path_var| dir_on_path(&platform.install_dir, &path_var))
+}
+
+/// Returns the shell startup file that PATH changes should go to, following
+/// the same rules as `install.sh`.
+pub fn shell_config_path() -> Result<PathBuf, CoreError> {
+    let home = dirs::home_dir().ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::InstallPath,
+            "Failed to resolve home directory".to_string(),
+        )
+    })?;
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    shell_config_path_for(&shell, &home)
+}
 
-    // Simple check: see if PATH contains the install dir string.
-    // This is not perfect (e.g. symlinks, edge cases), but good enough for a UI hint.
-    if let Ok(path_var) = std::env::var("PATH") {
-        // Handle standard PATH separators (: on Unix, ; on Windows)
-        // We can just iterate the split results
-        let separator = if cfg!(windows) { ';' } else { ':' };
-        return path_var.split(separator).any(|p| p == install_dir);
+/// Shells that never read the POSIX startup files and use their own PATH
+/// syntax, so an `export PATH=...` line would silently do nothing.
+const NON_POSIX_SHELLS: &[&str] = &["fish", "csh", "tcsh", "nu", "elvish", "xonsh"];
+
+fn shell_config_path_for(shell: &str, home: &Path) -> Result<PathBuf, CoreError> {
+    let name = shell.rsplit('/').next().unwrap_or_default();
+    if NON_POSIX_SHELLS.contains(&name) {
+        return Err(CoreError::new(
+            ErrorCode::Unsupported,
+            format!("Automatic PATH setup is not supported for the {name} shell"),
+        ));
     }
-    false
+    let file = match name {
+        "zsh" => ".zshrc",
+        "bash" => ".bashrc",
+        _ => ".profile",
+    };
+    Ok(home.join(file))
 }
 
-pub fn install_shim(
+fn path_export_line(install_dir: &Path) -> String {
+    let dir = match dirs::home_dir()
+        .and_then(|home| install_dir.strip_prefix(home).ok().map(Path::to_path_buf))
+    {
+        Some(relative) => format!("$HOME/{}", relative.display()),
+        None => install_dir.display().to_string(),
+    };
+    format!("export PATH=\"{dir}:$PATH\"")
+}
+
+/// Appends an `export PATH=...` line for `install_dir` to `shell_config`.
+///
+/// Returns `false` without touching the file when the line is already there,
+/// so calling it repeatedly is safe.
+pub fn configure_user_path_in(install_dir: &Path, shell_config: &Path) -> Result<bool, CoreError> {
+    let line = path_export_line(install_dir);
+    let existing = match fs::read_to_string(shell_config) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to read {}: {err}", shell_config.display()),
+            ));
+        }
+    };
+    if existing
+        .lines()
+        .any(|existing_line| existing_line.trim() == line)
+    {
+        return Ok(false);
+    }
+    let mut updated = existing;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str("\n# Added by EnvHub\n");
+    updated.push_str(&line);
+    updated.push('\n');
+    fs::write(shell_config, updated).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to update {}: {err}", shell_config.display()),
+        )
+    })?;
+    Ok(true)
+}
+
+/// Where Windows keeps the user's `Path`; reported as the "config" that
+/// `ensure_setup` changed there.
+pub const WINDOWS_USER_ENV_KEY: &str = r"HKCU\Environment";
+
+/// Adds `install_dir` to the user's PATH the way the platform expects: the
+/// `Path` value under `HKCU\Environment` on Windows (new shells are told via
+/// `WM_SETTINGCHANGE`), the shell startup file elsewhere. Returns whether
+/// anything changed; running it again is a no-op.
+pub fn configure_user_path(install_dir: &Path) -> Result<bool, CoreError> {
+    #[cfg(windows)]
+    {
+        windows_path::add_to_user_path(install_dir)
+    }
+    #[cfg(not(windows))]
+    {
+        configure_user_path_in(install_dir, &shell_config_path()?)
+    }
+}
+
+/// `current` (a `;`-separated Windows PATH) with `dir` appended, or `None`
+/// when it is already listed. Entries compare case-insensitively and ignore
+/// trailing separators, as Windows does.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn windows_path_with(current: &str, dir: &Path) -> Option<String> {
+    fn normalize(entry: &str) -> String {
+        entry
+            .trim()
+            .trim_end_matches(['\\', '/'])
+            .replace('/', "\\")
+            .to_lowercase()
+    }
+    let dir = dir.to_string_lossy();
+    let wanted = normalize(&dir);
+    if current.split(';').any(|entry| normalize(entry) == wanted) {
+        return None;
+    }
+    let current = current.trim_end_matches(';');
+    Some(if current.is_empty() {
+        dir.into_owned()
+    } else {
+        format!("{current};{dir}")
+    })
+}
+
+#[cfg(windows)]
+mod windows_path {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        HWND_BROADCAST, SMTO_ABORTIFHUNG, SendMessageTimeoutW, WM_SETTINGCHANGE,
+    };
+    use winreg::RegKey;
+    use winreg::RegValue;
+    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ, KEY_WRITE, RegType};
+
+    use crate::{CoreError, ErrorCode};
+
+    fn wide(value: &str) -> Vec<u16> {
+        OsStr::new(value).encode_wide().chain(Some(0)).collect()
+    }
+
+    fn registry_error(action: &str, err: std::io::Error) -> CoreError {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to {action} {}: {err}", super::WINDOWS_USER_ENV_KEY),
+        )
+    }
+
+    pub(super) fn add_to_user_path(install_dir: &Path) -> Result<bool, CoreError> {
+        let env = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
+            .map_err(|err| registry_error("open", err))?;
+        let current: String = match env.get_value("Path") {
+            Ok(current) => current,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(registry_error("read", err)),
+        };
+        let Some(updated) = super::windows_path_with(&current, install_dir) else {
+            return Ok(false);
+        };
+        // REG_EXPAND_SZ keeps entries like %USERPROFILE%\bin working.
+        let bytes = wide(&updated)
+            .into_iter()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        env.set_raw_value(
+            "Path",
+            &RegValue {
+                bytes,
+                vtype: RegType::REG_EXPAND_SZ,
+            },
+        )
+        .map_err(|err| registry_error("update", err))?;
+        broadcast_environment_change();
+        Ok(true)
+    }
+
+    /// Tells Explorer (and so newly started shells) to re-read the user
+    /// environment. Best effort: the registry already holds the change.
+    fn broadcast_environment_change() {
+        let section = wide("Environment");
+        let mut result = 0;
+        // SAFETY: `section` is a NUL-terminated UTF-16 string that outlives
+        // the call, and `result` is a valid out-pointer.
+        unsafe {
+            SendMessageTimeoutW(
+                HWND_BROADCAST,
+                WM_SETTINGCHANGE,
+                0,
+                section.as_ptr() as isize,
+                SMTO_ABORTIFHUNG,
+                5000,
+                &mut result,
+            );
+        }
+    }
+}
+
+/// What `ensure_setup` actually changed on this machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SetupResult {
+    pub launcher_path: PathBuf,
+    pub launcher_installed: bool,
+    pub install_dir: PathBuf,
+    pub path_configured_in: Option<PathBuf>,
+}
+
+impl SetupResult {
+    pub fn summary(&self) -> String {
+        match (self.launcher_installed, &self.path_configured_in) {
+            (true, Some(config)) => format!(
+                "Installed launcher and added {} to {}",
+                self.install_dir.display(),
+                config.display()
+            ),
+            (true, None) => format!("Installed launcher to {}", self.install_dir.display()),
+            (false, Some(config)) => format!(
+                "Added {} to {}",
+                self.install_dir.display(),
+                config.display()
+            ),
+            (false, None) => "Launcher already set up".to_string(),
+        }
+    }
+}
+
+/// Performs first-run setup: installs the launcher if it is missing and, in
+/// user mode, adds the install directory to the shell's PATH (on Windows,
+/// the user `Path` in the registry). Each step is skipped when already done,
+/// and the PATH step is skipped for shells it cannot configure.
+pub fn ensure_setup(launcher_path: &Path, mode: InstallMode) -> Result<SetupResult, CoreError> {
+    let platform = detect_platform(mode)?;
+    if platform.is_windows {
+        let mut result = ensure_setup_in(launcher_path, &platform.install_dir, None)?;
+        if mode == InstallMode::User && configure_user_path(&platform.install_dir)? {
+            result.path_configured_in = Some(PathBuf::from(WINDOWS_USER_ENV_KEY));
+        }
+        return Ok(result);
+    }
+    let shell_config = if mode == InstallMode::User && !is_user_path_configured() {
+        match shell_config_path() {
+            Ok(path) => Some(path),
+            Err(err) if err.code == ErrorCode::Unsupported => None,
+            Err(err) => return Err(err),
+        }
+    } else {
+        None
+    };
+    ensure_setup_in(
+        launcher_path,
+        &platform.install_dir,
+        shell_config.as_deref(),
+    )
+}
+
+pub fn ensure_setup_in(
+    launcher_path: &Path,
+    install_dir: &Path,
+    shell_config: Option<&Path>,
+) -> Result<SetupResult, CoreError> {
+    let installed_path = install_dir.join(launcher_file_name_for(&launcher_name()));
+    let launcher_installed = !installed_path.exists();
+    if launcher_installed {
+        install_launcher_in(install_dir, launcher_path)?;
+    }
+    let path_configured_in = match shell_config {
+        Some(config) if configure_user_path_in(install_dir, config)? => Some(config.to_path_buf()),
+        _ => None,
+    };
+    Ok(SetupResult {
+        launcher_path: installed_path,
+        launcher_installed,
+        install_dir: install_dir.to_path_buf(),
+        path_configured_in,
+    })
+}
+
+/// How a shim is put in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShimStrategy {
+    /// A symlink to the launcher (unix).
+    Symlink,
+    /// A copy of the launcher binary (Windows).
+    Copy,
+}
+
+fn shim_strategy(windows: bool) -> ShimStrategy {
+    if windows {
+        ShimStrategy::Copy
+    } else {
+        ShimStrategy::Symlink
+    }
+}
+
+/// What `install_shim_in` would do, computed without touching the disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstallPlan {
+    pub dest: PathBuf,
+    pub strategy: ShimStrategy,
+    /// Something already exists at `dest` (including a dangling symlink)
+    /// and would be replaced.
+    pub overwrites: bool,
+    /// What would be replaced is an envhub shim, so nothing is lost.
+    pub replaces_shim: bool,
+}
+
+impl InstallPlan {
+    /// A confirmation prompt when the install would replace a file that isn't
+    /// an envhub shim, e.g. "This will overwrite /usr/local/bin/node".
+    pub fn warning(&self) -> Option<String> {
+        (self.overwrites && !self.replaces_shim)
+            .then(|| format!("This will overwrite {}", self.dest.display()))
+    }
+}
+
+pub fn plan_install_shim(
     name: &str,
     mode: InstallMode,
     launcher_path: &Path,
-) -> Result<PathBuf, CoreError> {
+) -> Result<InstallPlan, CoreError> {
     let platform = detect_platform(mode)?;
-    install_shim_in(name, &platform.install_dir, launcher_path)
+    plan_install_shim_in(name, &platform.install_dir, launcher_path)
 }
 
-pub fn install_shim_in(
+/// Dry run of `install_shim_in`: fails the same way for an empty name or a
+/// missing launcher, otherwise reports the plan.
+pub fn plan_install_shim_in(
     name: &str,
     install_dir: &Path,
     launcher_path: &Path,
-) -> Result<PathBuf, CoreError> {
+) -> Result<InstallPlan, CoreError> {
     if name.trim().is_empty() {
         return Err(CoreError::new(
             ErrorCode::InvalidState,
@@ -169,66 +772,409 @@ pub fn install_shim_in(
             format!("Launcher not found at {}", launcher_path.display()),
         ));
     }
-    fs::create_dir_all(install_dir).map_err(|err| {
-        let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
-            ErrorCode::Permission
-        } else {
-            ErrorCode::InstallPath
-        };
-        CoreError::new(code, format!("Failed to create install directory: {err}"))
-    })?;
-
-    if cfg!(windows) {
-        let dest = install_dir.join(format!("{name}.exe"));
-        fs::copy(launcher_path, &dest).map_err(|err| {
-            let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
-                ErrorCode::Permission
-            } else {
-                ErrorCode::Io
-            };
-            CoreError::new(code, format!("Failed to copy shim: {err}"))
-        })?;
-        return Ok(dest);
-    }
+    let dest = shim_path_in(install_dir, name);
+    let overwrites = fs::symlink_metadata(&dest).is_ok();
+    Ok(InstallPlan {
+        strategy: shim_strategy(cfg!(windows)),
+        overwrites,
+        replaces_shim: overwrites && is_replaceable_shim(install_dir, name, launcher_path),
+        dest,
+    })
+}
 
-    let dest = install_dir.join(name);
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs as unix_fs;
-        if dest.exists() {
-            fs::remove_file(&dest).map_err(|err| {
-                CoreError::new(ErrorCode::Io, format!("Failed to replace shim: {err}"))
-            })?;
-        }
-        unix_fs::symlink(launcher_path, &dest).map_err(|err| {
-            let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
-                ErrorCode::Permission
-            } else {
-                ErrorCode::Io
-            };
-            CoreError::new(code, format!("Failed to create shim: {err}"))
-        })?;
+/// Whether the existing file at `name`'s shim path can be replaced without
+/// `force`: a symlink to a launcher, or a byte-for-byte copy of
+/// `launcher_path`. Copies of an older launcher need `force`; `reshim_all`
+/// passes it for the ones it identified.
+fn is_replaceable_shim(install_dir: &Path, name: &str, launcher_path: &Path) -> bool {
+    let shim = shim_path_in(install_dir, name);
+    let Ok(meta) = fs::symlink_metadata(&shim) else {
+        return false;
+    };
+    if meta.file_type().is_symlink() {
+        return crate::is_envhub_shim(&shim) || links_to_launcher(&shim);
     }
-    Ok(dest)
+    matches!(
+        (fs::read(&shim), fs::read(launcher_path)),
+        (Ok(shim_bytes), Ok(launcher_bytes)) if shim_bytes == launcher_bytes
+    )
 }
 
-pub fn install_shim_for_state(
-    state: &State,
+pub fn install_shim(
     name: &str,
     mode: InstallMode,
     launcher_path: &Path,
 ) -> Result<PathBuf, CoreError> {
-    let app = state.apps.get(name).ok_or_else(|| {
-        CoreError::new(
-            ErrorCode::AppNotFound,
-            format!("App \"{name}\" is not registered"),
-        )
-    })?;
-    let install_dir = match &app.install_path {
-        Some(path) => PathBuf::from(path),
+    install_shim_with(name, mode, launcher_path, false)
+}
+
+/// Installs the shim, refusing with `AlreadyExists` when a file that is not
+/// an envhub shim already occupies its path, e.g. a real `node` binary. Use
+/// `install_shim_with_in` with `force` to replace it anyway.
+pub fn install_shim_in(
+    name: &str,
+    install_dir: &Path,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    install_shim_with_in(name, install_dir, launcher_path, false)
+}
+
+pub fn install_shim_with(
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+    force: bool,
+) -> Result<PathBuf, CoreError> {
+    let platform = detect_platform(mode)?;
+    install_shim_with_in(name, &platform.install_dir, launcher_path, force).map_err(|err| {
+        if mode == InstallMode::Global {
+            with_elevation_hint(err, name, &platform.install_dir, launcher_path)
+        } else {
+            err
+        }
+    })
+}
+
+/// Adds the command that installs the shim as root to a `Permission` error,
+/// e.g. for `/usr/local/bin`. Other errors, and Windows, are left as is.
+fn with_elevation_hint(
+    err: CoreError,
+    name: &str,
+    install_dir: &Path,
+    launcher_path: &Path,
+) -> CoreError {
+    if err.code != ErrorCode::Permission || cfg!(windows) {
+        return err;
+    }
+    CoreError::new(
+        err.code,
+        format!(
+            "{}; to install it as root, run: {}",
+            err.message,
+            elevated_install_command(name, install_dir, launcher_path)
+        ),
+    )
+}
+
+/// The shell command that creates the shim with `sudo` on unix, with paths
+/// quoted as needed. When a file that isn't an envhub shim is already there,
+/// the command leaves out `-f`, so `ln` refuses rather than replaces it.
+pub fn elevated_install_command(name: &str, install_dir: &Path, launcher_path: &Path) -> String {
+    let dest = install_dir.join(name);
+    let foreign = fs::symlink_metadata(&dest).is_ok()
+        && !is_replaceable_shim(install_dir, name, launcher_path);
+    format!(
+        "sudo ln {} {} {}",
+        if foreign { "-s" } else { "-sf" },
+        shell_quote(&launcher_path.to_string_lossy()),
+        shell_quote(&dest.to_string_lossy())
+    )
+}
+
+pub(crate) fn shell_quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+:=@,".contains(c));
+    if plain {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+/// Installs the shim into the `Global` directory by running `ln` through
+/// `sudo`, or `pkexec` where there is no `sudo`, which may prompt for a
+/// password. Meant for terminal callers; a full-screen UI must leave raw
+/// mode first. Like `install_shim_with`, it refuses to replace a file that
+/// isn't an envhub shim unless `force` is set.
+pub fn install_shim_elevated(
+    name: &str,
+    launcher_path: &Path,
+    force: bool,
+) -> Result<PathBuf, CoreError> {
+    let platform = detect_platform(InstallMode::Global)?;
+    install_shim_elevated_in(name, &platform.install_dir, launcher_path, force)
+}
+
+pub fn install_shim_elevated_in(
+    name: &str,
+    install_dir: &Path,
+    launcher_path: &Path,
+    force: bool,
+) -> Result<PathBuf, CoreError> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!("\"{name}\" is not a valid shim name"),
+        ));
+    }
+    if cfg!(windows) {
+        return Err(CoreError::new(
+            ErrorCode::Unsupported,
+            "Elevated installs are not supported on Windows".to_string(),
+        ));
+    }
+    let plan = plan_install_shim_in(name, install_dir, launcher_path)?;
+    if plan.overwrites && !plan.replaces_shim && !force {
+        return Err(CoreError::new(
+            ErrorCode::AlreadyExists,
+            format!(
+                "{} already exists and is not an envhub shim; refusing to overwrite it",
+                plan.dest.display()
+            ),
+        ));
+    }
+    let dest = plan.dest;
+    let elevator = ["sudo", "pkexec"]
+        .into_iter()
+        .find_map(|tool| which::which(tool).ok())
+        .ok_or_else(|| {
+            CoreError::new(
+                ErrorCode::Unsupported,
+                "Neither sudo nor pkexec is available".to_string(),
+            )
+        })?;
+    let status = std::process::Command::new(&elevator)
+        .arg("ln")
+        .arg("-sf")
+        .arg(launcher_path)
+        .arg(&dest)
+        .status()
+        .map_err(|err| {
+            CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to run {}: {err}", elevator.display()),
+            )
+        })?;
+    if !status.success() {
+        return Err(CoreError::new(
+            ErrorCode::Permission,
+            format!(
+                "{} ln exited with {status}; the shim was not installed",
+                elevator.display()
+            ),
+        ));
+    }
+    Ok(dest)
+}
+
+pub fn install_shim_with_in(
+    name: &str,
+    install_dir: &Path,
+    launcher_path: &Path,
+    force: bool,
+) -> Result<PathBuf, CoreError> {
+    let plan = plan_install_shim_in(name, install_dir, launcher_path)?;
+    if plan.overwrites && !plan.replaces_shim && !force {
+        return Err(CoreError::new(
+            ErrorCode::AlreadyExists,
+            format!(
+                "{} already exists and is not an envhub shim; refusing to overwrite it",
+                plan.dest.display()
+            ),
+        ));
+    }
+    fs::create_dir_all(install_dir).map_err(|err| {
+        let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
+            ErrorCode::Permission
+        } else {
+            ErrorCode::InstallPath
+        };
+        CoreError::new(code, format!("Failed to create install directory: {err}"))
+    })?;
+
+    if cfg!(windows) {
+        let dest = install_dir.join(format!("{name}.exe"));
+        fs::copy(launcher_path, &dest).map_err(|err| {
+            let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
+                ErrorCode::Permission
+            } else {
+                ErrorCode::Io
+            };
+            CoreError::new(code, format!("Failed to copy shim: {err}"))
+        })?;
+        return Ok(dest);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs as unix_fs;
+        let dest = install_dir.join(name);
+        // `symlink_metadata` so a dangling link to a moved launcher is
+        // replaced too.
+        if fs::symlink_metadata(&dest).is_ok() {
+            fs::remove_file(&dest).map_err(|err| {
+                let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
+                    ErrorCode::Permission
+                } else {
+                    ErrorCode::Io
+                };
+                CoreError::new(code, format!("Failed to replace shim: {err}"))
+            })?;
+        }
+        unix_fs::symlink(launcher_path, &dest).map_err(|err| {
+            let code = if err.kind() == std::io::ErrorKind::PermissionDenied {
+                ErrorCode::Permission
+            } else {
+                ErrorCode::Io
+            };
+            CoreError::new(code, format!("Failed to create shim: {err}"))
+        })?;
+        Ok(dest)
+    }
+    #[cfg(not(unix))]
+    {
+        Err(CoreError::new(
+            ErrorCode::Unsupported,
+            "Installing shims is not supported on this platform".to_string(),
+        ))
+    }
+}
+
+pub fn install_shim_for_state(
+    state: &State,
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    install_app_shims(state, name, mode, launcher_path, false)
+}
+
+/// Installs the shims of `name` and its aliases. With `only_stale`, just the
+/// shims left by another launcher build are installed again, replacing them
+/// even where that takes `force`.
+fn install_app_shims(
+    state: &State,
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+    only_stale: bool,
+) -> Result<PathBuf, CoreError> {
+    let app = state.apps.get(name).ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::AppNotFound,
+            format!("App \"{name}\" is not registered"),
+        )
+    })?;
+    let install_dir = match &app.install_path {
+        Some(path) => PathBuf::from(path),
         None => detect_platform(mode)?.install_dir,
     };
-    install_shim_in(name, &install_dir, launcher_path)
+    let global = mode == InstallMode::Global && app.install_path.is_none();
+    let install = |shim_name: &str| {
+        let shim = shim_path_in(&install_dir, shim_name);
+        if only_stale && !is_stale_shim(&shim, launcher_path) {
+            return Ok(shim);
+        }
+        install_shim_with_in(shim_name, &install_dir, launcher_path, only_stale).map_err(|err| {
+            if global {
+                with_elevation_hint(err, shim_name, &install_dir, launcher_path)
+            } else {
+                err
+            }
+        })
+    };
+    let shim = install(name)?;
+    for alias in &app.aliases {
+        install(alias)?;
+    }
+    Ok(shim)
+}
+
+/// Installs the app's shim like `install_shim_for_state`, then records
+/// `installed = true` and the directory actually used as `install_path`, so
+/// the saved config matches what is on disk.
+pub fn install_shim_and_record(
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    let path = default_state_path()?;
+    install_shim_and_record_in(&path, name, mode, launcher_path)
+}
+
+pub fn install_shim_and_record_in(
+    path: &Path,
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    install_shim_and_record_returning_state_in(path, name, mode, launcher_path)
+        .map(|(shim_path, _)| shim_path)
+}
+
+/// Like `install_shim_and_record`, and also returns the state as saved.
+pub fn install_shim_and_record_returning_state(
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<(PathBuf, State), CoreError> {
+    let path = default_state_path()?;
+    install_shim_and_record_returning_state_in(&path, name, mode, launcher_path)
+}
+
+pub fn install_shim_and_record_returning_state_in(
+    path: &Path,
+    name: &str,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<(PathBuf, State), CoreError> {
+    record_install(path, name, Some(mode), launcher_path)
+}
+
+/// Like `install_shim_and_record`, in the state's `default_install_mode`.
+pub fn install_shim_and_record_default(
+    name: &str,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    let path = default_state_path()?;
+    install_shim_and_record_default_in(&path, name, launcher_path)
+}
+
+pub fn install_shim_and_record_default_in(
+    path: &Path,
+    name: &str,
+    launcher_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    record_install(path, name, None, launcher_path).map(|(shim_path, _)| shim_path)
+}
+
+fn record_install(
+    path: &Path,
+    name: &str,
+    mode: Option<InstallMode>,
+    launcher_path: &Path,
+) -> Result<(PathBuf, State), CoreError> {
+    let mut state = load_state_from_path(path)?;
+    let mode = state.install_mode(mode);
+    let shim_path = install_shim_for_state(&state, name, mode, launcher_path)?;
+    let default_dir = detect_platform(mode)
+        .ok()
+        .map(|platform| platform.install_dir);
+    if let Some(app) = state.apps.get_mut(name) {
+        mark_installed(app, &shim_path, default_dir.as_deref());
+    }
+    save_and_notify(
+        path,
+        &state,
+        StateChange::AppUpdated {
+            app: name.to_string(),
+        },
+    )?;
+    Ok((shim_path, state))
+}
+
+/// Marks `app` installed at `shim_path`. The directory is written to
+/// `install_path` only when it is not the mode's default, so an app installed
+/// in the default directory keeps following it.
+fn mark_installed(app: &mut AppConfig, shim_path: &Path, default_dir: Option<&Path>) {
+    app.installed = true;
+    if let Some(dir) = shim_path.parent()
+        && Some(dir) != default_dir
+    {
+        app.install_path = Some(dir.to_string_lossy().to_string());
+    }
 }
 
 pub fn load_state_for_install() -> Result<State, CoreError> {
@@ -236,11 +1182,163 @@ pub fn load_state_for_install() -> Result<State, CoreError> {
     load_state_from_path(&path)
 }
 
+/// Bytes only launcher builds contain: `envhub-launcher` keeps them in a
+/// static, so an outdated copy of the launcher can be told from an unrelated
+/// binary that shares the app's name. This crate holds them only reversed
+/// ([`LAUNCHER_MAGIC_REVERSED`]), so the TUI, the GUI and anything else
+/// linking it never match.
+pub const LAUNCHER_MAGIC: [u8; 32] = *b"\0envhub-launcher-build:7c1f4e2a\0";
+
+const LAUNCHER_MAGIC_REVERSED: [u8; LAUNCHER_MAGIC.len()] = reversed(LAUNCHER_MAGIC);
+
+const fn reversed<const N: usize>(bytes: [u8; N]) -> [u8; N] {
+    let mut out = [0; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = bytes[N - 1 - i];
+        i += 1;
+    }
+    out
+}
+
+/// Apps whose installed shim, or the shim of one of their aliases, is an
+/// older launcher than the one installed for `mode` and should be installed
+/// again.
+pub fn needs_reshim(mode: InstallMode) -> Result<Vec<String>, CoreError> {
+    let platform = detect_platform(mode)?;
+    let state = load_state_for_install()?;
+    let launcher_path = platform
+        .install_dir
+        .join(launcher_file_name_for(&launcher_name_in(&state)));
+    Ok(needs_reshim_for_state(&state, mode, &launcher_path))
+}
+
+pub fn needs_reshim_for_state(
+    state: &State,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Vec<String> {
+    let default_dir = detect_platform(mode)
+        .ok()
+        .map(|platform| platform.install_dir);
+    state
+        .apps
+        .iter()
+        .filter(|(name, app)| {
+            let install_dir = app
+                .install_path
+                .as_ref()
+                .map(PathBuf::from)
+                .or_else(|| default_dir.clone());
+            install_dir.is_some_and(|dir| {
+                std::iter::once(*name)
+                    .chain(&app.aliases)
+                    .any(|shim_name| is_stale_shim(&shim_path_in(&dir, shim_name), launcher_path))
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Reinstalls the stale shims, aliases included, of every app reported by
+/// `needs_reshim_for_state` against `launcher_path`, returning the apps that
+/// were updated.
+pub fn reshim_all(mode: InstallMode, launcher_path: &Path) -> Result<Vec<String>, CoreError> {
+    let path = default_state_path()?;
+    reshim_all_in(&path, mode, launcher_path)
+}
+
+pub fn reshim_all_in(
+    path: &Path,
+    mode: InstallMode,
+    launcher_path: &Path,
+) -> Result<Vec<String>, CoreError> {
+    let state = load_state_from_path(path)?;
+    let stale = needs_reshim_for_state(&state, mode, launcher_path);
+    for name in &stale {
+        install_app_shims(&state, name, mode, launcher_path, true)?;
+    }
+    Ok(stale)
+}
+
+/// A symlink shim is stale when it points at a launcher other than
+/// `launcher_path` (including one that no longer exists); a copied shim is
+/// stale when it is a launcher build whose bytes differ from it.
+pub(crate) fn is_stale_shim(shim: &Path, launcher_path: &Path) -> bool {
+    let Ok(meta) = fs::symlink_metadata(shim) else {
+        return false;
+    };
+    if meta.file_type().is_symlink() {
+        if !links_to_launcher(shim) {
+            return false;
+        }
+        return match (shim.canonicalize(), launcher_path.canonicalize()) {
+            (Ok(current), Ok(expected)) => current != expected,
+            _ => true,
+        };
+    }
+    match (fs::read(shim), fs::read(launcher_path)) {
+        (Ok(shim_bytes), Ok(launcher_bytes)) => {
+            shim_bytes != launcher_bytes && is_launcher_build(&shim_bytes)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the symlink at `shim` names a launcher file, even one that has
+/// since moved or been deleted.
+fn links_to_launcher(shim: &Path) -> bool {
+    fs::read_link(shim)
+        .ok()
+        .and_then(|link| link.file_name().map(is_launcher_file_name))
+        .unwrap_or(false)
+}
+
+/// Whether `path` is a launcher build, by [`LAUNCHER_MAGIC`] rather than by
+/// name or any text the file happens to contain.
+fn is_launcher_copy(path: &Path) -> bool {
+    fs::read(path).is_ok_and(|bytes| is_launcher_build(&bytes))
+}
+
+fn is_launcher_build(bytes: &[u8]) -> bool {
+    // Rebuilt at run time so the forward bytes never land in this binary.
+    let magic = reversed(std::hint::black_box(LAUNCHER_MAGIC_REVERSED));
+    bytes.windows(magic.len()).any(|window| window == magic)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    /// Stand-in for a launcher binary of `version`.
+    fn launcher_build(version: &str) -> Vec<u8> {
+        [
+            format!("envhub-launcher {version}").as_bytes(),
+            &LAUNCHER_MAGIC,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn windows_path_with_appends_once() {
+        let dir = Path::new(r"C:\Users\me\AppData\Local\EnvHub\bin");
+        let updated = windows_path_with(r"C:\Tools;%USERPROFILE%\bin;", dir).expect("added");
+        assert_eq!(
+            updated,
+            r"C:\Tools;%USERPROFILE%\bin;C:\Users\me\AppData\Local\EnvHub\bin"
+        );
+        assert_eq!(windows_path_with(&updated, dir), None);
+        assert_eq!(
+            windows_path_with(r"c:\users\ME\appdata\local\envhub\BIN\;C:\Tools", dir),
+            None
+        );
+        assert_eq!(
+            windows_path_with("", dir).as_deref(),
+            Some(r"C:\Users\me\AppData\Local\EnvHub\bin")
+        );
+    }
+
     #[test]
     fn install_shim_in_creates_copy_or_symlink() {
         let dir = TempDir::new().expect("temp dir");
@@ -256,6 +1354,221 @@ mod tests {
         assert!(shim_path.exists());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn permission_errors_suggest_the_sudo_command() {
+        let launcher = Path::new("/opt/envhub/envhub-launcher");
+        let install_dir = Path::new("/usr/local/bin");
+        let denied = CoreError::new(
+            ErrorCode::Permission,
+            "Failed to create shim: Permission denied".to_string(),
+        );
+        let err = with_elevation_hint(denied, "node", install_dir, launcher);
+        assert_eq!(err.code, ErrorCode::Permission);
+        assert_eq!(
+            err.message,
+            "Failed to create shim: Permission denied; to install it as root, run: \
+             sudo ln -sf /opt/envhub/envhub-launcher /usr/local/bin/node"
+        );
+
+        let other = CoreError::new(ErrorCode::Io, "disk full".to_string());
+        let err = with_elevation_hint(other, "node", install_dir, launcher);
+        assert_eq!(err.message, "disk full");
+
+        let command =
+            elevated_install_command("it's", Path::new("/my bin"), Path::new("/opt/launcher"));
+        assert_eq!(command, r"sudo ln -sf /opt/launcher '/my bin/it'\''s'");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn elevated_installs_never_replace_foreign_files() {
+        let dir = TempDir::new().expect("temp dir");
+        let launcher = dir.path().join("envhub-launcher");
+        fs::write(&launcher, b"launcher").expect("launcher");
+        let install_dir = dir.path().join("bin");
+        fs::create_dir_all(&install_dir).expect("bin dir");
+        fs::write(install_dir.join("node"), b"real node").expect("node");
+
+        let command = elevated_install_command("node", &install_dir, &launcher);
+        assert!(command.starts_with("sudo ln -s "), "{command}");
+        let command = elevated_install_command("other", &install_dir, &launcher);
+        assert!(command.starts_with("sudo ln -sf "), "{command}");
+
+        let err = install_shim_elevated_in("node", &install_dir, &launcher, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+        assert_eq!(
+            fs::read(install_dir.join("node")).expect("node"),
+            b"real node"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn user_install_dir_prefers_xdg_bin_only_when_opted_in() {
+        let home = TempDir::new().expect("temp dir");
+        let local_bin = home.path().join(".local").join("bin");
+        let default_dir = home.path().join(".envhub").join("bin");
+        let path = format!("/usr/bin:{}", local_bin.display());
+        let vars = |pairs: Vec<(&'static str, String)>| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+        let dir_with = |pairs| user_install_dir_from(vars(pairs), home.path());
+
+        let xdg = ("XDG_BIN_HOME", "/xdg/bin".to_string());
+        assert_eq!(dir_with(vec![xdg.clone()]), default_dir);
+        let opt_in = ("ENVHUB_XDG_BIN", "1".to_string());
+        assert_eq!(
+            dir_with(vec![opt_in.clone(), xdg.clone()]),
+            Path::new("/xdg/bin")
+        );
+        assert_eq!(
+            dir_with(vec![("ENVHUB_XDG_BIN", "0".to_string()), xdg]),
+            default_dir
+        );
+
+        let on_path = ("PATH", path);
+        assert_eq!(dir_with(vec![opt_in.clone(), on_path.clone()]), default_dir);
+        fs::create_dir_all(&local_bin).expect("local bin");
+        assert_eq!(dir_with(vec![opt_in.clone(), on_path.clone()]), local_bin);
+        assert_eq!(
+            dir_with(vec![
+                opt_in.clone(),
+                ("XDG_BIN_HOME", "rel".to_string()),
+                on_path
+            ]),
+            local_bin
+        );
+        let trailing = ("PATH", format!("/usr/bin:{}/", local_bin.display()));
+        assert_eq!(dir_with(vec![opt_in.clone(), trailing]), local_bin);
+        assert_eq!(dir_with(vec![opt_in]), default_dir);
+    }
+
+    #[test]
+    fn launcher_can_be_installed_and_found_under_a_custom_name() {
+        assert_eq!(launcher_name_from(None, None), DEFAULT_LAUNCHER_NAME);
+        assert_eq!(
+            launcher_name_from(Some("  ".to_string()), None),
+            DEFAULT_LAUNCHER_NAME
+        );
+        assert_eq!(launcher_name_from(Some(" ehl ".to_string()), None), "ehl");
+        assert_eq!(
+            launcher_name_from(Some("env-name".to_string()), Some("ehl".to_string())),
+            "env-name"
+        );
+        assert_eq!(launcher_name_from(None, Some("ehl".to_string())), "ehl");
+
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let source = dir.path().join("build-output");
+        fs::write(&source, b"envhub-launcher").expect("launcher");
+
+        let installed = install_launcher_as_in(&install_dir, &source, "ehl").expect("install");
+        assert_eq!(installed, install_dir.join(launcher_file_name_for("ehl")));
+        assert!(installed.exists());
+        assert_eq!(
+            find_launcher("ehl", Some(install_dir.clone().into_os_string())),
+            Some(installed)
+        );
+        assert_eq!(
+            find_launcher(DEFAULT_LAUNCHER_NAME, Some(install_dir.into_os_string())),
+            None
+        );
+
+        let err = install_launcher_as_in(dir.path(), &source, "a/b").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+
+        // The name is kept in the state, so it is known without the variable.
+        let state_path = dir.path().join("state.json");
+        set_launcher_name_in(&state_path, " ehl ").expect("record");
+        let state = load_state_from_path(&state_path).expect("load");
+        assert_eq!(state.launcher_name.as_deref(), Some("ehl"));
+        set_launcher_name_in(&state_path, DEFAULT_LAUNCHER_NAME).expect("reset");
+        let state = load_state_from_path(&state_path).expect("load");
+        assert_eq!(state.launcher_name, None);
+    }
+
+    #[test]
+    fn plan_install_shim_detects_overwrites() {
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let launcher = install_dir.join(launcher_file_name());
+        fs::create_dir_all(&install_dir).expect("bin");
+        fs::write(&launcher, b"binary").expect("launcher");
+
+        let plan = plan_install_shim_in("tool", &install_dir, &launcher).expect("plan");
+        assert_eq!(plan.dest, shim_path_in(&install_dir, "tool"));
+        assert!(!plan.overwrites);
+        assert_eq!(plan.warning(), None);
+        assert!(!plan.dest.exists(), "planning must not install");
+
+        install_shim_in("tool", &install_dir, &launcher).expect("shim");
+        let plan = plan_install_shim_in("tool", &install_dir, &launcher).expect("plan");
+        assert!(plan.overwrites && plan.replaces_shim);
+        assert_eq!(plan.warning(), None);
+
+        fs::write(shim_path_in(&install_dir, "node"), b"real node").expect("node");
+        let plan = plan_install_shim_in("node", &install_dir, &launcher).expect("plan");
+        assert!(plan.overwrites && !plan.replaces_shim);
+        assert_eq!(
+            plan.warning(),
+            Some(format!("This will overwrite {}", plan.dest.display()))
+        );
+
+        // An older launcher build is only replaced with force (or by reshim).
+        fs::write(shim_path_in(&install_dir, "old"), launcher_build("0.1.0")).expect("old");
+        let plan = plan_install_shim_in("old", &install_dir, &launcher).expect("plan");
+        assert!(plan.overwrites && !plan.replaces_shim);
+        fs::copy(&launcher, shim_path_in(&install_dir, "copy")).expect("copy");
+        let plan = plan_install_shim_in("copy", &install_dir, &launcher).expect("plan");
+        assert!(plan.overwrites && plan.replaces_shim);
+
+        let missing = dir.path().join("missing-launcher");
+        let err = plan_install_shim_in("tool", &install_dir, &missing).unwrap_err();
+        assert_eq!(err.code, ErrorCode::MissingLauncher);
+    }
+
+    #[test]
+    fn install_shim_refuses_foreign_files_unless_forced() {
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let launcher = dir.path().join(launcher_file_name());
+        fs::create_dir_all(&install_dir).expect("bin");
+        fs::write(&launcher, b"envhub-launcher 0.2.0").expect("launcher");
+        let node = shim_path_in(&install_dir, "node");
+        fs::write(&node, b"real node").expect("node");
+
+        let err = install_shim_in("node", &install_dir, &launcher).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+        assert_eq!(fs::read(&node).expect("node"), b"real node");
+
+        install_shim_with_in("node", &install_dir, &launcher, true).expect("forced");
+        assert_eq!(fs::read(&node).expect("shim"), b"envhub-launcher 0.2.0");
+        // Replacing our own shim never needs force.
+        install_shim_in("node", &install_dir, &launcher).expect("reinstall");
+    }
+
+    #[test]
+    fn shim_strategy_follows_platform() {
+        assert_eq!(shim_strategy(true), ShimStrategy::Copy);
+        assert_eq!(shim_strategy(false), ShimStrategy::Symlink);
+        let expected = if cfg!(windows) {
+            ShimStrategy::Copy
+        } else {
+            ShimStrategy::Symlink
+        };
+        let dir = TempDir::new().expect("temp dir");
+        let launcher = dir.path().join("launcher");
+        fs::write(&launcher, b"binary").expect("launcher");
+        let plan = plan_install_shim_in("tool", dir.path(), &launcher).expect("plan");
+        assert_eq!(plan.strategy, expected);
+    }
+
     #[test]
     fn install_shim_for_state_uses_custom_install_path() {
         let dir = TempDir::new().expect("temp dir");
@@ -269,6 +1582,7 @@ mod tests {
             AppConfig {
                 target_binary: "tool-bin".to_string(),
                 install_path: Some(custom_dir.to_string_lossy().to_string()),
+                aliases: vec!["toolx".to_string()],
                 ..AppConfig::default()
             },
         );
@@ -276,6 +1590,363 @@ mod tests {
         let shim_path =
             install_shim_for_state(&state, "tool", InstallMode::User, &launcher).expect("shim");
         assert!(shim_path.exists());
+        assert!(shim_path_in(&custom_dir, "toolx").exists());
+    }
+
+    #[test]
+    fn install_shim_and_record_updates_state() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let custom_dir = dir.path().join("custom");
+        let launcher = dir.path().join("launcher");
+        fs::write(&launcher, b"binary").expect("launcher");
+
+        let mut state = State::default();
+        state.apps.insert(
+            "tool".to_string(),
+            AppConfig {
+                target_binary: "tool-bin".to_string(),
+                install_path: Some(custom_dir.to_string_lossy().to_string()),
+                ..AppConfig::default()
+            },
+        );
+        crate::save_state_to_path(&path, &state).expect("save");
+
+        let shim_path = install_shim_and_record_in(&path, "tool", InstallMode::User, &launcher)
+            .expect("install");
+        assert_eq!(shim_path, shim_path_in(&custom_dir, "tool"));
+        let state = load_state_from_path(&path).expect("load");
+        let app = state.apps.get("tool").expect("app");
+        assert!(app.installed);
+        assert_eq!(
+            app.install_path.as_deref(),
+            Some(custom_dir.to_string_lossy().as_ref())
+        );
+
+        let err =
+            install_shim_and_record_in(&path, "missing", InstallMode::User, &launcher).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+    }
+
+    #[test]
+    fn copied_shims_are_recognised_without_a_launcher_beside_them() {
+        let dir = TempDir::new().expect("temp dir");
+        let copy = dir.path().join("tool.exe");
+        fs::write(&copy, launcher_build("0.1.0")).expect("copy");
+        assert!(is_launcher_copy(&copy));
+
+        let foreign = dir.path().join("other.exe");
+        fs::write(&foreign, b"MZ...some other tool...").expect("foreign");
+        assert!(!is_launcher_copy(&foreign));
+        // The TUI and GUI mention the launcher but are not copies of it.
+        fs::write(&foreign, b"MZ...runs envhub-launcher shims...").expect("tui");
+        assert!(!is_launcher_copy(&foreign));
+        assert!(!is_launcher_copy(&dir.path().join("missing.exe")));
+    }
+
+    #[test]
+    fn installs_in_the_default_dir_leave_install_path_unset() {
+        let default_dir = Path::new("/opt/envhub/bin");
+        let mut app = AppConfig::default();
+        mark_installed(
+            &mut app,
+            &shim_path_in(default_dir, "tool"),
+            Some(default_dir),
+        );
+        assert!(app.installed);
+        assert_eq!(app.install_path, None);
+
+        let custom_dir = Path::new("/srv/shims");
+        mark_installed(
+            &mut app,
+            &shim_path_in(custom_dir, "tool"),
+            Some(default_dir),
+        );
+        assert_eq!(app.install_path.as_deref(), Some("/srv/shims"));
+    }
+
+    #[test]
+    fn default_install_mode_round_trips_and_fills_in_for_installs() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let custom_dir = dir.path().join("custom");
+        let launcher = dir.path().join("launcher");
+        fs::write(&launcher, b"binary").expect("launcher");
+        assert_eq!(
+            default_install_mode_in(&path).expect("mode"),
+            InstallMode::User
+        );
+
+        set_default_install_mode_in(&path, InstallMode::Global).expect("set");
+        assert_eq!(
+            default_install_mode_in(&path).expect("mode"),
+            InstallMode::Global
+        );
+        let raw = fs::read_to_string(&path).expect("read");
+        assert!(
+            raw.contains("\"default_install_mode\": \"global\""),
+            "{raw}"
+        );
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.install_mode(None), InstallMode::Global);
+        assert_eq!(
+            state.install_mode(Some(InstallMode::User)),
+            InstallMode::User
+        );
+
+        crate::register_app_in(&path, "tool", "tool-bin").expect("register");
+        crate::set_app_install_path_in(&path, "tool", Some(custom_dir.clone())).expect("dir");
+        let shim_path =
+            install_shim_and_record_default_in(&path, "tool", &launcher).expect("install");
+        assert_eq!(shim_path, shim_path_in(&custom_dir, "tool"));
+
+        set_default_install_mode_in(&path, InstallMode::User).expect("reset");
+        let raw = fs::read_to_string(&path).expect("read");
+        assert!(!raw.contains("default_install_mode"), "{raw}");
+    }
+
+    #[test]
+    fn shim_locations_report_path_install_and_path_status() {
+        let dir = TempDir::new().expect("temp dir");
+        let default_dir = dir.path().join("bin");
+        let custom_dir = dir.path().join("custom");
+        fs::create_dir_all(&custom_dir).expect("custom dir");
+        let launcher = custom_dir.join(launcher_file_name());
+        fs::write(&launcher, b"launcher").expect("launcher");
+        install_shim_in("claudex", &custom_dir, &launcher).expect("shim");
+
+        let mut state = State::default();
+        for (name, install_path) in [("claudex", Some(&custom_dir)), ("node", None)] {
+            state.apps.insert(
+                name.to_string(),
+                AppConfig {
+                    target_binary: format!("{name}-bin"),
+                    install_path: install_path.map(|dir| dir.to_string_lossy().to_string()),
+                    ..AppConfig::default()
+                },
+            );
+        }
+        let path_var = std::env::join_paths([Path::new("/usr/bin"), &custom_dir]).expect("PATH");
+        let names = ["claudex", "node", "unknown"].map(str::to_string);
+
+        let locations = shim_locations_in(&state, &names, &default_dir, &path_var);
+        assert_eq!(
+            locations,
+            [
+                ShimLocation {
+                    name: "claudex".to_string(),
+                    shim_path: shim_path_in(&custom_dir, "claudex"),
+                    installed: true,
+                    on_path: true,
+                },
+                ShimLocation {
+                    name: "node".to_string(),
+                    shim_path: shim_path_in(&default_dir, "node"),
+                    installed: false,
+                    on_path: false,
+                },
+                ShimLocation {
+                    name: "unknown".to_string(),
+                    shim_path: shim_path_in(&default_dir, "unknown"),
+                    installed: false,
+                    on_path: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dir_on_path_ignores_trailing_separators_and_home_spelling() {
+        let dir = TempDir::new().expect("temp dir");
+        let bin = dir.path().join("bin");
+        let with_slash = format!("{}{}", bin.display(), std::path::MAIN_SEPARATOR);
+        let path_var =
+            std::env::join_paths([Path::new("/usr/bin"), Path::new(&with_slash)]).expect("PATH");
+        assert!(dir_on_path(&bin, &path_var));
+        assert!(!dir_on_path(&dir.path().join("other"), &path_var));
+
+        if let Some(home) = dirs::home_dir() {
+            let path_var = std::env::join_paths(["~/.envhub/bin/"]).expect("PATH");
+            assert!(dir_on_path(&home.join(".envhub").join("bin"), &path_var));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_on_path_follows_symlinked_directories() {
+        let dir = TempDir::new().expect("temp dir");
+        let real_home = dir.path().join("real-home");
+        fs::create_dir_all(real_home.join(".envhub").join("bin")).expect("bin");
+        let linked_home = dir.path().join("home");
+        std::os::unix::fs::symlink(&real_home, &linked_home).expect("symlink");
+
+        let install_dir = linked_home.join(".envhub").join("bin");
+        let path_var = real_home.join(".envhub").join("bin").into_os_string();
+        assert!(dir_on_path(&install_dir, &path_var));
+        let path_var = install_dir.clone().into_os_string();
+        assert!(dir_on_path(&real_home.join(".envhub/bin"), &path_var));
+    }
+
+    #[test]
+    fn is_shim_installed_rejects_unrelated_binary() {
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let launcher = install_dir.join(launcher_file_name());
+        fs::create_dir_all(&install_dir).expect("bin dir");
+        fs::write(&launcher, b"launcher").expect("launcher");
+
+        fs::write(shim_path_in(&install_dir, "node"), b"a real node").expect("node");
+        assert!(!is_shim_installed_in(&install_dir, "node"));
+        assert!(!is_shim_installed_in(&install_dir, "missing"));
+
+        install_shim_in("tool", &install_dir, &launcher).expect("shim");
+        assert!(is_shim_installed_in(&install_dir, "tool"));
+    }
+
+    #[test]
+    fn reshim_updates_shims_from_older_launcher() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let install_dir = dir.path().join("bin");
+        fs::create_dir_all(&install_dir).expect("bin dir");
+        let launcher = install_dir.join(launcher_file_name());
+        fs::write(&launcher, launcher_build("0.2.0")).expect("launcher");
+
+        let mut state = State::default();
+        for name in ["old", "fresh", "foreign", "linked"] {
+            state.apps.insert(
+                name.to_string(),
+                AppConfig {
+                    target_binary: format!("{name}-bin"),
+                    install_path: Some(install_dir.to_string_lossy().to_string()),
+                    ..AppConfig::default()
+                },
+            );
+        }
+        crate::save_state_to_path(&path, &state).expect("save");
+        // A copy left behind by the previous launcher version.
+        fs::write(shim_path_in(&install_dir, "old"), launcher_build("0.1.0")).expect("old");
+        install_shim_in("fresh", &install_dir, &launcher).expect("fresh");
+        // Not ours: must never be flagged or replaced.
+        fs::write(shim_path_in(&install_dir, "foreign"), b"a real binary").expect("foreign");
+        // Links envhub-core, like the TUI, so it names the launcher.
+        let linked = b"runs envhub-launcher shims".to_vec();
+        fs::write(shim_path_in(&install_dir, "linked"), &linked).expect("linked");
+
+        assert_eq!(
+            needs_reshim_for_state(&state, InstallMode::User, &launcher),
+            vec!["old".to_string()]
+        );
+        let updated = reshim_all_in(&path, InstallMode::User, &launcher).expect("reshim");
+        assert_eq!(updated, vec!["old".to_string()]);
+        assert!(needs_reshim_for_state(&state, InstallMode::User, &launcher).is_empty());
+        assert!(is_shim_installed_in(&install_dir, "old"));
+        assert_eq!(
+            fs::read(shim_path_in(&install_dir, "foreign")).expect("foreign"),
+            b"a real binary"
+        );
+        assert_eq!(
+            fs::read(shim_path_in(&install_dir, "linked")).expect("linked"),
+            linked
+        );
+    }
+
+    #[test]
+    fn reshim_updates_stale_alias_shims() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let install_dir = dir.path().join("bin");
+        fs::create_dir_all(&install_dir).expect("bin dir");
+        let launcher = install_dir.join(launcher_file_name());
+        fs::write(&launcher, launcher_build("0.2.0")).expect("launcher");
+
+        let mut state = State::default();
+        state.apps.insert(
+            "tool".to_string(),
+            AppConfig {
+                target_binary: "tool-bin".to_string(),
+                install_path: Some(install_dir.to_string_lossy().to_string()),
+                aliases: vec!["toolx".to_string(), "tl".to_string()],
+                ..AppConfig::default()
+            },
+        );
+        crate::save_state_to_path(&path, &state).expect("save");
+        install_shim_in("tool", &install_dir, &launcher).expect("tool");
+        let old = shim_path_in(&install_dir, "toolx");
+        fs::write(&old, launcher_build("0.1.0")).expect("old alias");
+        fs::write(shim_path_in(&install_dir, "tl"), b"real tl").expect("foreign alias");
+
+        assert_eq!(
+            needs_reshim_for_state(&state, InstallMode::User, &launcher),
+            vec!["tool".to_string()]
+        );
+        let updated = reshim_all_in(&path, InstallMode::User, &launcher).expect("reshim");
+        assert_eq!(updated, vec!["tool".to_string()]);
+        assert!(!is_stale_shim(&old, &launcher));
+        assert!(needs_reshim_for_state(&state, InstallMode::User, &launcher).is_empty());
+        assert_eq!(
+            fs::read(shim_path_in(&install_dir, "tl")).expect("tl"),
+            b"real tl"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reshim_detects_link_to_moved_launcher() {
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let old_dir = dir.path().join("old");
+        fs::create_dir_all(&old_dir).expect("old dir");
+        let old_launcher = old_dir.join(launcher_file_name());
+        fs::write(&old_launcher, b"envhub-launcher 0.1.0").expect("old launcher");
+        let launcher = dir.path().join(launcher_file_name());
+        fs::write(&launcher, b"envhub-launcher 0.2.0").expect("launcher");
+        install_shim_in("tool", &install_dir, &old_launcher).expect("shim");
+
+        let shim = shim_path_in(&install_dir, "tool");
+        assert!(is_stale_shim(&shim, &launcher));
+        fs::remove_file(&old_launcher).expect("remove old launcher");
+        assert!(is_stale_shim(&shim, &launcher));
+        install_shim_in("tool", &install_dir, &launcher).expect("reshim");
+        assert!(!is_stale_shim(&shim, &launcher));
+    }
+
+    #[test]
+    fn shell_config_follows_install_script() {
+        let home = Path::new("/home/user");
+        let config = |shell: &str| shell_config_path_for(shell, home);
+        assert_eq!(config("/bin/zsh").expect("zsh"), home.join(".zshrc"));
+        assert_eq!(config("/usr/bin/bash").expect("bash"), home.join(".bashrc"));
+        assert_eq!(config("/bin/sh").expect("sh"), home.join(".profile"));
+        assert_eq!(config("").expect("unset"), home.join(".profile"));
+        let err = config("/usr/local/bin/fish").unwrap_err();
+        assert_eq!(err.code, ErrorCode::Unsupported);
+        assert!(err.message.contains("fish"));
+    }
+
+    #[test]
+    fn ensure_setup_is_idempotent() {
+        let dir = TempDir::new().expect("temp dir");
+        let install_dir = dir.path().join("bin");
+        let rc = dir.path().join(".zshrc");
+        let launcher = dir.path().join("launcher");
+        fs::write(&launcher, b"binary").expect("launcher");
+        fs::write(&rc, "alias ll='ls -l'").expect("rc");
+
+        let first = ensure_setup_in(&launcher, &install_dir, Some(&rc)).expect("setup");
+        assert!(first.launcher_installed);
+        assert!(first.launcher_path.exists());
+        assert_eq!(first.path_configured_in.as_deref(), Some(rc.as_path()));
+        assert!(first.summary().starts_with("Installed launcher and added"));
+
+        let second = ensure_setup_in(&launcher, &install_dir, Some(&rc)).expect("setup");
+        assert!(!second.launcher_installed);
+        assert!(second.path_configured_in.is_none());
+        assert_eq!(second.summary(), "Launcher already set up");
+
+        let contents = fs::read_to_string(&rc).expect("read rc");
+        assert!(contents.starts_with("alias ll='ls -l'\n"));
+        assert_eq!(contents.matches("export PATH=").count(), 1);
     }
 
     #[test]
@@ -1,9 +1,31 @@
 mod apps;
+mod diff;
+mod doctor;
+mod env;
 mod error;
+mod export;
 mod install;
+mod lint;
+mod migrate;
+mod observe;
+mod resolve;
+mod share;
 mod state;
+mod store;
+mod undo;
 
 pub use apps::*;
+pub use diff::*;
+pub use doctor::*;
+pub use env::*;
 pub use error::*;
+pub use export::*;
 pub use install::*;
+pub use lint::*;
+pub use migrate::*;
+pub use observe::*;
+pub use resolve::*;
+pub use share::*;
 pub use state::*;
+pub use store::*;
+pub use undo::*;
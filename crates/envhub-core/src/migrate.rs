@@ -0,0 +1,227 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    CoreError, ErrorCode, ProfileConfig, SaveOptions, State, StateChange, load_state_from_path,
+};
+
+/// The schema version [`migrate_state_file`] brings a state file up to.
+pub const STATE_VERSION: u32 = 1;
+
+/// What [`migrate_state_file`] did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// One line per change, e.g. `claude/default: moved 2 variables into env`.
+    /// Empty when the file was already current and was left untouched.
+    pub changes: Vec<String>,
+    /// Copy of the file as it was before the migration, when one was written.
+    pub backup: Option<PathBuf>,
+}
+
+/// Path of the backup kept next to a migrated state file
+/// (`config.json` -> `config.json.pre-v1`).
+pub fn migration_backup_path_for(path: &Path, version: u32) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_else(|| OsString::from("config.json"));
+    name.push(format!(".pre-v{version}"));
+    path.with_file_name(name)
+}
+
+/// Upgrades the state file at `path` to [`STATE_VERSION`]: profiles written as
+/// a bare `{"KEY": "value"}` map become `{"env": {...}}`, and the version is
+/// stamped. The original is copied to [`migration_backup_path_for`] and the
+/// new file replaces it by rename, so a failed write leaves the old one
+/// intact. A file that is already current is not touched.
+pub fn migrate_state_file(path: &Path) -> Result<MigrationReport, CoreError> {
+    if !path.exists() {
+        return Err(CoreError::new(
+            ErrorCode::Io,
+            format!("Nothing to migrate: {} does not exist", path.display()),
+        ));
+    }
+    let mut state = load_state_from_path(path)?;
+    let from_version = state.version;
+    let changes = migrate_state(&mut state);
+    if changes.is_empty() {
+        return Ok(MigrationReport {
+            from_version,
+            to_version: from_version,
+            changes,
+            backup: None,
+        });
+    }
+
+    crate::state::ensure_writable(path)?;
+    let data = crate::state::render_state(&state, SaveOptions::default())?;
+    let backup = migration_backup_path_for(path, state.version);
+    fs::copy(path, &backup).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to back up {}: {err}", path.display()),
+        )
+    })?;
+    let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+    temp_name.push(".migrating");
+    let temp = path.with_file_name(temp_name);
+    let written = crate::state::write_private(&temp, &data)
+        .and_then(|_| fs::set_permissions(&temp, fs::metadata(path)?.permissions()))
+        .and_then(|_| fs::rename(&temp, path));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to write config.json: {err}"),
+        ));
+    }
+    crate::observe::notify(&StateChange::StateSaved);
+    Ok(MigrationReport {
+        from_version,
+        to_version: state.version,
+        changes,
+        backup: Some(backup),
+    })
+}
+
+/// Applies every migration `state` still needs, returning what changed.
+fn migrate_state(state: &mut State) -> Vec<String> {
+    let mut changes = Vec::new();
+    if state.version >= STATE_VERSION {
+        return changes;
+    }
+    if state.version < 1 {
+        for (app_name, app) in &mut state.apps {
+            for (profile_name, profile) in &mut app.profiles {
+                let moved = profile_from_env_map(profile);
+                if moved > 0 {
+                    changes.push(format!(
+                        "{app_name}/{profile_name}: moved {moved} variable{} into env",
+                        if moved == 1 { "" } else { "s" }
+                    ));
+                }
+            }
+        }
+    }
+    changes.push(format!("version {} -> {STATE_VERSION}", state.version));
+    state.version = STATE_VERSION;
+    changes
+}
+
+/// Turns a profile stored as a bare map of variables, which loads with every
+/// key in `extra`, into one with those variables in `env`. Profiles that set
+/// any real field, or hold a non-string value, are left alone. Returns how
+/// many variables moved.
+fn profile_from_env_map(profile: &mut ProfileConfig) -> usize {
+    let bare = ProfileConfig {
+        extra: profile.extra.clone(),
+        ..ProfileConfig::default()
+    };
+    let only_extra = serde_json::to_value(&bare).ok() == serde_json::to_value(&*profile).ok();
+    if !only_extra || !profile.extra.values().all(serde_json::Value::is_string) {
+        return 0;
+    }
+    let moved = profile.extra.len();
+    for (key, value) in std::mem::take(&mut profile.extra) {
+        if let serde_json::Value::String(value) = value {
+            profile.env.insert(key, value);
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const OLD_FORMAT: &str = r#"{
+  "apps": {
+    "claude": {
+      "installed": true,
+      "target_binary": "/usr/local/bin/claude",
+      "active_profile": "work",
+      "profiles": {
+        "default": {},
+        "work": { "ZED": "last", "ANTHROPIC_API_KEY": "sk-1", "ALPHA": "first" },
+        "current": { "env": { "MODE": "fast" }, "command_args": ["--verbose"] }
+      }
+    }
+  }
+}"#;
+
+    #[test]
+    fn migrate_moves_bare_env_maps_and_keeps_a_backup() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        fs::write(&path, OLD_FORMAT).expect("fixture");
+
+        let report = migrate_state_file(&path).expect("migrate");
+        assert_eq!((report.from_version, report.to_version), (0, STATE_VERSION));
+        assert_eq!(
+            report.changes,
+            [
+                "claude/work: moved 3 variables into env".to_string(),
+                format!("version 0 -> {STATE_VERSION}"),
+            ]
+        );
+        let backup = report.backup.expect("backup");
+        assert_eq!(backup, dir.path().join("config.json.pre-v1"));
+        assert_eq!(fs::read_to_string(&backup).expect("backup"), OLD_FORMAT);
+
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.version, STATE_VERSION);
+        let profiles = &state.apps["claude"].profiles;
+        let work = &profiles["work"];
+        assert!(work.extra.is_empty());
+        assert_eq!(
+            work.env.iter().collect::<Vec<_>>(),
+            [
+                (&"ZED".to_string(), &"last".to_string()),
+                (&"ANTHROPIC_API_KEY".to_string(), &"sk-1".to_string()),
+                (&"ALPHA".to_string(), &"first".to_string()),
+            ]
+        );
+        assert!(profiles["default"].env.is_empty());
+        assert_eq!(profiles["current"].env["MODE"], "fast");
+        assert_eq!(profiles["current"].command_args, ["--verbose"]);
+        let raw: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).expect("read")).expect("json");
+        assert_eq!(
+            raw["apps"]["claude"]["profiles"]["work"]["env"]["ZED"],
+            "last"
+        );
+        assert_eq!(raw["version"], STATE_VERSION);
+
+        let again = migrate_state_file(&path).expect("migrate again");
+        assert!(again.changes.is_empty());
+        assert_eq!(again.backup, None);
+    }
+
+    #[test]
+    fn migrate_leaves_profiles_with_real_fields_alone() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        let fixture = r#"{"apps": {"tool": {"target_binary": "tool-bin", "profiles": {
+            "mixed": {"notes": "keep", "FUTURE": "x"},
+            "nested": {"FLAG": {"on": true}}
+        }}}}"#;
+        fs::write(&path, fixture).expect("fixture");
+
+        let report = migrate_state_file(&path).expect("migrate");
+        assert_eq!(report.changes, [format!("version 0 -> {STATE_VERSION}")]);
+        let state = load_state_from_path(&path).expect("load");
+        let profiles = &state.apps["tool"].profiles;
+        assert!(profiles["mixed"].env.is_empty());
+        assert_eq!(profiles["mixed"].extra["FUTURE"], "x");
+        assert!(profiles["nested"].env.is_empty());
+
+        let missing = migrate_state_file(&dir.path().join("missing.json")).unwrap_err();
+        assert_eq!(missing.code, ErrorCode::Io);
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::Serialize;
+
+use crate::{CoreError, State, StateStore};
+
+/// A successful mutation, as reported to observers registered with
+/// [`on_state_change`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StateChange {
+    AppRegistered {
+        app: String,
+    },
+    /// An app-level setting changed (disabled, install dir, target root,
+    /// group, env order, installed).
+    AppUpdated {
+        app: String,
+    },
+    AppImported {
+        app: String,
+    },
+    ProfileAdded {
+        app: String,
+        profile: String,
+    },
+    ProfileRemoved {
+        app: String,
+        profile: String,
+    },
+    ProfileActivated {
+        app: String,
+        profile: String,
+    },
+    EnvSet {
+        app: String,
+        profile: String,
+        key: String,
+    },
+    /// Variables were merged into a profile from a file by `import_profile`.
+    EnvImported {
+        app: String,
+        profile: String,
+        keys: Vec<String>,
+    },
+    EnvRemoved {
+        app: String,
+        profile: String,
+        keys: Vec<String>,
+    },
+    EnvReordered {
+        app: String,
+        profile: String,
+    },
+    CommandArgsSet {
+        app: String,
+        profile: String,
+    },
+    ProfileTargetSet {
+        app: String,
+        profile: String,
+    },
+    ProfileNotesSet {
+        app: String,
+        profile: String,
+    },
+    ProfileDangerousSet {
+        app: String,
+        profile: String,
+    },
+    /// A profile's `include` list changed.
+    ProfileIncludesSet {
+        app: String,
+        profile: String,
+    },
+    /// The whole state was written, e.g. by `save_state`.
+    StateSaved,
+    /// `undo_last` restored the previous state.
+    Undone,
+}
+
+type Observer = Arc<dyn Fn(&StateChange) + Send + Sync>;
+
+/// Handle returned by [`on_state_change`], used to unregister.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+struct Registry {
+    next_id: u64,
+    observers: Vec<(ObserverId, Observer)>,
+}
+
+static OBSERVERS: Mutex<Registry> = Mutex::new(Registry {
+    next_id: 0,
+    observers: Vec::new(),
+});
+
+/// Registers `observer` to be called synchronously, on the mutating thread,
+/// after every successful mutation made through this crate. Observers may
+/// call back into envhub-core.
+pub fn on_state_change(observer: Box<dyn Fn(&StateChange) + Send + Sync>) -> ObserverId {
+    let mut registry = OBSERVERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let id = ObserverId(registry.next_id);
+    registry.next_id += 1;
+    registry.observers.push((id, Arc::from(observer)));
+    id
+}
+
+/// Unregisters an observer. Returns whether it was registered.
+pub fn remove_state_observer(id: ObserverId) -> bool {
+    let mut registry = OBSERVERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let before = registry.observers.len();
+    registry.observers.retain(|(existing, _)| *existing != id);
+    registry.observers.len() != before
+}
+
+pub(crate) fn notify(change: &StateChange) {
+    // Call outside the lock so observers can register, unregister or mutate.
+    let observers: Vec<Observer> = OBSERVERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .observers
+        .iter()
+        .map(|(_, observer)| Arc::clone(observer))
+        .collect();
+    for observer in observers {
+        observer(change);
+    }
+}
+
+/// Saves `state` to `store` as an undoable change and, once it is stored,
+/// tells observers about `change`.
+pub(crate) fn save_and_notify(
+    store: &(impl StateStore + ?Sized),
+    state: &State,
+    change: StateChange,
+) -> Result<(), CoreError> {
+    store.save_undoable(state)?;
+    notify(&change);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observer_fires_after_register_app() {
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        // Observers are process-wide; only keep this test's app.
+        let id = on_state_change(Box::new(move |change| {
+            if let StateChange::AppRegistered { app } | StateChange::ProfileAdded { app, .. } =
+                change
+                && app == "observed-tool"
+            {
+                sink.lock().unwrap().push(change.clone());
+            }
+        }));
+
+        crate::register_app_in(&path, "observed-tool", "tool-bin").expect("register");
+        crate::add_profile_in(&path, "observed-tool", "work").expect("add");
+        let _ = crate::add_profile_in(&path, "observed-tool", " ");
+
+        assert!(remove_state_observer(id));
+        crate::add_profile_in(&path, "observed-tool", "later").expect("add");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                StateChange::AppRegistered {
+                    app: "observed-tool".to_string()
+                },
+                StateChange::ProfileAdded {
+                    app: "observed-tool".to_string(),
+                    profile: "work".to_string()
+                },
+            ]
+        );
+        assert!(!remove_state_observer(id));
+    }
+}
@@ -0,0 +1,641 @@
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{AppConfig, CoreError, ErrorCode};
+
+/// Expands a leading `~` and any `$VAR`/`${VAR}` references in `input`.
+///
+/// Stored targets stay portable (`~/bin/tool` works on every machine) and are
+/// only expanded when they are about to be used. A variable that is not set is
+/// left as written, like `resolve_env_references` does, so the resulting
+/// "not found" error names it.
+pub fn expand_path(input: &str) -> Result<String, CoreError> {
+    expand_path_with(input, dirs::home_dir().as_deref(), |name| {
+        std::env::var(name).ok()
+    })
+}
+
+pub fn expand_path_with(
+    input: &str,
+    home: Option<&Path>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, CoreError> {
+    let expanded_home = expand_home(input, home)?;
+    Ok(expand_vars(&expanded_home, lookup))
+}
+
+fn expand_home(input: &str, home: Option<&Path>) -> Result<String, CoreError> {
+    let rest = if input == "~" {
+        ""
+    } else if let Some(rest) = input
+        .strip_prefix("~/")
+        .or_else(|| input.strip_prefix(&format!("~{}", std::path::MAIN_SEPARATOR)))
+    {
+        rest
+    } else {
+        return Ok(input.to_string());
+    };
+    let home = home.ok_or_else(|| {
+        CoreError::new(
+            ErrorCode::InstallPath,
+            "Failed to resolve home directory".to_string(),
+        )
+    })?;
+    if rest.is_empty() {
+        return Ok(home.to_string_lossy().to_string());
+    }
+    Ok(home.join(rest).to_string_lossy().to_string())
+}
+
+fn expand_vars(input: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        if name.is_empty() || !is_var_name(name) {
+            // Not a variable reference, keep the `$` literally.
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[pos..pos + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Normalizes a stored `target_binary` into the form used for resolution.
+pub fn normalize_target(target: &str) -> Result<String, CoreError> {
+    expand_path(target.trim())
+}
+
+/// Resolves a stored `target_binary` to an executable path, expanding `~` and
+/// environment variables first and refusing to resolve to the running
+/// launcher itself.
+pub fn resolve_target_binary(target: &str) -> Result<PathBuf, CoreError> {
+    let self_path = std::env::current_exe().map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to resolve launcher path: {err}"),
+        )
+    })?;
+    resolve_target_binary_from(target, &self_path)
+}
+
+pub fn resolve_target_binary_from(target: &str, self_path: &Path) -> Result<PathBuf, CoreError> {
+    let expanded = normalize_target(target)?;
+    let target = expanded.as_str();
+    let target_path = Path::new(target);
+
+    if target_path.is_absolute() || target_path.components().count() > 1 {
+        follow_symlinks(target_path)?;
+        if target_path.exists() {
+            return ensure_not_self(target_path.to_path_buf(), self_path);
+        }
+        return Err(CoreError::new(
+            ErrorCode::TargetNotFound,
+            format!("Target \"{target}\" not found"),
+        ));
+    }
+
+    let resolved = find_executable_in_path(target, self_path).ok_or_else(|| {
+        let looped =
+            std::env::var_os("PATH").and_then(|path_var| path_symlink_loop(target, &path_var));
+        CoreError::new(
+            ErrorCode::TargetNotFound,
+            match looped {
+                Some(looped) => format!("Target \"{target}\" not found in PATH; {looped}"),
+                None => format!("Target \"{target}\" not found in PATH"),
+            },
+        )
+    })?;
+    Ok(resolved)
+}
+
+/// How many symlinks a target may pass through before it counts as a loop,
+/// the same limit Linux applies.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Follows `path` through symlinks to whatever it finally names, failing with
+/// `TargetNotFound` on a cycle or after [`MAX_SYMLINK_HOPS`] links rather than
+/// leaving it to the OS. A dangling link yields its missing destination.
+fn follow_symlinks(path: &Path) -> Result<PathBuf, CoreError> {
+    let mut current = path.to_path_buf();
+    let mut seen = HashSet::new();
+    for _ in 0..MAX_SYMLINK_HOPS {
+        let Ok(link) = std::fs::read_link(&current) else {
+            return Ok(current);
+        };
+        if !seen.insert(current.clone()) {
+            break;
+        }
+        current = match current.parent() {
+            Some(parent) => parent.join(link),
+            None => link,
+        };
+    }
+    Err(CoreError::new(
+        ErrorCode::TargetNotFound,
+        format!(
+            "Target \"{}\" is a symlink loop (or a chain of more than {MAX_SYMLINK_HOPS} links)",
+            path.display()
+        ),
+    ))
+}
+
+/// Why a `PATH` lookup of `target` may have come up empty: the first `PATH`
+/// entry where `target` is a symlink loop, which the search skips.
+fn path_symlink_loop(target: &str, path_var: &OsStr) -> Option<String> {
+    std::env::split_paths(path_var)
+        .find_map(|dir| follow_symlinks(&dir.join(target)).err())
+        .map(|err| err.message)
+}
+
+/// The app's cached target, if a launch of `targets` may use it without
+/// resolving again: caching is on, the cache was filled from these same
+/// targets, and the path is still an executable other than envhub itself.
+/// `None` is a miss, and the caller resolves as usual; the launcher then
+/// stores the result with `store_resolved_target`.
+pub fn cached_target(app: &AppConfig, targets: &[String]) -> Option<PathBuf> {
+    if !app.cache_target {
+        return None;
+    }
+    let cached = app
+        .resolved_target
+        .as_ref()
+        .filter(|cached| cached.targets == targets)?;
+    let path = PathBuf::from(&cached.path);
+    if !is_executable(&path) {
+        return None;
+    }
+    let self_path = std::env::current_exe().ok()?;
+    ensure_not_self(path, &self_path).ok()
+}
+
+/// Resolves the first of `candidates` that resolves, in order.
+///
+/// Fails with `TargetNotFound` listing every candidate that was tried when
+/// none of them resolve, or `SelfReference` when each one is the launcher.
+pub fn resolve_target_candidates(candidates: &[String]) -> Result<PathBuf, CoreError> {
+    let self_path = std::env::current_exe().map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to resolve launcher path: {err}"),
+        )
+    })?;
+    resolve_target_candidates_from(candidates, &self_path)
+}
+
+pub fn resolve_target_candidates_from(
+    candidates: &[String],
+    self_path: &Path,
+) -> Result<PathBuf, CoreError> {
+    let mut failures = Vec::new();
+    let mut all_self = true;
+    for candidate in candidates {
+        match resolve_target_binary_from(candidate, self_path) {
+            Ok(path) => return Ok(path),
+            Err(err) => {
+                all_self &= err.code == ErrorCode::SelfReference;
+                failures.push(format!("{candidate} ({})", err.message));
+            }
+        }
+    }
+    if failures.is_empty() {
+        return Err(CoreError::new(
+            ErrorCode::TargetNotFound,
+            "No target candidates configured".to_string(),
+        ));
+    }
+    // Only report a self-reference when nothing else went wrong, so a missing
+    // candidate still reads as "not found".
+    let code = if all_self {
+        ErrorCode::SelfReference
+    } else {
+        ErrorCode::TargetNotFound
+    };
+    Err(CoreError::new(
+        code,
+        format!(
+            "No target candidate resolved; tried: {}",
+            failures.join(", ")
+        ),
+    ))
+}
+
+/// How long `find_executable_in_path` reuses its `PATH` listings. Long
+/// enough to cover every candidate of one launch, short enough that the TUI
+/// notices newly installed binaries.
+const PATH_CACHE_TTL: Duration = Duration::from_secs(2);
+
+static PATH_CACHE: Mutex<Option<(Instant, PathIndex)>> = Mutex::new(None);
+
+pub fn find_executable_in_path(target: &str, self_path: &Path) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    let mut cache = PATH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let fresh = cache.as_ref().is_some_and(|(built, index)| {
+        built.elapsed() < PATH_CACHE_TTL && index.path_var == path_var
+    });
+    if !fresh {
+        *cache = Some((Instant::now(), PathIndex::new(&path_var)));
+    }
+    cache.as_mut()?.1.find(target, self_path)
+}
+
+/// The directories of a `PATH` value and their file names. Each directory
+/// is listed the first time a lookup reaches it and never again, so
+/// resolving several candidates costs one scan of `PATH`.
+#[derive(Debug)]
+pub struct PathIndex {
+    path_var: OsString,
+    dirs: Vec<(PathBuf, Listing)>,
+    exts: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Listing {
+    Unread,
+    Names(HashSet<OsString>),
+    /// The directory can't be listed (e.g. execute-only); fall back to
+    /// checking each candidate path directly.
+    Unlistable,
+}
+
+impl PathIndex {
+    pub fn new(path_var: &OsStr) -> Self {
+        let exts = if cfg!(windows) {
+            std::env::var_os("PATHEXT")
+                .map(|exts| {
+                    exts.to_string_lossy()
+                        .split(';')
+                        .map(|s| s.to_string())
+                        .collect()
+                })
+                .unwrap_or_else(|| vec![".EXE".to_string()])
+        } else {
+            Vec::new()
+        };
+        Self {
+            path_var: path_var.to_os_string(),
+            dirs: std::env::split_paths(path_var)
+                .map(|dir| (dir, Listing::Unread))
+                .collect(),
+            exts,
+        }
+    }
+
+    /// The first executable named `target` (or, on Windows, `target` plus a
+    /// `PATHEXT` extension) in `PATH` order, skipping envhub itself and its
+    /// shims.
+    pub fn find(&mut self, target: &str, self_path: &Path) -> Option<PathBuf> {
+        let mut names = vec![target.to_string()];
+        names.extend(self.exts.iter().map(|ext| format!("{target}{ext}")));
+        for (dir, listing) in &mut self.dirs {
+            if let Listing::Unread = listing {
+                *listing = list_dir(dir);
+            }
+            for name in &names {
+                let listed = match listing {
+                    Listing::Names(names) => names.contains(&listing_key(OsStr::new(name))),
+                    _ => true,
+                };
+                let candidate = dir.join(name);
+                if listed
+                    && is_executable(&candidate)
+                    && let Ok(path) = ensure_not_self(candidate, self_path)
+                {
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn list_dir(dir: &Path) -> Listing {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => Listing::Names(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| listing_key(&entry.file_name()))
+                .collect(),
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Listing::Names(HashSet::new()),
+        Err(_) => Listing::Unlistable,
+    }
+}
+
+/// Windows file names are case-insensitive, so compare them lowercased.
+fn listing_key(name: &OsStr) -> OsString {
+    if cfg!(windows) {
+        OsString::from(name.to_string_lossy().to_lowercase())
+    } else {
+        name.to_os_string()
+    }
+}
+
+/// Rejects targets that would run envhub again: the running launcher itself,
+/// or any other envhub shim, which would chain (or loop) through the launcher.
+fn ensure_not_self(path: PathBuf, self_path: &Path) -> Result<PathBuf, CoreError> {
+    if same_executable(&path, self_path).unwrap_or(false) {
+        return Err(CoreError::new(
+            ErrorCode::SelfReference,
+            "Target binary resolves to envhub-launcher".to_string(),
+        ));
+    }
+    if is_envhub_shim(&path) {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            format!(
+                "Target \"{}\" is another envhub shim; shim chains are not supported, point target_binary at the real binary",
+                path.display()
+            ),
+        ));
+    }
+    Ok(path)
+}
+
+/// Whether `path` ultimately resolves to an `envhub-launcher` binary, under
+/// its default or configured name.
+pub fn is_envhub_shim(path: &Path) -> bool {
+    path.canonicalize()
+        .ok()
+        .and_then(|canonical| {
+            canonical
+                .file_name()
+                .map(crate::install::is_launcher_file_name)
+        })
+        .unwrap_or(false)
+}
+
+fn same_executable(path: &Path, self_path: &Path) -> Option<bool> {
+    let canonical_candidate = path.canonicalize().ok()?;
+    let canonical_self = self_path.canonicalize().ok()?;
+    if canonical_candidate == canonical_self {
+        return Some(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let candidate_meta = fs_metadata(&canonical_candidate)?;
+        let self_meta = fs_metadata(&canonical_self)?;
+        Some(candidate_meta.ino() == self_meta.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        Some(false)
+    }
+}
+
+fn fs_metadata(path: &Path) -> Option<std::fs::Metadata> {
+    std::fs::metadata(path).ok()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(path) {
+        Ok(meta) => meta.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "TOOLS" => Some("/opt/tools".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_path_expands_leading_tilde() {
+        let home = Path::new("/home/user");
+        let expanded = expand_path_with("~/bin/tool", Some(home), lookup).expect("expand");
+        assert_eq!(PathBuf::from(expanded), home.join("bin/tool"));
+        assert_eq!(
+            expand_path_with("~", Some(home), lookup).expect("expand"),
+            "/home/user"
+        );
+        // Only a leading tilde is special.
+        assert_eq!(
+            expand_path_with("/tmp/~/tool", Some(home), lookup).expect("expand"),
+            "/tmp/~/tool"
+        );
+    }
+
+    #[test]
+    fn expand_path_expands_variables() {
+        assert_eq!(
+            expand_path_with("$TOOLS/bin/tool", None, lookup).expect("expand"),
+            "/opt/tools/bin/tool"
+        );
+        assert_eq!(
+            expand_path_with("${TOOLS}-v2/tool", None, lookup).expect("expand"),
+            "/opt/tools-v2/tool"
+        );
+        assert_eq!(
+            expand_path_with("cost$5", None, lookup).expect("expand"),
+            "cost$5"
+        );
+    }
+
+    #[test]
+    fn expand_path_keeps_undefined_variables() {
+        assert_eq!(
+            expand_path_with("$MISSING/tool", None, lookup).expect("expand"),
+            "$MISSING/tool"
+        );
+        assert_eq!(
+            expand_path_with("${MISSING}/$TOOLS", None, lookup).expect("expand"),
+            "${MISSING}//opt/tools"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_shim_chain() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let launcher = dir.path().join(crate::launcher_file_name());
+        std::fs::write(&launcher, b"binary").expect("launcher");
+        std::fs::set_permissions(&launcher, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        symlink(&launcher, &first).expect("first shim");
+        symlink(&launcher, &second).expect("second shim");
+
+        // Running as `first` (this test binary stands in for its launcher) with
+        // a target that is the `second` shim.
+        let self_path = std::env::current_exe().expect("self");
+        let err = resolve_target_binary_from(&second.to_string_lossy(), &self_path).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("shim chains"));
+
+        // A PATH lookup skips shims instead of chaining through them.
+        let found = PathIndex::new(dir.path().as_os_str()).find("second", &self_path);
+        assert!(found.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_fail_with_a_clear_message() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        symlink(&second, &first).expect("first link");
+        symlink("first", &second).expect("second link");
+        let self_path = std::env::current_exe().expect("self");
+
+        let err = resolve_target_binary_from(&first.to_string_lossy(), &self_path).unwrap_err();
+        assert_eq!(err.code, ErrorCode::TargetNotFound);
+        assert!(err.message.contains("symlink loop"), "{}", err.message);
+
+        assert!(
+            PathIndex::new(dir.path().as_os_str())
+                .find("first", &self_path)
+                .is_none()
+        );
+        let looped = path_symlink_loop("second", dir.path().as_os_str()).expect("loop");
+        assert!(looped.contains("second\" is a symlink loop"), "{looped}");
+        assert_eq!(path_symlink_loop("missing", dir.path().as_os_str()), None);
+
+        // A long chain without a cycle is cut off at the same limit.
+        let target = dir.path().join("real");
+        std::fs::write(&target, b"binary").expect("target");
+        let mut previous = target.clone();
+        for hop in 0..=MAX_SYMLINK_HOPS {
+            let link = dir.path().join(format!("hop{hop}"));
+            symlink(&previous, &link).expect("hop");
+            previous = link;
+        }
+        assert_eq!(
+            follow_symlinks(&dir.path().join("hop0")).expect("short"),
+            target
+        );
+        assert!(follow_symlinks(&previous).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_candidates_uses_first_that_resolves() {
+        let self_path = std::env::current_exe().expect("self");
+        let candidates = vec![
+            "/definitely/missing/python3".to_string(),
+            "/bin/sh".to_string(),
+            "/bin/ls".to_string(),
+        ];
+        let resolved = resolve_target_candidates_from(&candidates, &self_path).expect("resolve");
+        assert_eq!(resolved, PathBuf::from("/bin/sh"));
+    }
+
+    #[test]
+    fn resolve_candidates_lists_every_attempt() {
+        let self_path = std::env::current_exe().expect("self");
+        let candidates = vec![
+            "/definitely/missing/python3".to_string(),
+            "./missing/python".to_string(),
+        ];
+        let err = resolve_target_candidates_from(&candidates, &self_path).unwrap_err();
+        assert_eq!(err.code, ErrorCode::TargetNotFound);
+        assert!(err.message.contains("/definitely/missing/python3"));
+        assert!(err.message.contains("./missing/python"));
+
+        let err = resolve_target_candidates_from(&[], &self_path).unwrap_err();
+        assert_eq!(err.code, ErrorCode::TargetNotFound);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_index_resolves_candidates_against_one_scan() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::TempDir::new().expect("temp dir");
+        let dirs: Vec<PathBuf> = (0..200)
+            .map(|i| root.path().join(format!("d{i}")))
+            .collect();
+        for dir in &dirs {
+            std::fs::create_dir_all(dir).expect("dir");
+        }
+        let write_tool = |dir: &Path| {
+            let tool = dir.join("tool");
+            std::fs::write(&tool, b"#!/bin/sh\n").expect("tool");
+            std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+            tool
+        };
+        let last = write_tool(&dirs[199]);
+        // Present but not executable: skipped like before.
+        std::fs::write(dirs[5].join("tool"), b"data").expect("data");
+        let path_var = std::env::join_paths(&dirs).expect("join");
+        let self_path = std::env::current_exe().expect("self");
+
+        let mut index = PathIndex::new(&path_var);
+        let start = Instant::now();
+        for _ in 0..100 {
+            for candidate in ["missing-a", "missing-b", "tool"] {
+                let found = index.find(candidate, &self_path);
+                assert_eq!(found.is_some(), candidate == "tool");
+            }
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(index.find("tool", &self_path), Some(last.clone()));
+
+        // Listings are reused: a binary added after the scan is not seen by
+        // the same index, only by a fresh one.
+        let early = write_tool(&dirs[0]);
+        assert_eq!(index.find("tool", &self_path), Some(last));
+        assert_eq!(
+            PathIndex::new(&path_var).find("tool", &self_path),
+            Some(early)
+        );
+    }
+
+    #[test]
+    fn find_executable_skips_self() {
+        let self_path = std::env::current_exe().expect("self");
+        let self_dir = self_path.parent().expect("self dir").to_path_buf();
+        let file_name = self_path.file_name().unwrap().to_string_lossy().to_string();
+
+        let found = PathIndex::new(self_dir.as_os_str()).find(&file_name, &self_path);
+        assert!(found.is_none());
+    }
+}
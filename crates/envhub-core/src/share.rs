@@ -0,0 +1,268 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::{CoreError, ErrorCode, State, is_secret_key, load_state_from_path, validate_state};
+
+/// Marks a share blob, so pasting the wrong text fails with a clear message.
+pub const SHARE_PREFIX: &str = "envhub:";
+
+/// Decoded blobs larger than this are refused rather than inflated.
+const MAX_SHARE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// The current state as one line of text to paste into a chat or a QR code:
+/// [`SHARE_PREFIX`] followed by the gzipped JSON in URL-safe base64. Secrets
+/// are stripped first (see [`strip_secrets`]). Unlike `export_app`, this
+/// covers every app and never touches a file.
+pub fn encode_state_share() -> Result<String, CoreError> {
+    let path = crate::default_state_path()?;
+    encode_state_share_in(&path)
+}
+
+pub fn encode_state_share_in(path: &Path) -> Result<String, CoreError> {
+    let state = load_state_from_path(path)?;
+    encode_share(&state)
+}
+
+/// Encodes `state` like [`encode_state_share`].
+pub fn encode_share(state: &State) -> Result<String, CoreError> {
+    let mut state = state.clone();
+    strip_secrets(&mut state);
+    let json = serde_json::to_vec(&state).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Json,
+            format!("Failed to serialize shared state: {err}"),
+        )
+    })?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    let compressed = encoder
+        .write_all(&json)
+        .and_then(|()| encoder.finish())
+        .map_err(|err| {
+            CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to compress shared state: {err}"),
+            )
+        })?;
+    Ok(format!(
+        "{SHARE_PREFIX}{}",
+        URL_SAFE_NO_PAD.encode(compressed)
+    ))
+}
+
+/// Reads a blob made by [`encode_state_share`] back into a state, validated
+/// like `save_state_validated` would. Nothing is saved; the caller decides
+/// whether to replace or merge. Surrounding whitespace is ignored.
+pub fn decode_state_share(blob: &str) -> Result<State, CoreError> {
+    let invalid = |detail: String| {
+        CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Not an envhub share: {detail}"),
+        )
+    };
+    let encoded = blob
+        .trim()
+        .strip_prefix(SHARE_PREFIX)
+        .ok_or_else(|| invalid(format!("missing \"{SHARE_PREFIX}\" prefix")))?;
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|err| invalid(err.to_string()))?;
+    let mut json = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .take(MAX_SHARE_BYTES + 1)
+        .read_to_end(&mut json)
+        .map_err(|err| invalid(err.to_string()))?;
+    if json.len() as u64 > MAX_SHARE_BYTES {
+        return Err(invalid("too large".to_string()));
+    }
+    let mut state: State = serde_json::from_slice(&json).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Json,
+            format!("Failed to parse shared state: {err}"),
+        )
+    })?;
+    validate_state(&mut state)?;
+    Ok(state)
+}
+
+/// Drops what should not leave the machine: env keys that [`is_secret_key`]
+/// matches or whose values look like credentials, command arguments that
+/// carry such values, unknown fields (which envhub cannot vet), every
+/// profile's env history, the file checksum, and install details that only
+/// describe this machine, as `export_app` does.
+pub fn strip_secrets(state: &mut State) {
+    state.checksum = None;
+    state.launcher_name = None;
+    state.extra.clear();
+    state.meta.extra.clear();
+    for app in state.apps.values_mut() {
+        app.installed = false;
+        app.install_path = None;
+        app.resolved_target = None;
+        app.extra.clear();
+        for profile in app.profiles.values_mut() {
+            profile
+                .env
+                .retain(|key, value| !is_secret_key(key) && !crate::lint::looks_like_secret(value));
+            profile.env_history.clear();
+            profile.extra.clear();
+            strip_secret_args(&mut profile.command_args);
+        }
+    }
+}
+
+/// Removes arguments that look like credentials, `--flag=value` pairs whose
+/// flag or value is secret, and a secret-named flag (`--api-key`) together
+/// with the value that follows it.
+fn strip_secret_args(args: &mut Vec<String>) {
+    let secret_flag = |flag: &str| {
+        let name = flag.trim_start_matches('-');
+        flag.starts_with('-') && !name.is_empty() && is_secret_key(&name.replace('-', "_"))
+    };
+    let mut kept = Vec::with_capacity(args.len());
+    let mut drop_next = false;
+    for arg in args.drain(..) {
+        if std::mem::take(&mut drop_next) && !arg.starts_with('-') {
+            continue;
+        }
+        let secret = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => {
+                secret_flag(flag) || crate::lint::looks_like_secret(value)
+            }
+            _ if secret_flag(&arg) => {
+                drop_next = true;
+                true
+            }
+            _ => crate::lint::looks_like_secret(&arg),
+        };
+        if !secret {
+            kept.push(arg);
+        }
+    }
+    *args = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_app_in, set_profile_env_in};
+    use tempfile::TempDir;
+
+    #[test]
+    fn share_round_trips_without_secrets() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        register_app_in(&path, "node", "node-bin").expect("register");
+        set_profile_env_in(&path, "claude", "default", "BASE_URL", "https://api").expect("set");
+        set_profile_env_in(&path, "claude", "default", "ANTHROPIC_API_KEY", "sk-1").expect("set");
+        set_profile_env_in(
+            &path,
+            "claude",
+            "default",
+            "AUTH",
+            "sk-ant-REDACTED",
+        )
+        .expect("set");
+        set_profile_env_in(&path, "node", "default", "EMPTY", "").expect("set");
+
+        let blob = encode_state_share_in(&path).expect("encode");
+        assert!(blob.starts_with(SHARE_PREFIX), "{blob}");
+        assert!(!blob.contains(char::is_whitespace));
+
+        let state = decode_state_share(&format!("  {blob}\n")).expect("decode");
+        assert_eq!(state.apps.keys().collect::<Vec<_>>(), ["claude", "node"]);
+        let env = &state.apps["claude"].profiles["default"].env;
+        assert_eq!(env.keys().collect::<Vec<_>>(), ["BASE_URL"]);
+        assert_eq!(state.apps["node"].profiles["default"].env["EMPTY"], "");
+        assert_eq!(state.apps["claude"].target_binary, "claude-bin");
+        assert!(state.checksum.is_none());
+
+        let mut local = load_state_from_path(&path).expect("load");
+        assert!(
+            local.apps["claude"].profiles["default"]
+                .env
+                .contains_key("ANTHROPIC_API_KEY")
+        );
+
+        // The cached target and install details are this machine's only.
+        let claude = local.apps.get_mut("claude").expect("app");
+        claude.resolved_target = Some(crate::ResolvedTarget {
+            targets: vec!["claude-bin".to_string()],
+            path: "/opt/claude/claude-bin".to_string(),
+        });
+        claude.installed = true;
+        claude.install_path = Some("/home/me/.envhub/bin".to_string());
+        let shared = decode_state_share(&encode_share(&local).expect("encode")).expect("decode");
+        let claude = &shared.apps["claude"];
+        assert!(claude.resolved_target.is_none());
+        assert!(!claude.installed);
+        assert!(claude.install_path.is_none());
+    }
+
+    #[test]
+    fn strip_secrets_drops_secret_args_and_unknown_fields() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        register_app_in(&path, "claude", "claude-bin").expect("register");
+        let mut state = load_state_from_path(&path).expect("load");
+        state
+            .extra
+            .insert("_comment".to_string(), "token sk-1".into());
+        state.meta.extra.insert("vault".to_string(), "pw".into());
+        let app = state.apps.get_mut("claude").expect("app");
+        app.extra.insert("api_key".to_string(), "sk-1".into());
+        let profile = app.profiles.get_mut("default").expect("profile");
+        profile.extra.insert("note".to_string(), "secret".into());
+        profile.command_args = [
+            "--verbose",
+            "--api-key",
+            "sk-1",
+            "--password=hunter2",
+            "--model=opus",
+            "--auth=sk-ant-REDACTED",
+            "sk-ant-REDACTED",
+            "--token",
+            "--debug",
+            "run",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        strip_secrets(&mut state);
+        assert!(state.extra.is_empty());
+        assert!(state.meta.extra.is_empty());
+        let app = &state.apps["claude"];
+        assert!(app.extra.is_empty());
+        let profile = &app.profiles["default"];
+        assert!(profile.extra.is_empty());
+        assert_eq!(
+            profile.command_args,
+            ["--verbose", "--model=opus", "--debug", "run"]
+        );
+    }
+
+    #[test]
+    fn decode_rejects_foreign_and_invalid_blobs() {
+        let err = decode_state_share("hello").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("prefix"), "{}", err.message);
+
+        let err = decode_state_share("envhub:***").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+
+        let mut state = State::default();
+        state
+            .apps
+            .insert("broken".to_string(), crate::AppConfig::default());
+        let blob = encode_share(&state).expect("encode");
+        let err = decode_state_share(&blob).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("broken"), "{}", err.message);
+    }
+}
@@ -1,9 +1,12 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use dirs::config_dir;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{CoreError, ErrorCode};
 
@@ -13,48 +16,472 @@ pub struct ProfileConfig {
     pub env: IndexMap<String, String>,
     #[serde(default)]
     pub command_args: Vec<String>,
+    #[serde(default)]
+    pub stdout_file: Option<String>,
+    #[serde(default)]
+    pub stderr_file: Option<String>,
+    /// Dotenv file merged beneath `env` at launch; inline values win.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// Runs this binary instead of the app's targets while the profile is
+    /// active, e.g. a different node version per profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_binary: Option<String>,
+    /// Free-form reminder about the profile, e.g. "uses prod creds".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Activating the profile needs confirming in the TUI, e.g. for prod
+    /// credentials. Launches are unaffected.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dangerous: bool,
+    /// Other profiles of the same app whose `env` is layered beneath this
+    /// one's, in order, e.g. `base` under `eu-west`. See
+    /// [`AppConfig::layered_env`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Start the target with only this profile's env plus `inherit_env`,
+    /// instead of the launcher's whole environment.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub isolated: bool,
+    /// Variables passed through from the parent when `isolated`. Empty means
+    /// [`DEFAULT_ISOLATED_ENV`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inherit_env: Vec<String>,
+    /// Values `env` keys held before they were overwritten, oldest first,
+    /// while the state's `env_history_limit` is non-zero.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub env_history: IndexMap<String, Vec<EnvHistoryEntry>>,
     #[serde(flatten)]
     pub extra: IndexMap<String, serde_json::Value>,
 }
 
+/// A value an env key held, and when it was replaced (RFC 3339, UTC).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvHistoryEntry {
+    pub value: String,
+    pub replaced_at: String,
+}
+
+/// How deep profile `include`s may nest before [`AppConfig::layered_env`]
+/// gives up.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// A target resolved ahead of time for an app with `cache_target`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedTarget {
+    /// The target list it was resolved from; launches of any other list
+    /// (e.g. a profile's own target) resolve as usual.
+    pub targets: Vec<String>,
+    pub path: String,
+}
+
+/// What an isolated profile keeps from the parent environment unless it
+/// lists its own `inherit_env`: enough to find programs, locate the user and
+/// draw in the terminal. The Windows entries are required for most programs
+/// to start there at all.
+pub const DEFAULT_ISOLATED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "TMPDIR",
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "TEMP",
+    "TMP",
+    "COMSPEC",
+    "PATHEXT",
+];
+
+impl ProfileConfig {
+    /// The parent variables a launch keeps: `None` for all of them, or the
+    /// allowed names when the profile is isolated.
+    pub fn inherited_env(&self) -> Option<Vec<String>> {
+        if !self.isolated {
+            return None;
+        }
+        if self.inherit_env.is_empty() {
+            return Some(
+                DEFAULT_ISOLATED_ENV
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect(),
+            );
+        }
+        Some(self.inherit_env.clone())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct State {
     #[serde(default)]
     pub apps: IndexMap<String, AppConfig>,
+    /// SHA-256 over the canonical JSON of `apps`, refreshed on every save.
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Profile names apps may use. Empty allows any name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_profiles: Vec<String>,
+    /// How many previous values to keep per env key. 0, the default, keeps
+    /// no history.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub env_history_limit: usize,
+    /// Schema version the file was last migrated to by
+    /// [`migrate_state_file`](crate::migrate_state_file). 0 for files from
+    /// before versioning; ordinary saves keep whatever is there.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub version: u32,
+    /// The mode installs use when the caller doesn't pass one.
+    #[serde(default, skip_serializing_if = "crate::InstallMode::is_user")]
+    pub default_install_mode: crate::InstallMode,
+    /// The name the launcher was installed under by
+    /// [`install_launcher_as`](crate::install_launcher_as), when not the
+    /// default, so shims are recognised without `ENVHUB_LAUNCHER_NAME`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launcher_name: Option<String>,
+    /// Notes about the file for people who edit it by hand.
+    #[serde(rename = "_meta", default, skip_serializing_if = "StateMeta::is_empty")]
+    pub meta: StateMeta,
     #[serde(flatten)]
     pub extra: IndexMap<String, serde_json::Value>,
 }
 
+/// The `_meta` block of the state file. JSON has no comments, so this is
+/// where a hand-maintained file says what it is for and who looks after it.
+/// envhub shows it read-only and never acts on it; other top-level keys such
+/// as `_comment` are kept in [`State::extra`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct StateMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>,
+}
+
+impl StateMeta {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.maintainer.is_none() && self.extra.is_empty()
+    }
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl State {
+    /// The mode an install uses: `requested`, else `default_install_mode`.
+    pub fn install_mode(&self, requested: Option<crate::InstallMode>) -> crate::InstallMode {
+        requested.unwrap_or(self.default_install_mode)
+    }
+
+    /// The app a shim named `name` launches: the app of that name, else the
+    /// first app listing `name` in its `aliases`. Returns the app's own name.
+    pub fn lookup_app(&self, name: &str) -> Option<(&String, &AppConfig)> {
+        self.apps.get_key_value(name).or_else(|| {
+            self.apps
+                .iter()
+                .find(|(_, app)| app.aliases.iter().any(|alias| alias == name))
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
     pub installed: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    /// Makes the launcher ask "Run <app> with profile <profile>? [y/N]" before
+    /// each launch, for tools that reach production.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub confirm_before_run: bool,
+    /// Folder the app is listed under, e.g. "AI tools". Display only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
     #[serde(default)]
     pub target_binary: String,
+    /// Fallback targets tried in order when `target_binary` does not resolve.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_candidates: Vec<String>,
+    /// Directory bare target names are looked up in before `PATH`, e.g. a
+    /// version manager's `~/.nvm/versions/node/v18/bin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_root: Option<String>,
+    /// What `./`- and `../`-prefixed targets are relative to.
+    #[serde(default, skip_serializing_if = "RelativeTargets::is_cwd")]
+    pub relative_targets: RelativeTargets,
+    /// Directories put in front of the target's `PATH` (`~` and `$VAR`
+    /// allowed), first entry first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_prepend: Vec<String>,
+    /// Lets the launcher reuse `resolved_target` instead of resolving the
+    /// target on every launch, for targets on slow network mounts. Only
+    /// `set_target_cache` and `refresh_resolved_target` fill the cache; the
+    /// launcher never writes it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache_target: bool,
+    /// The target as last resolved while `cache_target` is on. Local to this
+    /// machine, so exports and shares drop it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_target: Option<ResolvedTarget>,
     #[serde(default)]
     pub install_path: Option<String>,
     #[serde(default)]
     pub active_profile: Option<String>,
+    /// The profile launches fall back to when `active_profile` is unset or
+    /// names a missing profile, ahead of the first profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "EnvOrder::is_append")]
+    pub env_order: EnvOrder,
+    /// Profile names this app may use, replacing the state-level list when
+    /// non-empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_profiles: Vec<String>,
     #[serde(default)]
     pub profiles: IndexMap<String, ProfileConfig>,
+    /// Extra shim names that launch this app with the same configuration,
+    /// e.g. `claudex` next to `claude`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// When the app was first registered (RFC 3339, UTC). Missing for apps
+    /// registered by older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
     #[serde(flatten)]
     pub extra: IndexMap<String, serde_json::Value>,
 }
 
-pub fn default_state_path() -> Result<PathBuf, CoreError> {
-    let base = config_dir().ok_or_else(|| {
-        CoreError::new(
-            ErrorCode::InstallPath,
-            "Failed to resolve config directory".to_string(),
+/// Where `set_profile_env` places a new key within a profile's env.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvOrder {
+    /// New keys go after existing ones (insertion order).
+    #[default]
+    Append,
+    /// Keys are kept sorted alphabetically.
+    Sorted,
+}
+
+impl EnvOrder {
+    pub fn is_append(&self) -> bool {
+        *self == EnvOrder::Append
+    }
+}
+
+/// The directory a `./tool` or `../tool` target is resolved against.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelativeTargets {
+    /// The launcher's working directory, i.e. wherever the shim was run.
+    #[default]
+    Cwd,
+    /// The app's `install_path`, or the user install dir when it has none,
+    /// so the target can sit next to its shim.
+    InstallPath,
+    /// A fixed directory.
+    Base(String),
+}
+
+impl RelativeTargets {
+    pub fn is_cwd(&self) -> bool {
+        *self == RelativeTargets::Cwd
+    }
+}
+
+impl AppConfig {
+    /// The profile a launch uses: `active_profile` when it names an existing
+    /// profile, else `default_profile` when that does, else the first profile.
+    pub fn resolve_active_profile(&self) -> Option<(&String, &ProfileConfig)> {
+        [&self.active_profile, &self.default_profile]
+            .into_iter()
+            .flatten()
+            .find_map(|name| self.profiles.get_key_value(name))
+            .or_else(|| self.profiles.first())
+    }
+
+    /// The variables `profile` sets: the `env` of each profile it includes,
+    /// in order and each with its own includes beneath it, then its own
+    /// `env`. Later layers win and a key keeps its first position, as in
+    /// [`crate::merge_environments`]. Fails on an include cycle, a missing
+    /// profile, or nesting deeper than [`MAX_INCLUDE_DEPTH`].
+    pub fn layered_env(&self, profile: &str) -> Result<IndexMap<String, String>, CoreError> {
+        let mut layers = Vec::new();
+        self.collect_env_layers(profile, &mut Vec::new(), &mut layers)?;
+        Ok(crate::env::merge_string_layers(&layers))
+    }
+
+    fn collect_env_layers<'a>(
+        &'a self,
+        profile: &'a str,
+        chain: &mut Vec<&'a str>,
+        layers: &mut Vec<&'a IndexMap<String, String>>,
+    ) -> Result<(), CoreError> {
+        if chain.contains(&profile) {
+            chain.push(profile);
+            return Err(CoreError::new(
+                ErrorCode::InvalidState,
+                format!("Profile includes form a cycle: {}", chain.join(" -> ")),
+            ));
+        }
+        if chain.len() > MAX_INCLUDE_DEPTH {
+            return Err(CoreError::new(
+                ErrorCode::InvalidState,
+                format!(
+                    "Profile includes nest deeper than {MAX_INCLUDE_DEPTH} levels: {} -> {profile}",
+                    chain.join(" -> ")
+                ),
+            ));
+        }
+        let config = self.profiles.get(profile).ok_or_else(|| {
+            let message = match chain.last() {
+                Some(parent) => format!("Profile \"{profile}\" included by \"{parent}\" not found"),
+                None => format!("Profile \"{profile}\" not found"),
+            };
+            CoreError::new(ErrorCode::ProfileNotFound, message)
+        })?;
+        chain.push(profile);
+        for include in &config.include {
+            self.collect_env_layers(include, chain, layers)?;
+        }
+        chain.pop();
+        layers.push(&config.env);
+        Ok(())
+    }
+
+    /// `target_binary` followed by `target_candidates`, skipping blank
+    /// entries. With a `target_root`, each bare name is preceded by the same
+    /// name under that root, so the rooted binary wins when it exists and
+    /// `PATH` is the fallback. `./`/`../` targets are joined onto the
+    /// [`RelativeTargets`] base unless that is the working directory. `mode`
+    /// picks the default install dir that `InstallPath` falls back to, normally
+    /// the state's `default_install_mode`.
+    pub fn target_list(&self, mode: crate::InstallMode) -> Vec<String> {
+        self.rooted_targets(
+            std::iter::once(&self.target_binary).chain(self.target_candidates.iter()),
+            mode,
         )
-    })?;
+    }
+
+    /// The targets to try while `profile` is active: the profile's
+    /// `target_binary` alone when it sets one, otherwise [`Self::target_list`].
+    pub fn target_list_for(
+        &self,
+        profile: Option<&ProfileConfig>,
+        mode: crate::InstallMode,
+    ) -> Vec<String> {
+        match profile
+            .and_then(|profile| profile.target_binary.as_ref())
+            .filter(|target| !target.trim().is_empty())
+        {
+            Some(target) => self.rooted_targets(std::iter::once(target), mode),
+            None => self.target_list(mode),
+        }
+    }
+
+    fn rooted_targets<'a>(
+        &self,
+        targets: impl Iterator<Item = &'a String>,
+        mode: crate::InstallMode,
+    ) -> Vec<String> {
+        let root = self
+            .target_root
+            .as_deref()
+            .map(str::trim)
+            .filter(|root| !root.is_empty());
+        let base = self.relative_base(mode);
+        let mut list = Vec::new();
+        for target in targets {
+            let target = target.trim();
+            if target.is_empty() {
+                continue;
+            }
+            if let Some(base) = &base
+                && is_dot_relative(target)
+            {
+                list.push(base.join(target).to_string_lossy().to_string());
+                continue;
+            }
+            if let Some(root) = root
+                && Path::new(target).components().count() == 1
+                && !Path::new(target).is_absolute()
+            {
+                list.push(Path::new(root).join(target).to_string_lossy().to_string());
+            }
+            list.push(target.to_string());
+        }
+        list
+    }
+
+    fn relative_base(&self, mode: crate::InstallMode) -> Option<PathBuf> {
+        match &self.relative_targets {
+            RelativeTargets::Cwd => None,
+            RelativeTargets::InstallPath => self
+                .install_path
+                .as_deref()
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| {
+                    crate::detect_platform(mode)
+                        .ok()
+                        .map(|platform| platform.install_dir)
+                }),
+            RelativeTargets::Base(dir) => Some(PathBuf::from(dir.trim())),
+        }
+    }
+}
+
+pub(crate) fn is_dot_relative(target: &str) -> bool {
+    let separators: &[char] = if cfg!(windows) { &['/', '\\'] } else { &['/'] };
+    [".", ".."].iter().any(|dots| {
+        target
+            .strip_prefix(dots)
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|next| separators.contains(&next))
+    })
+}
+
+/// Where the config lives: `$ENVHUB_STATE` when set, so tests and
+/// sandboxes can point envhub at their own file, otherwise `config.json`
+/// under the platform config directory. Minimal containers where that
+/// directory can't be determined fall back to `$XDG_CONFIG_HOME/envhub`, then
+/// `$HOME/.config/envhub`.
+pub fn default_state_path() -> Result<PathBuf, CoreError> {
+    state_path_from(|name| std::env::var_os(name), config_dir())
+}
+
+fn state_path_from(
+    var: impl Fn(&str) -> Option<OsString>,
+    config_dir: Option<PathBuf>,
+) -> Result<PathBuf, CoreError> {
+    if let Some(path) = var("ENVHUB_STATE")
+        && !path.is_empty()
+    {
+        return Ok(PathBuf::from(path));
+    }
     let envhub_dir = if cfg!(windows) { "EnvHub" } else { "envhub" };
+    let absolute = |name| var(name).map(PathBuf::from).filter(|dir| dir.is_absolute());
+    let base = config_dir
+        .or_else(|| absolute("XDG_CONFIG_HOME"))
+        .or_else(|| absolute("HOME").map(|home| home.join(".config")))
+        .ok_or_else(|| {
+            CoreError::new(
+                ErrorCode::InstallPath,
+                "Failed to resolve config directory; set ENVHUB_STATE or HOME".to_string(),
+            )
+        })?;
     Ok(base.join(envhub_dir).join("config.json"))
 }
 
 pub fn load_state() -> Result<State, CoreError> {
-    let path = default_state_path()?;
-    load_state_from_path(&path)
+    let store = crate::FileStore::open_default()?;
+    crate::StateStore::load(&store)
 }
 
 pub fn load_state_from_path(path: &Path) -> Result<State, CoreError> {
@@ -72,33 +499,269 @@ pub fn load_state_from_path(path: &Path) -> Result<State, CoreError> {
     })
 }
 
+/// Result of comparing a state file's stored checksum with its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The file has no checksum (never saved by envhub, or hand-written).
+    Missing,
+    Valid,
+    /// `apps` changed since envhub last wrote the file.
+    Mismatch,
+}
+
+/// Loads the state like `load_state_from_path` and additionally reports
+/// whether the file was edited outside envhub. A mismatch is informational,
+/// never an error.
+pub fn load_state_checked(path: &Path) -> Result<(State, ChecksumStatus), CoreError> {
+    if !path.exists() {
+        return Ok((State::default(), ChecksumStatus::Missing));
+    }
+    let data = fs::read_to_string(path).map_err(|err| {
+        CoreError::new(ErrorCode::Io, format!("Failed to read config.json: {err}"))
+    })?;
+    let raw: serde_json::Value = serde_json::from_str(&data).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Json,
+            format!("Failed to parse config.json: {err}"),
+        )
+    })?;
+    // Verify against the raw file rather than the re-serialized struct so new
+    // fields with defaults don't register as edits after an upgrade.
+    let status = match raw.get("checksum").and_then(|value| value.as_str()) {
+        None => ChecksumStatus::Missing,
+        Some(stored) => {
+            let apps = raw
+                .get("apps")
+                .cloned()
+                .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+            if checksum_value(&apps) == stored {
+                ChecksumStatus::Valid
+            } else {
+                ChecksumStatus::Mismatch
+            }
+        }
+    };
+    // Parse the struct from the text, not from `raw`: `serde_json::Value`
+    // sorts object keys, which would reorder apps, profiles and env.
+    let state = serde_json::from_str(&data).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Json,
+            format!("Failed to parse config.json: {err}"),
+        )
+    })?;
+    Ok((state, status))
+}
+
+fn checksum_value(value: &serde_json::Value) -> String {
+    let mut canonical = String::new();
+    write_canonical_json(value, &mut canonical);
+    let digest = Sha256::digest(canonical.as_bytes());
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Writes `value` as compact JSON with object keys sorted, so the checksum
+/// does not depend on key order.
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
 pub fn save_state(state: &State) -> Result<(), CoreError> {
+    let store = crate::FileStore::open_default()?;
+    crate::StateStore::save(&store, state)?;
+    crate::observe::notify(&crate::StateChange::StateSaved);
+    Ok(())
+}
+
+/// Validates a state received from outside (e.g. the GUI) and saves it, so an
+/// app without a target never reaches the launcher. `validate_state` may fill
+/// in missing profiles and active profiles before the write.
+pub fn save_state_validated(state: &State) -> Result<(), CoreError> {
     let path = default_state_path()?;
-    save_state_to_path(&path, state)
+    save_state_validated_in(&path, state)
+}
+
+pub fn save_state_validated_in(path: &Path, state: &State) -> Result<(), CoreError> {
+    let mut state = state.clone();
+    validate_state(&mut state)?;
+    save_state_to_path(path, &state)?;
+    crate::observe::notify(&crate::StateChange::StateSaved);
+    Ok(())
+}
+
+/// How the state file is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SaveOptions {
+    /// Write apps and each app's profiles sorted by name, for stable diffs
+    /// when the file is shared through git. Only the file is affected: the
+    /// in-memory order is kept, and loading keeps whatever order the file has.
+    pub sort_keys: bool,
+}
+
+pub fn save_state_with(state: &State, options: SaveOptions) -> Result<(), CoreError> {
+    let path = default_state_path()?;
+    save_state_to_path_with(&path, state, options)?;
+    crate::observe::notify(&crate::StateChange::StateSaved);
+    Ok(())
+}
+
+/// Whether `ENVHUB_READONLY` is set (to anything but empty or `0`), in which
+/// case every write of the state file is refused. Meant for locked-down
+/// machines and shared state files that launchers only read.
+pub fn is_state_readonly() -> bool {
+    readonly_flag(std::env::var_os("ENVHUB_READONLY").as_deref())
+}
+
+fn readonly_flag(value: Option<&std::ffi::OsStr>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "0")
 }
 
 pub fn save_state_to_path(path: &Path, state: &State) -> Result<(), CoreError> {
+    save_state_to_path_with(path, state, SaveOptions::default())
+}
+
+pub fn save_state_to_path_with(
+    path: &Path,
+    state: &State,
+    options: SaveOptions,
+) -> Result<(), CoreError> {
+    write_state(path, state, options, false)
+}
+
+/// Saves a change the user made, copying the old file aside first so
+/// `undo_last` can restore it. Whole-state saves skip the snapshot.
+pub(crate) fn save_state_undoable(path: &Path, state: &State) -> Result<(), CoreError> {
+    write_state(path, state, SaveOptions::default(), true)
+}
+
+fn write_state(
+    path: &Path,
+    state: &State,
+    options: SaveOptions,
+    undoable: bool,
+) -> Result<(), CoreError> {
+    ensure_writable(path)?;
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|err| {
+        create_private_dir(parent).map_err(|err| {
             CoreError::new(
                 ErrorCode::Io,
                 format!("Failed to create config.json directory: {err}"),
             )
         })?;
     }
-    let data = serde_json::to_vec_pretty(state).map_err(|err| {
+    let data = render_state(state, options)?;
+    if undoable {
+        crate::undo::snapshot_before_write(path)?;
+    }
+    write_private(path, &data)
+        .map_err(|err| CoreError::new(ErrorCode::Io, format!("Failed to write config.json: {err}")))
+}
+
+/// Refuses writes to `path` while [`is_state_readonly`].
+pub(crate) fn ensure_writable(path: &Path) -> Result<(), CoreError> {
+    ensure_writable_with(path, is_state_readonly())
+}
+
+pub(crate) fn ensure_writable_with(path: &Path, readonly: bool) -> Result<(), CoreError> {
+    if readonly {
+        return Err(CoreError::new(
+            ErrorCode::Permission,
+            format!(
+                "Not writing {}: state is read-only because ENVHUB_READONLY is set",
+                path.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The bytes of the state file for `state`, with a fresh checksum.
+pub(crate) fn render_state(state: &State, options: SaveOptions) -> Result<Vec<u8>, CoreError> {
+    let apps = serde_json::to_value(&state.apps).map_err(|err| {
         CoreError::new(
             ErrorCode::Json,
             format!("Failed to serialize config.json: {err}"),
         )
     })?;
-    fs::write(path, data)
-        .map_err(|err| CoreError::new(ErrorCode::Io, format!("Failed to write config.json: {err}")))
+    let mut state = state.clone();
+    state.checksum = Some(checksum_value(&apps));
+    if options.sort_keys {
+        state.apps.sort_keys();
+        for app in state.apps.values_mut() {
+            app.profiles.sort_keys();
+        }
+    }
+    serde_json::to_vec_pretty(&state).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Json,
+            format!("Failed to serialize config.json: {err}"),
+        )
+    })
+}
+
+/// Creates `dir` and any missing parents, owner-only (`0o700`) on unix since
+/// the state may hold tokens. Directories that already exist keep their
+/// permissions.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+    }
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(dir)
+    }
+}
+
+/// Writes `data` to `path`, creating it owner-only (`0o600`) on unix. An
+/// existing file keeps its permissions, e.g. a state file deliberately shared
+/// with a group.
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(data)
 }
 
 pub fn validate_state(state: &mut State) -> Result<(), CoreError> {
+    let mode = state.default_install_mode;
     for (name, app) in state.apps.iter_mut() {
-        if app.target_binary.trim().is_empty() {
+        if app.target_list(mode).is_empty() {
             return Err(CoreError::new(
                 ErrorCode::InvalidState,
                 format!("App \"{name}\" is missing target_binary"),
@@ -110,11 +773,41 @@ pub fn validate_state(state: &mut State) -> Result<(), CoreError> {
                 .insert("default".to_string(), ProfileConfig::default());
         }
 
-        let active = app.active_profile.clone();
-        let resolved = active
-            .filter(|profile| app.profiles.contains_key(profile))
-            .or_else(|| app.profiles.keys().next().cloned());
-        app.active_profile = resolved;
+        app.active_profile = app.resolve_active_profile().map(|(name, _)| name.clone());
+
+        for (profile, config) in &app.profiles {
+            let dangling: Vec<&str> = config
+                .include
+                .iter()
+                .filter(|included| !app.profiles.contains_key(included.as_str()))
+                .map(String::as_str)
+                .collect();
+            if !dangling.is_empty() {
+                return Err(CoreError::new(
+                    ErrorCode::InvalidState,
+                    format!(
+                        "Profile \"{profile}\" of app \"{name}\" includes missing profiles: {}",
+                        dangling.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+    let mut launches: HashMap<&str, &str> = HashMap::new();
+    for name in state.apps.keys() {
+        launches.insert(name, name);
+    }
+    for (name, app) in &state.apps {
+        for alias in &app.aliases {
+            if let Some(owner) = launches.insert(alias, name)
+                && owner != name
+            {
+                return Err(CoreError::new(
+                    ErrorCode::InvalidState,
+                    format!("Alias \"{alias}\" of app \"{name}\" already launches app \"{owner}\""),
+                ));
+            }
+        }
     }
     Ok(())
 }
@@ -124,6 +817,123 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn dot_relative_targets_follow_the_configured_base() {
+        let mut app = AppConfig {
+            target_binary: "./tool".to_string(),
+            target_candidates: vec!["bin/tool".to_string(), "../shared/tool".to_string()],
+            install_path: Some("/opt/shims".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            app.target_list(crate::InstallMode::User),
+            ["./tool", "bin/tool", "../shared/tool"]
+        );
+
+        app.relative_targets = RelativeTargets::InstallPath;
+        let expected = [
+            Path::new("/opt/shims").join("./tool"),
+            PathBuf::from("bin/tool"),
+            Path::new("/opt/shims").join("../shared/tool"),
+        ];
+        let expected: Vec<String> = expected
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(app.target_list(crate::InstallMode::User), expected);
+
+        app.install_path = None;
+        let global = crate::detect_platform(crate::InstallMode::Global)
+            .expect("platform")
+            .install_dir;
+        assert_eq!(
+            app.target_list(crate::InstallMode::Global)[0],
+            global.join("./tool").to_string_lossy()
+        );
+
+        app.relative_targets = RelativeTargets::Base("/srv/tools".to_string());
+        assert_eq!(
+            app.target_list(crate::InstallMode::User)[0],
+            Path::new("/srv/tools").join("./tool").to_string_lossy()
+        );
+        assert!(!is_dot_relative(".tool"));
+        assert!(!is_dot_relative("..."));
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let pairs: Vec<(String, String)> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |name| {
+            pairs
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn new_state_files_are_private_to_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("envhub").join("config.json");
+        save_state_to_path(&path, &State::default()).expect("save");
+        let mode = |path: &Path| fs::metadata(path).expect("metadata").permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+        let shared = dir.path().join("shared.json");
+        fs::write(&shared, b"{}").expect("write");
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o640)).expect("chmod");
+        save_state_to_path(&shared, &State::default()).expect("save");
+        assert_eq!(mode(&shared), 0o640);
+    }
+
+    #[test]
+    fn readonly_flag_needs_a_non_zero_value() {
+        assert!(!readonly_flag(None));
+        assert!(!readonly_flag(Some("".as_ref())));
+        assert!(!readonly_flag(Some("0".as_ref())));
+        assert!(readonly_flag(Some("1".as_ref())));
+        assert!(readonly_flag(Some("true".as_ref())));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn state_path_falls_back_without_config_dir() {
+        let envhub_dir = "envhub";
+        let platform = PathBuf::from("/platform/config");
+        let all = [
+            ("ENVHUB_STATE", "/explicit/state.json"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+            ("HOME", "/home/me"),
+        ];
+
+        let path = state_path_from(vars(&all), Some(platform.clone())).expect("path");
+        assert_eq!(path, PathBuf::from("/explicit/state.json"));
+        let path = state_path_from(vars(&all[1..]), Some(platform.clone())).expect("path");
+        assert_eq!(path, platform.join(envhub_dir).join("config.json"));
+        let path = state_path_from(vars(&all[1..]), None).expect("path");
+        assert_eq!(path, Path::new("/xdg").join(envhub_dir).join("config.json"));
+        let path = state_path_from(vars(&[("ENVHUB_STATE", ""), ("HOME", "/home/me")]), None)
+            .expect("path");
+        assert_eq!(
+            path,
+            Path::new("/home/me/.config")
+                .join(envhub_dir)
+                .join("config.json")
+        );
+        let path =
+            state_path_from(vars(&[("XDG_CONFIG_HOME", "relative"), all[2]]), None).expect("path");
+        assert!(path.starts_with("/home/me/.config"));
+
+        let err = state_path_from(vars(&[]), None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InstallPath);
+    }
+
     #[test]
     fn validate_state_fills_missing_profiles_and_active() {
         let mut state = State::default();
@@ -141,6 +951,56 @@ mod tests {
         assert_eq!(app.active_profile.as_deref(), Some("default"));
     }
 
+    #[test]
+    fn validate_state_reports_dangling_includes() {
+        let mut state = State::default();
+        let mut app = AppConfig {
+            target_binary: "tool-bin".to_string(),
+            ..AppConfig::default()
+        };
+        app.profiles
+            .insert("base".to_string(), ProfileConfig::default());
+        app.profiles.insert(
+            "work".to_string(),
+            ProfileConfig {
+                include: vec!["base".to_string(), "gone".to_string()],
+                ..ProfileConfig::default()
+            },
+        );
+        state.apps.insert("tool".to_string(), app);
+
+        let err = validate_state(&mut state).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("\"work\""), "{}", err.message);
+        assert!(err.message.contains("gone"), "{}", err.message);
+        assert!(!err.message.contains("base"), "{}", err.message);
+
+        state.apps["tool"].profiles["work"].include.pop();
+        validate_state(&mut state).expect("validate");
+    }
+
+    #[test]
+    fn validate_state_rejects_aliases_that_launch_another_app() {
+        let app = |aliases: &[&str]| AppConfig {
+            target_binary: "bin".to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..AppConfig::default()
+        };
+        let mut state = State::default();
+        state.apps.insert("claude".to_string(), app(&["claudex"]));
+        state.apps.insert("codex".to_string(), app(&["cx"]));
+        validate_state(&mut state).expect("distinct aliases");
+
+        state.apps["codex"].aliases.push("claude".to_string());
+        let err = validate_state(&mut state).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("\"claude\""), "{}", err.message);
+
+        state.apps["codex"].aliases = vec!["claudex".to_string()];
+        let err = validate_state(&mut state).unwrap_err();
+        assert!(err.message.contains("\"claudex\""), "{}", err.message);
+    }
+
     #[test]
     fn save_and_load_preserves_unknown_fields() {
         let dir = TempDir::new().expect("temp dir");
@@ -161,4 +1021,358 @@ mod tests {
             serde_json::from_str(&fs::read_to_string(&path).expect("read")).expect("parse");
         assert!(value.get("future").is_some());
     }
+
+    #[test]
+    fn meta_and_comments_survive_save_load_and_validate() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let raw = r##"
+        {
+          "_comment": "managed by the platform team",
+          "_meta": { "description": "Team tools", "owner_slack": "#tools" },
+          "apps": { "tool": { "target_binary": "tool-bin" } }
+        }
+        "##;
+        fs::write(&path, raw).expect("write state");
+
+        crate::set_state_meta_in(&path, None, Some("  ops@example.com ")).expect("set meta");
+        let mut state = load_state_from_path(&path).expect("load");
+        validate_state(&mut state).expect("validate");
+        save_state_to_path(&path, &state).expect("save");
+
+        let state = load_state_from_path(&path).expect("load");
+        assert_eq!(state.meta.description.as_deref(), Some("Team tools"));
+        assert_eq!(state.meta.maintainer.as_deref(), Some("ops@example.com"));
+        assert_eq!(state.meta.extra["owner_slack"], "#tools");
+        assert_eq!(state.extra["_comment"], "managed by the platform team");
+        assert!(!state.extra.contains_key("_meta"));
+
+        crate::set_state_meta_in(&path, Some(""), Some(" ")).expect("clear");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.meta.description.is_none());
+        assert!(state.meta.maintainer.is_none());
+        assert!(!state.meta.is_empty());
+    }
+
+    #[test]
+    fn save_state_validated_rejects_app_without_target() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let mut state = State::default();
+        state
+            .apps
+            .insert("broken".to_string(), AppConfig::default());
+
+        let err = save_state_validated_in(&path, &state).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert!(err.message.contains("\"broken\""), "{}", err.message);
+        assert!(!path.exists());
+
+        state.apps["broken"].target_binary = "tool-bin".to_string();
+        save_state_validated_in(&path, &state).expect("save");
+        let loaded = load_state_from_path(&path).expect("load");
+        assert_eq!(
+            loaded.apps["broken"].active_profile.as_deref(),
+            Some("default")
+        );
+    }
+
+    #[test]
+    fn sort_keys_only_affects_the_file() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let mut state = State::default();
+        for name in ["zeta", "alpha"] {
+            let mut app = AppConfig {
+                target_binary: format!("{name}-bin"),
+                ..AppConfig::default()
+            };
+            for profile in ["work", "home"] {
+                app.profiles
+                    .insert(profile.to_string(), ProfileConfig::default());
+            }
+            state.apps.insert(name.to_string(), app);
+        }
+        let options = SaveOptions { sort_keys: true };
+        save_state_to_path_with(&path, &state, options).expect("save");
+
+        assert_eq!(
+            state.apps.keys().collect::<Vec<_>>(),
+            ["zeta", "alpha"],
+            "in-memory order must not change"
+        );
+        let loaded = load_state_from_path(&path).expect("load");
+        assert_eq!(loaded.apps.keys().collect::<Vec<_>>(), ["alpha", "zeta"]);
+        assert_eq!(
+            loaded.apps["zeta"].profiles.keys().collect::<Vec<_>>(),
+            ["home", "work"]
+        );
+        let (_, status) = load_state_checked(&path).expect("checked");
+        assert_eq!(status, ChecksumStatus::Valid);
+
+        save_state_to_path(&path, &state).expect("save unsorted");
+        let loaded = load_state_from_path(&path).expect("load");
+        assert_eq!(loaded.apps.keys().collect::<Vec<_>>(), ["zeta", "alpha"]);
+    }
+
+    #[test]
+    fn checksum_detects_external_edits() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let mut state = State::default();
+        state.apps.insert(
+            "tool".to_string(),
+            AppConfig {
+                target_binary: "tool-bin".to_string(),
+                ..AppConfig::default()
+            },
+        );
+        validate_state(&mut state).expect("validate");
+        save_state_to_path(&path, &state).expect("save");
+
+        let (_, status) = load_state_checked(&path).expect("load");
+        assert_eq!(status, ChecksumStatus::Valid);
+
+        // Reordering keys is not an edit.
+        let mut value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).expect("read")).expect("parse");
+        let app = value["apps"]["tool"].as_object().expect("app").clone();
+        let mut reversed = serde_json::Map::new();
+        for (key, field) in app.into_iter().rev() {
+            reversed.insert(key, field);
+        }
+        value["apps"]["tool"] = serde_json::Value::Object(reversed);
+        fs::write(&path, serde_json::to_string(&value).expect("json")).expect("write");
+        let (_, status) = load_state_checked(&path).expect("load");
+        assert_eq!(status, ChecksumStatus::Valid);
+
+        value["apps"]["tool"]["target_binary"] = serde_json::json!("other-bin");
+        fs::write(&path, serde_json::to_string(&value).expect("json")).expect("write");
+        let (state, status) = load_state_checked(&path).expect("load");
+        assert_eq!(status, ChecksumStatus::Mismatch);
+        assert_eq!(state.apps["tool"].target_binary, "other-bin");
+    }
+
+    #[test]
+    fn checksum_missing_for_hand_written_file() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        fs::write(&path, r#"{ "apps": {} }"#).expect("write");
+
+        let (_, status) = load_state_checked(&path).expect("load");
+        assert_eq!(status, ChecksumStatus::Missing);
+    }
+
+    /// Small deterministic generator so round-trip failures are reproducible
+    /// from the printed seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            // xorshift64*
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn chance(&mut self) -> bool {
+            self.next() & 1 == 0
+        }
+
+        fn text(&mut self) -> String {
+            const PIECES: &[&str] = &[
+                "",
+                "tool",
+                "naïve",
+                "日本語",
+                "🚀",
+                " spaced ",
+                "quote\"",
+                "back\\slash",
+                "new\nline",
+                "tab\t",
+                "$HOME",
+                "=",
+                "\u{0}",
+            ];
+            (0..self.below(3))
+                .map(|_| PIECES[self.below(PIECES.len())])
+                .collect()
+        }
+
+        fn option(&mut self) -> Option<String> {
+            self.chance().then(|| self.text())
+        }
+
+        fn json(&mut self, depth: usize) -> serde_json::Value {
+            match self.below(if depth == 0 { 4 } else { 6 }) {
+                0 => serde_json::Value::Null,
+                1 => serde_json::Value::Bool(self.chance()),
+                2 => serde_json::json!(self.next() as i64),
+                3 => serde_json::Value::String(self.text()),
+                4 => (0..self.below(3)).map(|_| self.json(depth - 1)).collect(),
+                _ => serde_json::Value::Object(
+                    (0..self.below(3))
+                        .map(|i| (format!("{}{i}", self.text()), self.json(depth - 1)))
+                        .collect(),
+                ),
+            }
+        }
+
+        /// Unknown fields; the prefix keeps them clear of real field names.
+        fn extra(&mut self) -> IndexMap<String, serde_json::Value> {
+            (0..self.below(3))
+                .map(|i| (format!("x_{i}{}", self.text()), self.json(2)))
+                .collect()
+        }
+
+        fn profile(&mut self) -> ProfileConfig {
+            ProfileConfig {
+                env: (0..self.below(4))
+                    .map(|i| (format!("{}{i}", self.text()), self.text()))
+                    .collect(),
+                command_args: (0..self.below(3)).map(|_| self.text()).collect(),
+                stdout_file: self.option(),
+                stderr_file: self.option(),
+                env_file: self.option(),
+                target_binary: self.option(),
+                notes: self.option(),
+                dangerous: self.chance(),
+                include: (0..self.below(3)).map(|_| self.text()).collect(),
+                isolated: self.chance(),
+                inherit_env: (0..self.below(3)).map(|_| self.text()).collect(),
+                env_history: (0..self.below(2))
+                    .map(|i| {
+                        let entry = EnvHistoryEntry {
+                            value: self.text(),
+                            replaced_at: self.text(),
+                        };
+                        (format!("{}{i}", self.text()), vec![entry])
+                    })
+                    .collect(),
+                extra: self.extra(),
+            }
+        }
+
+        fn app(&mut self) -> AppConfig {
+            AppConfig {
+                installed: self.chance(),
+                disabled: self.chance(),
+                confirm_before_run: self.chance(),
+                group: self.option(),
+                target_binary: self.text(),
+                target_candidates: (0..self.below(3)).map(|_| self.text()).collect(),
+                target_root: self.option(),
+                relative_targets: match self.below(3) {
+                    0 => RelativeTargets::Cwd,
+                    1 => RelativeTargets::InstallPath,
+                    _ => RelativeTargets::Base(self.text()),
+                },
+                path_prepend: (0..self.below(3)).map(|_| self.text()).collect(),
+                cache_target: self.chance(),
+                resolved_target: self.chance().then(|| ResolvedTarget {
+                    targets: (0..self.below(3)).map(|_| self.text()).collect(),
+                    path: self.text(),
+                }),
+                install_path: self.option(),
+                active_profile: self.option(),
+                env_order: if self.chance() {
+                    EnvOrder::Append
+                } else {
+                    EnvOrder::Sorted
+                },
+                allowed_profiles: (0..self.below(3)).map(|_| self.text()).collect(),
+                profiles: (0..self.below(4))
+                    .map(|i| (format!("{}{i}", self.text()), self.profile()))
+                    .collect(),
+                aliases: (0..self.below(3)).map(|_| self.text()).collect(),
+                created_at: self.option(),
+                default_profile: self.option(),
+                extra: self.extra(),
+            }
+        }
+
+        fn state(&mut self) -> State {
+            State {
+                apps: (0..self.below(4))
+                    .map(|i| (format!("{}{i}", self.text()), self.app()))
+                    .collect(),
+                checksum: None,
+                allowed_profiles: (0..self.below(3)).map(|_| self.text()).collect(),
+                env_history_limit: self.below(4),
+                version: self.below(3) as u32,
+                default_install_mode: if self.chance() {
+                    crate::InstallMode::User
+                } else {
+                    crate::InstallMode::Global
+                },
+                launcher_name: self.option(),
+                meta: StateMeta {
+                    description: self.option(),
+                    maintainer: self.option(),
+                    extra: self.extra(),
+                },
+                extra: self.extra(),
+            }
+        }
+    }
+
+    #[test]
+    fn arbitrary_states_round_trip() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        for seed in 1..=300u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let state = rng.state();
+            save_state_to_path(&path, &state).expect("save");
+            let (loaded, status) = load_state_checked(&path).expect("load");
+            assert_eq!(status, ChecksumStatus::Valid, "seed {seed}");
+
+            let mut expected = state.clone();
+            expected.checksum = loaded.checksum.clone();
+            assert_eq!(
+                serde_json::to_string(&loaded).expect("json"),
+                serde_json::to_string(&expected).expect("json"),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_fields_survive_load_and_save_at_every_level() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("state.json");
+        let raw = serde_json::json!({
+            "apps": {
+                "tööl": {
+                    "target_binary": "tool-bin",
+                    "app_future": [1, {"nested": null}],
+                    "profiles": {
+                        "dëfault": { "env": {}, "profile_future": "x" }
+                    }
+                }
+            },
+            "state_future": { "flag": true }
+        });
+        fs::write(&path, raw.to_string()).expect("write");
+
+        let state = load_state_from_path(&path).expect("load");
+        save_state_to_path(&path, &state).expect("save");
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).expect("read")).expect("parse");
+        assert_eq!(value["state_future"], raw["state_future"]);
+        assert_eq!(
+            value["apps"]["tööl"]["app_future"],
+            raw["apps"]["tööl"]["app_future"]
+        );
+        assert_eq!(
+            value["apps"]["tööl"]["profiles"]["dëfault"]["profile_future"],
+            "x"
+        );
+    }
 }
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{CoreError, State, load_state_from_path, save_state_to_path};
+
+/// Where the state is kept. [`FileStore`] is the `config.json` everything
+/// uses today; other backends (sqlite, a remote store) plug in here.
+pub trait StateStore {
+    /// The stored state, or an empty one if nothing has been saved yet.
+    fn load(&self) -> Result<State, CoreError>;
+    fn save(&self, state: &State) -> Result<(), CoreError>;
+    /// Saves a change the user made through one of the mutators, so that
+    /// `undo_last` can revert it. Stores without an undo snapshot just save.
+    fn save_undoable(&self, state: &State) -> Result<(), CoreError> {
+        self.save(state)
+    }
+}
+
+/// The state file at a path, with the usual checksum, permissions and
+/// `ENVHUB_READONLY` handling of `save_state_to_path`, plus the undo
+/// snapshot for changes made through the mutators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The store at `default_state_path()`.
+    pub fn open_default() -> Result<Self, CoreError> {
+        crate::default_state_path().map(Self::new)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl StateStore for FileStore {
+    fn load(&self) -> Result<State, CoreError> {
+        load_state_from_path(&self.path)
+    }
+
+    fn save(&self, state: &State) -> Result<(), CoreError> {
+        save_state_to_path(&self.path, state)
+    }
+
+    fn save_undoable(&self, state: &State) -> Result<(), CoreError> {
+        crate::state::save_state_undoable(&self.path, state)
+    }
+}
+
+/// A bare path is the [`FileStore`] at that path, so every `*_in(path, …)`
+/// function also accepts any other store.
+impl StateStore for Path {
+    fn load(&self) -> Result<State, CoreError> {
+        load_state_from_path(self)
+    }
+
+    fn save(&self, state: &State) -> Result<(), CoreError> {
+        save_state_to_path(self, state)
+    }
+
+    fn save_undoable(&self, state: &State) -> Result<(), CoreError> {
+        crate::state::save_state_undoable(self, state)
+    }
+}
+
+impl StateStore for PathBuf {
+    fn load(&self) -> Result<State, CoreError> {
+        self.as_path().load()
+    }
+
+    fn save(&self, state: &State) -> Result<(), CoreError> {
+        self.as_path().save(state)
+    }
+
+    fn save_undoable(&self, state: &State) -> Result<(), CoreError> {
+        self.as_path().save_undoable(state)
+    }
+}
+
+/// A state held in memory, for tests and embedders that never touch disk.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+impl MemoryStore {
+    pub fn new(state: State) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    /// The state as last saved.
+    pub fn snapshot(&self) -> State {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self) -> Result<State, CoreError> {
+        Ok(self.snapshot())
+    }
+
+    fn save(&self, state: &State) -> Result<(), CoreError> {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = state.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCode;
+    use tempfile::TempDir;
+
+    #[test]
+    fn memory_store_keeps_updates_and_drops_failed_ones() {
+        let store = MemoryStore::default();
+        crate::register_app_in(&store, "tool", "tool-bin").expect("register");
+        let err = crate::add_profile_in(&store, "missing", "work").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AppNotFound);
+        let state = store.snapshot();
+        assert_eq!(state.apps.keys().collect::<Vec<_>>(), ["tool"]);
+        assert_eq!(state.apps["tool"].target_binary, "tool-bin");
+    }
+
+    #[test]
+    fn mutators_run_against_a_memory_store() {
+        let store = MemoryStore::default();
+        let dyn_store: &dyn StateStore = &store;
+        crate::register_app_in(dyn_store, "claude", "claude-bin").expect("register");
+        crate::add_profile_in(&store, "claude", "work").expect("add profile");
+        crate::set_profile_env_in(&store, "claude", "work", "KEY", "VALUE").expect("set env");
+        crate::set_active_profile_in(&store, "claude", "work").expect("activate");
+        let err = crate::set_active_profile_in(&store, "claude", "missing").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+
+        let state = store.snapshot();
+        let app = &state.apps["claude"];
+        assert_eq!(app.active_profile.as_deref(), Some("work"));
+        assert_eq!(app.profiles["work"].env["KEY"], "VALUE");
+        assert_eq!(
+            crate::list_profiles_in(&store, "claude").expect("list"),
+            ["default", "work"]
+        );
+    }
+
+    #[test]
+    fn file_store_reads_what_the_free_functions_write() {
+        let dir = TempDir::new().expect("temp dir");
+        let store = FileStore::new(dir.path().join("config.json"));
+        assert!(store.load().expect("load").apps.is_empty());
+
+        crate::register_app_in(&store, "tool", "tool-bin").expect("register");
+        crate::register_app_in(store.path(), "other", "other-bin").expect("register");
+        let state = store.load().expect("load");
+        assert_eq!(state.apps.keys().collect::<Vec<_>>(), ["tool", "other"]);
+        assert_eq!(
+            serde_json::to_value(&state).unwrap(),
+            serde_json::to_value(load_state_from_path(store.path()).expect("load")).unwrap()
+        );
+    }
+}
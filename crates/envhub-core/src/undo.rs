@@ -0,0 +1,138 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{CoreError, ErrorCode, State, default_state_path};
+
+/// Path of the single-level undo snapshot kept next to a state file
+/// (`config.json` -> `config.json.undo`).
+pub fn undo_path_for(path: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_else(|| OsString::from("config.json"));
+    name.push(".undo");
+    path.with_file_name(name)
+}
+
+/// Copies the current state file aside before it is overwritten. A missing
+/// state file is recorded as an empty state so the first change can be undone
+/// too.
+pub(crate) fn snapshot_before_write(path: &Path) -> Result<(), CoreError> {
+    let undo_path = undo_path_for(path);
+    let result = if path.exists() {
+        fs::copy(path, &undo_path).map(|_| ())
+    } else {
+        let data = serde_json::to_vec_pretty(&State::default()).map_err(|err| {
+            CoreError::new(
+                ErrorCode::Json,
+                format!("Failed to serialize undo snapshot: {err}"),
+            )
+        })?;
+        fs::write(&undo_path, data)
+    };
+    result.map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to write undo snapshot: {err}"),
+        )
+    })
+}
+
+/// Restores the state as it was before the last change and clears the
+/// snapshot. Only one level is kept, so a second undo fails. Like any other
+/// write, it is refused while `ENVHUB_READONLY` is set.
+pub fn undo_last() -> Result<(), CoreError> {
+    let path = default_state_path()?;
+    undo_last_in(&path)
+}
+
+pub fn undo_last_in(path: &Path) -> Result<(), CoreError> {
+    restore_snapshot(path, crate::is_state_readonly())
+}
+
+fn restore_snapshot(path: &Path, readonly: bool) -> Result<(), CoreError> {
+    crate::state::ensure_writable_with(path, readonly)?;
+    let undo_path = undo_path_for(path);
+    if !undo_path.exists() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidState,
+            "Nothing to undo".to_string(),
+        ));
+    }
+    fs::rename(&undo_path, path).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to restore undo snapshot: {err}"),
+        )
+    })?;
+    crate::observe::notify(&crate::StateChange::Undone);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_state_from_path, register_app_in, remove_profile_env_in, set_profile_env_in};
+    use tempfile::TempDir;
+
+    #[test]
+    fn undo_reverts_register() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+
+        undo_last_in(&path).expect("undo");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps.is_empty());
+        assert!(!undo_path_for(&path).exists());
+
+        let err = undo_last_in(&path).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn undo_reverts_delete() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "KEY", "VALUE").expect("set env");
+        remove_profile_env_in(&path, "tool", "default", "KEY").expect("remove env");
+
+        undo_last_in(&path).expect("undo");
+        let state = load_state_from_path(&path).expect("load");
+        let profile = &state.apps["tool"].profiles["default"];
+        assert_eq!(profile.env.get("KEY").map(String::as_str), Some("VALUE"));
+    }
+
+    #[test]
+    fn whole_state_saves_leave_the_snapshot_alone() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        set_profile_env_in(&path, "tool", "default", "KEY", "VALUE").expect("set env");
+        let snapshot = fs::read(undo_path_for(&path)).expect("snapshot");
+
+        let state = load_state_from_path(&path).expect("load");
+        crate::save_state_validated_in(&path, &state).expect("save");
+        crate::save_state_to_path(&path, &state).expect("save");
+        assert_eq!(fs::read(undo_path_for(&path)).expect("snapshot"), snapshot);
+
+        undo_last_in(&path).expect("undo");
+        let state = load_state_from_path(&path).expect("load");
+        assert!(state.apps["tool"].profiles["default"].env.is_empty());
+    }
+
+    #[test]
+    fn undo_is_refused_while_readonly() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("config.json");
+        register_app_in(&path, "tool", "tool-bin").expect("register");
+        let before = fs::read(&path).expect("read state");
+
+        let err = restore_snapshot(&path, true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Permission);
+        assert_eq!(fs::read(&path).expect("read state"), before);
+        assert!(undo_path_for(&path).exists());
+    }
+}
@@ -1,111 +1,643 @@
-use std::collections::HashMap;
 use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitCode};
+use std::process::{Command, ExitCode, Stdio};
+use std::time::{Duration, Instant};
 
-use envhub_core::{AppConfig, CoreError, ErrorCode};
+use envhub_core::{AppConfig, CoreError, ErrorCode, InstallMode, ProfileConfig};
+use indexmap::IndexMap;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Lets `reshim` tell copies of this binary apart from anything else on disk.
+#[used]
+static BUILD_MAGIC: [u8; 32] = envhub_core::LAUNCHER_MAGIC;
+
 fn main() -> ExitCode {
+    std::hint::black_box(&BUILD_MAGIC);
     match run() {
         Ok(code) => code,
         Err(err) => {
             eprintln!("envhub-launcher error: {} - {}", err.code, err.message);
-            ExitCode::from(1)
+            ExitCode::from(exit_code_for(err.code))
         }
     }
 }
 
+/// The launcher's own exit status for a failure, so shell callers can tell
+/// them apart via `$?`. 126 and 127 follow the shell's "cannot execute" and
+/// "not found"; 64 and 78 are sysexits' usage and config errors.
+fn exit_code_for(code: ErrorCode) -> u8 {
+    match code {
+        ErrorCode::MissingArgv0 => 64,
+        ErrorCode::SelfReference => 78,
+        ErrorCode::ExecFailed => 126,
+        ErrorCode::TargetNotFound => 127,
+        _ => 1,
+    }
+}
+
 fn run() -> Result<ExitCode, CoreError> {
     let app_name = app_name_from_argv0()
-        .ok_or_else(|| CoreError::new(ErrorCode::InvalidState, "Missing argv[0]".to_string()))?;
+        .ok_or_else(|| CoreError::new(ErrorCode::MissingArgv0, "Missing argv[0]".to_string()))?;
 
     // Only handle --version/--help when directly running envhub-launcher
-    // For aliases (e.g., claudex), pass all args through to the target binary
-    if app_name == "envhub-launcher" {
-        let args: Vec<String> = std::env::args().collect();
-        if args.len() > 1 {
-            match args[1].as_str() {
-                "--version" | "-v" => {
-                    println!("envhub-launcher {}", VERSION);
-                    return Ok(ExitCode::SUCCESS);
-                }
-                "--help" | "-h" => {
-                    print_help();
-                    return Ok(ExitCode::SUCCESS);
-                }
-                _ => {}
-            }
-        }
-
-        // Prevent direct execution of envhub-launcher without flags
-        eprintln!("Error: envhub-launcher should not be run directly.");
-        eprintln!("This binary is meant to be symlinked/copied with your app name.");
-        eprintln!();
-        eprintln!("Usage:");
-        eprintln!("  1. Register an app in envhub TUI");
-        eprintln!("  2. Install the shim for that app");
-        eprintln!("  3. Run your app by its alias name (e.g., 'iclaude', 'inode')");
-        eprintln!();
-        eprintln!("For more information, run: envhub-launcher --help");
-        return Ok(ExitCode::from(1));
+    // For aliases (e.g., claudex), pass all args through to the target binary.
+    // A name recorded in the state is checked once it is loaded below.
+    let direct = app_name == envhub_core::DEFAULT_LAUNCHER_NAME
+        || app_name == envhub_core::launcher_name_in(&envhub_core::State::default());
+    if direct {
+        return run_as_launcher();
     }
+    let mut timing = Timing::new(env_flag(std::env::var_os(TIMING_ENV)));
     let state = envhub_core::load_state()?;
+    timing.phase("state");
+    if state.lookup_app(&app_name).is_none() && app_name == envhub_core::launcher_name_in(&state) {
+        return run_as_launcher();
+    }
 
-    let (target_binary, profile_env, command_args) = match state.apps.get(&app_name) {
-        Some(app) => {
-            let target = app.target_binary.clone();
-            if target.trim().is_empty() {
-                return Err(CoreError::new(
-                    ErrorCode::InvalidState,
-                    format!("App \"{app_name}\" is missing target_binary"),
-                ));
+    let (profile_override, user_args) = take_profile_flag(std::env::args_os().skip(1).collect())?;
+    let found = state.lookup_app(&app_name);
+    let app = found.map(|(_, app)| app);
+    let config = launch_config(
+        &app_name,
+        app,
+        profile_override.as_deref(),
+        state.default_install_mode,
+    )?;
+    timing.phase("profile");
+    if let Some(app) = app
+        && app.confirm_before_run
+    {
+        let profile = profile_override.as_deref().or_else(|| {
+            app.resolve_active_profile()
+                .filter(|_| !app.disabled)
+                .map(|(name, _)| name.as_str())
+        });
+        confirm_run(
+            &app_name,
+            profile,
+            io::stdin().is_terminal(),
+            env_flag(std::env::var_os(CONFIRM_ENV)),
+            &mut io::stdin().lock(),
+            &mut io::stderr(),
+        )?;
+        // Waiting for the answer is not launcher overhead.
+        timing.skip();
+    }
+
+    let cached = app.and_then(|app| envhub_core::cached_target(app, &config.targets));
+    let resolved = match cached {
+        Some(path) => {
+            debug_log(&format!("using cached target {}", path.display()));
+            path
+        }
+        None => {
+            let path = envhub_core::resolve_target_candidates(&config.targets)?;
+            if let Some((name, app)) = found {
+                refresh_cached_target(name, app, &config.targets, &path);
             }
-            let (env, args) = select_profile_config(app);
-            (target, env, args)
+            path
         }
-        None => (app_name.clone(), HashMap::new(), Vec::new()),
     };
+    timing.phase("target");
+    let inherited = inherited_env(std::env::vars_os().collect(), config.inherit.as_deref());
+    let mut env = merge_env(inherited, profile_env(&config)?);
+    // The approval is for this launch; a shim the target runs asks again.
+    env.shift_remove(std::ffi::OsStr::new(CONFIRM_ENV));
+    prepend_path(&mut env, &config.path_prepend)?;
 
-    let resolved = resolve_target_binary(&target_binary)?;
-    let mut env = merge_env(std::env::vars_os().collect(), &profile_env);
-
-    let mut args: Vec<OsString> = command_args.into_iter().map(OsString::from).collect();
-    args.extend(std::env::args_os().skip(1));
-    if cfg!(windows) {
-        let status = Command::new(&resolved)
-            .args(args)
-            .envs(env.drain())
-            .stdin(std::process::Stdio::inherit())
-            .stdout(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit())
-            .status()
-            .map_err(|err| {
-                CoreError::new(ErrorCode::Io, format!("Failed to launch target: {err}"))
-            })?;
-        let code = status.code().unwrap_or(1) as u8;
-        return Ok(ExitCode::from(code));
+    let mut args: Vec<OsString> = config.command_args.iter().map(OsString::from).collect();
+    args.extend(user_args);
+    timing.phase("env");
+    timing.report(&mut io::stderr());
+    if needs_child_supervision(&config) {
+        return spawn_and_wait(&app_name, &resolved, args, env, &config);
     }
 
     #[cfg(unix)]
     {
         use std::os::unix::process::CommandExt;
-        let err = Command::new(&resolved).args(args).envs(env.drain()).exec();
+        let err = Command::new(&resolved)
+            .args(args)
+            .env_clear()
+            .envs(env)
+            .exec();
         Err(CoreError::new(
-            ErrorCode::Io,
+            ErrorCode::ExecFailed,
             format!("Failed to exec target: {err}"),
         ))
     }
     #[cfg(not(unix))]
     {
         Err(CoreError::new(
-            ErrorCode::Io,
-            "Unsupported platform".to_string(),
+            ErrorCode::Unsupported,
+            "Launching targets is not supported on this platform".to_string(),
+        ))
+    }
+}
+
+/// Runs as the launcher itself rather than as a shim: the subcommands, or a
+/// usage note.
+fn run_as_launcher() -> Result<ExitCode, CoreError> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        match args[1].as_str() {
+            "--version" | "-v" => {
+                println!("envhub-launcher {}", VERSION);
+                return Ok(ExitCode::SUCCESS);
+            }
+            "--help" | "-h" => {
+                print_help();
+                return Ok(ExitCode::SUCCESS);
+            }
+            "path" => {
+                print_paths()?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            "doctor" => return Ok(run_doctor()),
+            "selftest" => return Ok(run_selftest()),
+            "migrate" => {
+                migrate()?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            _ => {}
+        }
+    }
+
+    // Prevent direct execution of envhub-launcher without flags
+    eprintln!("Error: envhub-launcher should not be run directly.");
+    eprintln!("This binary is meant to be symlinked/copied with your app name.");
+    eprintln!();
+    eprintln!("Usage:");
+    eprintln!("  1. Register an app in envhub TUI");
+    eprintln!("  2. Install the shim for that app");
+    eprintln!("  3. Run your app by its alias name (e.g., 'iclaude', 'inode')");
+    eprintln!();
+    eprintln!("For more information, run: envhub-launcher --help");
+    Ok(ExitCode::from(1))
+}
+
+/// Caches where the targets resolved after a miss, so the next launch skips
+/// the scan. Skipped under `ENVHUB_READONLY`; a failed write only costs the
+/// next launch another scan, so it is reported under `ENVHUB_DEBUG` alone.
+fn refresh_cached_target(name: &str, app: &AppConfig, targets: &[String], path: &Path) {
+    if !app.cache_target || envhub_core::is_state_readonly() {
+        return;
+    }
+    match envhub_core::store_resolved_target(name, targets, path) {
+        Ok(true) => debug_log(&format!("cached target {}", path.display())),
+        Ok(false) => {}
+        Err(err) => debug_log(&format!("could not cache target: {}", err.message)),
+    }
+}
+
+/// Set to run apps marked `confirm_before_run` without the prompt, e.g. from
+/// scripts and pipelines where stdin is not a terminal.
+const CONFIRM_ENV: &str = "ENVHUB_CONFIRM";
+
+/// Set to print how long each launch phase took, to find a slow `PATH` scan
+/// or an oversized state file.
+const TIMING_ENV: &str = "ENVHUB_TIMING";
+
+/// Per-phase durations of one launch under [`TIMING_ENV`]. When off, the
+/// clock is never read and nothing is recorded.
+struct Timing {
+    last: Option<Instant>,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timing {
+    fn new(enabled: bool) -> Self {
+        Self {
+            last: enabled.then(Instant::now),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Records the time since the previous phase ended as `name`.
+    fn phase(&mut self, name: &'static str) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            self.phases.push((name, now - last));
+            self.last = Some(now);
+        }
+    }
+
+    /// Leaves the time since the previous phase out of every phase.
+    fn skip(&mut self) {
+        if self.last.is_some() {
+            self.last = Some(Instant::now());
+        }
+    }
+
+    /// One line with each phase and their total, in milliseconds.
+    fn report(&self, out: &mut dyn Write) {
+        if self.last.is_none() {
+            return;
+        }
+        let mut line = String::from("envhub-launcher: timing");
+        for (name, duration) in &self.phases {
+            line.push_str(&format!(" {name}={:.3}ms", duration.as_secs_f64() * 1000.0));
+        }
+        let total: Duration = self.phases.iter().map(|(_, duration)| *duration).sum();
+        line.push_str(&format!(" total={:.3}ms", total.as_secs_f64() * 1000.0));
+        let _ = writeln!(out, "{line}");
+    }
+}
+
+/// Whether a flag variable is on: set, non-empty and not `0`.
+fn env_flag(value: Option<OsString>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Asks on `prompt` whether to go ahead with a `confirm_before_run` launch and
+/// reads the answer from `input`; only `y` or `yes` proceeds. Without a
+/// terminal there is nobody to ask, so rather than hang in a pipeline it
+/// refuses unless `preapproved` via [`CONFIRM_ENV`].
+fn confirm_run(
+    app_name: &str,
+    profile: Option<&str>,
+    interactive: bool,
+    preapproved: bool,
+    input: &mut dyn BufRead,
+    prompt: &mut dyn Write,
+) -> Result<(), CoreError> {
+    if preapproved {
+        return Ok(());
+    }
+    if !interactive {
+        return Err(CoreError::new(
+            ErrorCode::Cancelled,
+            format!(
+                "App \"{app_name}\" asks for confirmation before running, but stdin is not a \
+                 terminal; set {CONFIRM_ENV}=1 to run it anyway"
+            ),
+        ));
+    }
+    let question = match profile {
+        Some(profile) => format!("Run {app_name} with profile {profile}? [y/N] "),
+        None => format!("Run {app_name}? [y/N] "),
+    };
+    let mut answer = String::new();
+    prompt
+        .write_all(question.as_bytes())
+        .and_then(|_| prompt.flush())
+        .and_then(|_| input.read_line(&mut answer))
+        .map_err(|err| {
+            CoreError::new(ErrorCode::Io, format!("Failed to read confirmation: {err}"))
+        })?;
+    if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(CoreError::new(
+            ErrorCode::Cancelled,
+            format!("Not running \"{app_name}\""),
         ))
     }
 }
 
+/// The variables the profile sets: its `env_file` (if any) beneath its inline
+/// `env`, with `{{...}}` templates expanded and then `${KEY}` references
+/// resolved. Global and shared layers slot in ahead of these once they exist.
+fn profile_env(config: &LaunchConfig) -> Result<IndexMap<OsString, OsString>, CoreError> {
+    let file_env = match config.env_file.as_deref() {
+        Some(path) => {
+            let path = PathBuf::from(envhub_core::expand_path(path)?);
+            envhub_core::read_env_file(&path)?.unwrap_or_else(|| {
+                debug_log(&format!("env_file {} not found, skipping", path.display()));
+                IndexMap::new()
+            })
+        }
+        None => IndexMap::new(),
+    };
+    // Inline values override the file's; `${KEY}` references then see both.
+    let mut env = envhub_core::expand_env_templates(&file_env)?;
+    env.extend(envhub_core::expand_env_templates(&config.env)?);
+    let env = envhub_core::resolve_env_references(&env, |name| std::env::var(name).ok())?;
+    Ok(envhub_core::merge_environments(&[&env]))
+}
+
+/// Puts the app's `path_prepend` directories, expanded, in front of `PATH`
+/// as it stands after the profile applied, so a profile's own `PATH` edits
+/// are kept behind them. Duplicate entries are dropped, keeping the first,
+/// so shims launching shims don't grow `PATH` on every hop.
+fn prepend_path(env: &mut IndexMap<OsString, OsString>, dirs: &[String]) -> Result<(), CoreError> {
+    let key = OsString::from("PATH");
+    if dirs.is_empty() && !env.contains_key(&key) {
+        return Ok(());
+    }
+    let mut entries = Vec::new();
+    for dir in dirs {
+        entries.push(PathBuf::from(envhub_core::expand_path(dir)?));
+    }
+    if let Some(existing) = env.get(&key) {
+        entries.extend(std::env::split_paths(existing));
+    }
+    let joined = std::env::join_paths(dedup_paths(entries)).map_err(|err| {
+        CoreError::new(
+            ErrorCode::InvalidState,
+            format!("Invalid path_prepend entry: {err}"),
+        )
+    })?;
+    env.insert(key, joined);
+    Ok(())
+}
+
+/// Removes repeated `PATH` entries, keeping each first occurrence in place.
+/// Windows paths compare case-insensitively.
+fn dedup_paths(entries: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| {
+            let key = if cfg!(windows) {
+                entry.to_string_lossy().to_lowercase()
+            } else {
+                entry.to_string_lossy().to_string()
+            };
+            seen.insert(key)
+        })
+        .collect()
+}
+
+/// Prints launcher diagnostics to stderr when `ENVHUB_DEBUG` is set.
+fn debug_log(message: &str) {
+    if std::env::var_os("ENVHUB_DEBUG").is_some() {
+        eprintln!("envhub-launcher: {message}");
+    }
+}
+
+/// Whether the target must run as a child of the launcher instead of
+/// replacing it via `exec`.
+fn needs_child_supervision(config: &LaunchConfig) -> bool {
+    cfg!(windows) || config.stdout_file.is_some() || config.stderr_file.is_some()
+}
+
+fn spawn_and_wait(
+    app_name: &str,
+    resolved: &Path,
+    args: Vec<OsString>,
+    env: IndexMap<OsString, OsString>,
+    config: &LaunchConfig,
+) -> Result<ExitCode, CoreError> {
+    // Trapped before the spawn, so a signal that arrives while the child
+    // starts is held for it instead of killing the launcher.
+    #[cfg(unix)]
+    let signals = trap_signals()?;
+    let mut child = Command::new(resolved)
+        .args(args)
+        .env_clear()
+        .envs(env)
+        .stdin(Stdio::inherit())
+        .stdout(output_stdio(config.stdout_file.as_deref())?)
+        .stderr(output_stdio(config.stderr_file.as_deref())?)
+        .spawn()
+        .map_err(|err| {
+            CoreError::new(
+                ErrorCode::ExecFailed,
+                format!("Failed to launch target: {err}"),
+            )
+        })?;
+    #[cfg(unix)]
+    let forwarder = forward_signals(app_name, signals, child.id());
+    #[cfg(not(unix))]
+    let _ = app_name;
+    let status = child.wait().map_err(|err| {
+        CoreError::new(ErrorCode::Io, format!("Failed to wait for target: {err}"))
+    })?;
+    #[cfg(unix)]
+    forwarder.close();
+    Ok(ExitCode::from(exit_code(status)))
+}
+
+/// The child's exit code, or 128 + the signal number when a signal ended it,
+/// as a shell reports it.
+fn exit_code(status: std::process::ExitStatus) -> u8 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return (128 + signal) as u8;
+        }
+    }
+    status.code().unwrap_or(1) as u8
+}
+
+/// Keeps the launcher alive while it supervises `child`: SIGTERM and SIGHUP
+/// are passed on to the child, SIGINT and SIGQUIT are left to reach it
+/// through the terminal's process group so it doesn't see them twice. On
+/// SIGHUP the state is re-read and logged; the child's environment can't
+/// change, so edits only apply to the next launch.
+#[cfg(unix)]
+fn forward_signals(
+    app_name: &str,
+    mut signals: signal_hook::iterator::Signals,
+    child: u32,
+) -> signal_hook::iterator::Handle {
+    use signal_hook::consts::{SIGHUP, SIGTERM};
+
+    let handle = signals.handle();
+    let app_name = app_name.to_string();
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGHUP {
+                log_reloaded_profile(&app_name);
+            }
+            if signal == SIGHUP || signal == SIGTERM {
+                // SAFETY: kill has no memory-safety preconditions; the pid is
+                // our own child, which is not reaped until wait() returns.
+                unsafe {
+                    libc::kill(child as libc::pid_t, signal);
+                }
+            }
+        }
+    });
+    handle
+}
+
+#[cfg(unix)]
+fn trap_signals() -> Result<signal_hook::iterator::Signals, CoreError> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
+
+    signal_hook::iterator::Signals::new([SIGHUP, SIGINT, SIGQUIT, SIGTERM]).map_err(|err| {
+        CoreError::new(
+            ErrorCode::Io,
+            format!("Failed to install signal handlers: {err}"),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn log_reloaded_profile(app_name: &str) {
+    match envhub_core::load_state() {
+        Ok(state) => {
+            let profile = reloaded_profile(&state, app_name);
+            debug_log(&format!(
+                "reloaded config: active profile for {app_name} is {profile}; applies to the next launch"
+            ));
+        }
+        Err(err) => debug_log(&format!("failed to reload config: {err}")),
+    }
+}
+
+/// The profile the next launch of `app_name` would pick, found the way a
+/// launch finds it: through aliases and the active/default/first fallback.
+#[cfg(unix)]
+fn reloaded_profile(state: &envhub_core::State, app_name: &str) -> String {
+    state
+        .lookup_app(app_name)
+        .and_then(|(_, app)| app.resolve_active_profile())
+        .map(|(name, _)| name.clone())
+        .unwrap_or_else(|| "(none)".to_string())
+}
+
+/// Opens `path` for appending (creating it and its parent directories), or
+/// inherits the launcher's stream when no file is configured.
+fn output_stdio(path: Option<&str>) -> Result<Stdio, CoreError> {
+    let Some(path) = path else {
+        return Ok(Stdio::inherit());
+    };
+    let path = PathBuf::from(envhub_core::expand_path(path)?);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| {
+            CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to create log directory {}: {err}", parent.display()),
+            )
+        })?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| {
+            CoreError::new(
+                ErrorCode::Io,
+                format!("Failed to open {}: {err}", path.display()),
+            )
+        })?;
+    Ok(Stdio::from(file))
+}
+
+/// Prints where envhub keeps its config and installs shims, one labeled
+/// path per line.
+fn print_paths() -> Result<(), CoreError> {
+    let state = envhub_core::default_state_path()?;
+    let mode = envhub_core::default_install_mode_in(&state).unwrap_or_default();
+    let platform = envhub_core::detect_platform(mode)?;
+    println!("state: {}", state.display());
+    println!("install_dir: {}", platform.install_dir.display());
+    Ok(())
+}
+
+/// Upgrades the state file to the current schema version, keeping a backup.
+fn migrate() -> Result<(), CoreError> {
+    let path = envhub_core::default_state_path()?;
+    let report = envhub_core::migrate_state_file(&path)?;
+    if report.changes.is_empty() {
+        println!(
+            "{} is already at version {}; nothing to migrate",
+            path.display(),
+            report.to_version
+        );
+        return Ok(());
+    }
+    println!(
+        "Migrated {} from version {} to {}",
+        path.display(),
+        report.from_version,
+        report.to_version
+    );
+    for change in &report.changes {
+        println!("  {change}");
+    }
+    if let Some(backup) = &report.backup {
+        println!("Backup: {}", backup.display());
+    }
+    Ok(())
+}
+
+/// Prints `envhub_core::diagnose()` findings, failing when any is an error.
+fn run_doctor() -> ExitCode {
+    let found = envhub_core::diagnose();
+    if found.is_empty() {
+        println!("No problems found");
+    }
+    for diagnostic in &found {
+        println!("{diagnostic}");
+    }
+    if found
+        .iter()
+        .any(|diagnostic| diagnostic.severity == envhub_core::Severity::Error)
+    {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Checks that the launcher itself works, independent of any app: the state
+/// file is found and parses, the launcher knows its own path, and no other
+/// executable on `PATH` goes by the launcher's name. Prints one line per
+/// check and fails when any check does.
+fn run_selftest() -> ExitCode {
+    let mut failed = false;
+    let mut report = |check: &str, result: Result<String, String>| match result {
+        Ok(detail) => println!("ok    {check:<8} {detail}"),
+        Err(detail) => {
+            failed = true;
+            println!("FAIL  {check:<8} {detail}");
+        }
+    };
+
+    match envhub_core::default_state_path() {
+        Ok(path) => {
+            let found = if path.exists() {
+                path.display().to_string()
+            } else {
+                format!("{} (not created yet)", path.display())
+            };
+            report("state", Ok(found));
+            let parsed = envhub_core::load_state_from_path(&path)
+                .map(|state| format!("version {}, {} app(s)", state.version, state.apps.len()));
+            report("parse", parsed.map_err(|err| err.to_string()));
+        }
+        Err(err) => report("state", Err(err.to_string())),
+    }
+
+    match std::env::current_exe() {
+        Ok(self_path) => {
+            report("self", Ok(self_path.display().to_string()));
+            let name = self_path
+                .file_name()
+                .map(|name| name.to_string_lossy().trim_end_matches(".exe").to_string())
+                .unwrap_or_else(envhub_core::launcher_name);
+            let shadowed = match envhub_core::find_executable_in_path(&name, &self_path) {
+                Some(other) => Err(format!(
+                    "\"{name}\" is also {}; one of them hides the other on PATH",
+                    other.display()
+                )),
+                None => Ok(format!("no other \"{name}\" on PATH")),
+            };
+            report("shadow", shadowed);
+        }
+        Err(err) => report(
+            "self",
+            Err(format!("Failed to resolve launcher path: {err}")),
+        ),
+    }
+
+    if failed {
+        println!("selftest failed");
+        ExitCode::from(1)
+    } else {
+        println!("selftest passed");
+        ExitCode::SUCCESS
+    }
+}
+
 fn print_help() {
     println!("envhub-launcher {}", VERSION);
     println!();
@@ -128,6 +660,30 @@ fn print_help() {
     println!("OPTIONS:");
     println!("  -h, --help       Show this help message");
     println!("  -v, --version    Show version information");
+    println!("  path             Print the config file and shim install locations");
+    println!("  doctor           Check the config, shims and targets; exits 1 on errors");
+    println!("  migrate          Upgrade an old config file in place, keeping a backup");
+    println!("  selftest         Check the launcher can read its config and find itself");
+    println!();
+    println!("SHIM OPTIONS:");
+    println!("  --envhub-profile <name>  Use <name> instead of the active profile for this run.");
+    println!("                           Must come first; it is not forwarded to the target.");
+    println!();
+    println!("ENVIRONMENT:");
+    println!("  ENVHUB_STATE     Read this config file instead of the default one");
+    println!("  ENVHUB_READONLY  Set to 1 to refuse any write to the config file");
+    println!("  ENVHUB_DEBUG     Print launcher diagnostics to stderr");
+    println!("  ENVHUB_CONFIRM   Set to 1 to skip the prompt of apps that confirm before running");
+    println!("  ENVHUB_TIMING    Set to 1 to print the time spent in each launch phase to stderr");
+    println!("  ENVHUB_LAUNCHER_NAME");
+    println!("                   Name the launcher is installed under (default envhub-launcher)");
+    println!();
+    println!("EXIT CODES (launcher failures; otherwise the target's own):");
+    println!("  64   Missing argv[0]");
+    println!("  78   Target resolves to the launcher itself");
+    println!("  126  Target could not be executed");
+    println!("  127  Target not found");
+    println!("  1    Any other error");
     println!();
     println!("For more information: https://github.com/sontallive/envhub");
 }
@@ -141,152 +697,169 @@ fn app_name_from_argv0() -> Option<String> {
     Some(name)
 }
 
-fn select_profile_config(app: &AppConfig) -> (HashMap<String, String>, Vec<String>) {
-    if app.profiles.is_empty() {
-        return (HashMap::new(), Vec::new());
-    }
-    let profile = app
-        .active_profile
-        .as_ref()
-        .filter(|name| app.profiles.contains_key(*name))
-        .or_else(|| app.profiles.keys().next());
-    match profile.and_then(|name| app.profiles.get(name)) {
-        Some(profile) => (
-            profile.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
-            profile.command_args.clone(),
-        ),
-        None => (HashMap::new(), Vec::new()),
-    }
+/// What the shim needs to know to launch the target for one invocation.
+struct LaunchConfig {
+    /// Targets to try in order; the first that resolves is launched.
+    targets: Vec<String>,
+    env: IndexMap<String, String>,
+    command_args: Vec<String>,
+    stdout_file: Option<String>,
+    stderr_file: Option<String>,
+    env_file: Option<String>,
+    /// The app's `path_prepend`, unexpanded.
+    path_prepend: Vec<String>,
+    /// Parent variables to keep for an isolated profile; `None` keeps all.
+    inherit: Option<Vec<String>>,
 }
 
-fn merge_env(
-    base: Vec<(OsString, OsString)>,
-    overrides: &HashMap<String, String>,
-) -> HashMap<OsString, OsString> {
-    let mut env: HashMap<OsString, OsString> = base.into_iter().collect();
-    for (key, value) in overrides {
-        env.insert(OsString::from(key), OsString::from(value));
+impl LaunchConfig {
+    fn passthrough(targets: Vec<String>) -> Self {
+        Self {
+            targets,
+            env: IndexMap::new(),
+            command_args: Vec::new(),
+            stdout_file: None,
+            stderr_file: None,
+            env_file: None,
+            path_prepend: Vec::new(),
+            inherit: None,
+        }
     }
-    env
 }
 
-fn resolve_target_binary(target: &str) -> Result<PathBuf, CoreError> {
-    let target_path = Path::new(target);
-    let self_path = std::env::current_exe().map_err(|err| {
-        CoreError::new(
-            ErrorCode::Io,
-            format!("Failed to resolve launcher path: {err}"),
-        )
-    })?;
+/// Flag that picks the profile for a single invocation. It must come first so
+/// the target's own arguments are never mistaken for it.
+const PROFILE_FLAG: &str = "--envhub-profile";
 
-    if target_path.is_absolute() {
-        return ensure_not_self(target_path.to_path_buf(), &self_path);
+/// Strips a leading `--envhub-profile <name>` from the shim's arguments and
+/// returns the requested profile along with the arguments to forward.
+fn take_profile_flag(
+    mut args: Vec<OsString>,
+) -> Result<(Option<String>, Vec<OsString>), CoreError> {
+    if args.first().is_none_or(|arg| arg != PROFILE_FLAG) {
+        return Ok((None, args));
     }
+    let name = args
+        .get(1)
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.trim().is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            CoreError::new(
+                ErrorCode::InvalidState,
+                format!("{PROFILE_FLAG} requires a profile name"),
+            )
+        })?;
+    args.drain(..2);
+    Ok((Some(name), args))
+}
 
-    if target_path.components().count() > 1 {
-        if target_path.exists() {
-            return ensure_not_self(target_path.to_path_buf(), &self_path);
+fn launch_config(
+    app_name: &str,
+    app: Option<&AppConfig>,
+    profile_override: Option<&str>,
+    mode: InstallMode,
+) -> Result<LaunchConfig, CoreError> {
+    let Some(app) = app else {
+        if profile_override.is_some() {
+            return Err(CoreError::new(
+                ErrorCode::AppNotFound,
+                format!("App \"{app_name}\" is not registered, so {PROFILE_FLAG} cannot apply"),
+            ));
         }
-        return Err(CoreError::new(
-            ErrorCode::TargetNotFound,
-            format!("Target \"{target}\" not found"),
-        ));
-    }
-
-    let resolved = find_executable_in_path(target, &self_path).ok_or_else(|| {
-        CoreError::new(
-            ErrorCode::TargetNotFound,
-            format!("Target \"{target}\" not found in PATH"),
-        )
-    })?;
-    Ok(resolved)
-}
-
-fn find_executable_in_path(target: &str, self_path: &Path) -> Option<PathBuf> {
-    let path_var = std::env::var_os("PATH")?;
-    let path_exts = if cfg!(windows) {
-        std::env::var_os("PATHEXT")
-            .map(|exts| {
-                exts.to_string_lossy()
-                    .split(';')
-                    .map(|s| s.to_string())
-                    .collect()
-            })
-            .unwrap_or_else(|| vec![".EXE".to_string()])
+        return Ok(LaunchConfig::passthrough(vec![app_name.to_string()]));
+    };
+    let profile = match profile_override {
+        Some(name) => Some(app.profiles.get_key_value(name).ok_or_else(|| {
+            let available: Vec<&str> = app.profiles.keys().map(String::as_str).collect();
+            CoreError::new(
+                ErrorCode::ProfileNotFound,
+                format!(
+                    "Profile \"{name}\" not found for app \"{app_name}\" (available: {})",
+                    available.join(", ")
+                ),
+            )
+        })?),
+        None => select_profile(app),
+    };
+    // A disabled app keeps its shim but runs the app's own target with the
+    // inherited environment and the user's arguments only.
+    let targets = if app.disabled {
+        app.target_list(mode)
     } else {
-        Vec::new()
+        app.target_list_for(profile.map(|(_, profile)| profile), mode)
     };
-
-    for dir in std::env::split_paths(&path_var) {
-        let candidate = dir.join(target);
-        if cfg!(windows) {
-            if candidate.exists() {
-                if let Ok(path) = ensure_not_self(candidate.clone(), self_path) {
-                    return Some(path);
-                }
-            }
-            for ext in &path_exts {
-                let candidate = dir.join(format!("{target}{ext}"));
-                if candidate.exists() {
-                    if let Ok(path) = ensure_not_self(candidate.clone(), self_path) {
-                        return Some(path);
-                    }
-                }
-            }
-        } else if is_executable(&candidate) {
-            if let Ok(path) = ensure_not_self(candidate.clone(), self_path) {
-                return Some(path);
-            }
-        }
-    }
-    None
-}
-
-fn ensure_not_self(path: PathBuf, self_path: &Path) -> Result<PathBuf, CoreError> {
-    if same_executable(&path, self_path).unwrap_or(false) {
+    if targets.is_empty() {
         return Err(CoreError::new(
-            ErrorCode::TargetNotFound,
-            "Target binary resolves to envhub-launcher".to_string(),
+            ErrorCode::InvalidState,
+            format!("App \"{app_name}\" is missing target_binary"),
         ));
     }
-    Ok(path)
-}
-
-fn same_executable(path: &Path, self_path: &Path) -> Option<bool> {
-    let canonical_candidate = path.canonicalize().ok()?;
-    let canonical_self = self_path.canonicalize().ok()?;
-    if canonical_candidate == canonical_self {
-        return Some(true);
-    }
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::MetadataExt;
-        let candidate_meta = fs_metadata(&canonical_candidate)?;
-        let self_meta = fs_metadata(&canonical_self)?;
-        return Some(candidate_meta.ino() == self_meta.ino());
-    }
-    #[cfg(not(unix))]
-    {
-        Some(false)
+    if app.disabled {
+        return Ok(LaunchConfig::passthrough(targets));
     }
+    let Some((profile_name, profile)) = profile else {
+        return Ok(LaunchConfig {
+            path_prepend: app.path_prepend.clone(),
+            ..LaunchConfig::passthrough(targets)
+        });
+    };
+    Ok(LaunchConfig {
+        targets,
+        env: app.layered_env(profile_name)?,
+        command_args: profile.command_args.clone(),
+        stdout_file: profile.stdout_file.clone(),
+        stderr_file: profile.stderr_file.clone(),
+        env_file: profile.env_file.clone(),
+        path_prepend: app.path_prepend.clone(),
+        inherit: profile.inherited_env(),
+    })
 }
 
-fn fs_metadata(path: &Path) -> Option<std::fs::Metadata> {
-    std::fs::metadata(path).ok()
+fn select_profile(app: &AppConfig) -> Option<(&String, &ProfileConfig)> {
+    app.resolve_active_profile()
 }
 
-#[cfg(unix)]
-fn is_executable(path: &Path) -> bool {
-    use std::os::unix::fs::PermissionsExt;
-    match std::fs::metadata(path) {
-        Ok(meta) => meta.permissions().mode() & 0o111 != 0,
-        Err(_) => false,
-    }
+/// The parent's variables the child starts from: all of them, or for an
+/// isolated profile only those named in `keep` (case-insensitively on
+/// Windows, where variable names are).
+fn inherited_env(
+    parent: Vec<(OsString, OsString)>,
+    keep: Option<&[String]>,
+) -> Vec<(OsString, OsString)> {
+    let Some(keep) = keep else {
+        return parent;
+    };
+    parent
+        .into_iter()
+        .filter(|(key, _)| {
+            let key = key.to_string_lossy();
+            keep.iter().any(|name| {
+                if cfg!(windows) {
+                    name.eq_ignore_ascii_case(&key)
+                } else {
+                    *name == key
+                }
+            })
+        })
+        .collect()
 }
 
-#[cfg(not(unix))]
-fn is_executable(path: &Path) -> bool {
-    path.exists()
+/// Applies the merged profile layers on top of the inherited environment.
+///
+/// Inherited variables keep their order. A name inherited twice (possible via
+/// a raw `execve`) resolves to its first value, the one `getenv` returns, and
+/// the duplicate is dropped.
+fn merge_env(
+    base: Vec<(OsString, OsString)>,
+    overrides: IndexMap<OsString, OsString>,
+) -> IndexMap<OsString, OsString> {
+    let mut env = IndexMap::new();
+    for (key, value) in base {
+        env.entry(key).or_insert(value);
+    }
+    env.extend(overrides);
+    env
 }
 
 #[cfg(test)]
@@ -295,43 +868,440 @@ mod tests {
 
     #[test]
     fn select_profile_env_falls_back_to_first_profile() {
-        let mut app = AppConfig::default();
-        app.target_binary = "tool".to_string();
+        let mut app = AppConfig {
+            target_binary: "tool".to_string(),
+            ..AppConfig::default()
+        };
+        let mut profile = envhub_core::ProfileConfig::default();
+        profile.env.insert("KEY".to_string(), "VALUE".to_string());
+        app.profiles.insert("work".to_string(), profile);
+        let (name, profile) = select_profile(&app).expect("profile");
+        assert_eq!(name, "work");
+        assert_eq!(profile.env.get("KEY").map(String::as_str), Some("VALUE"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reloaded_profile_follows_aliases_and_the_profile_fallback() {
+        let mut app = AppConfig {
+            target_binary: "tool".to_string(),
+            aliases: vec!["tl".to_string()],
+            default_profile: Some("work".to_string()),
+            ..AppConfig::default()
+        };
+        app.profiles
+            .insert("base".to_string(), envhub_core::ProfileConfig::default());
+        app.profiles
+            .insert("work".to_string(), envhub_core::ProfileConfig::default());
+        let mut state = envhub_core::State::default();
+        state.apps.insert("tool".to_string(), app);
+
+        assert_eq!(reloaded_profile(&state, "tl"), "work");
+        assert_eq!(reloaded_profile(&state, "tool"), "work");
+        assert_eq!(reloaded_profile(&state, "other"), "(none)");
+    }
+
+    #[test]
+    fn launch_config_skips_injection_for_disabled_app() {
+        let mut app = AppConfig {
+            target_binary: "tool".to_string(),
+            disabled: true,
+            ..AppConfig::default()
+        };
         let mut profile = envhub_core::ProfileConfig::default();
         profile.env.insert("KEY".to_string(), "VALUE".to_string());
+        profile.command_args.push("--flag".to_string());
         app.profiles.insert("work".to_string(), profile);
-        let (env, _args) = select_profile_config(&app);
-        assert_eq!(env.get("KEY").map(String::as_str), Some("VALUE"));
+
+        let config = launch_config("alias", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(config.targets, vec!["tool".to_string()]);
+        assert!(config.env.is_empty());
+        assert!(config.command_args.is_empty());
+
+        app.disabled = false;
+        let config = launch_config("alias", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(config.env.get("KEY").map(String::as_str), Some("VALUE"));
+        assert_eq!(config.command_args, vec!["--flag".to_string()]);
+    }
+
+    #[test]
+    fn launch_config_layers_included_profiles_beneath_the_profile() {
+        let mut app = AppConfig {
+            target_binary: "tool".to_string(),
+            active_profile: Some("eu".to_string()),
+            ..AppConfig::default()
+        };
+        let mut base = envhub_core::ProfileConfig::default();
+        base.env.insert("REGION".to_string(), "us".to_string());
+        base.env.insert("TOKEN".to_string(), "base".to_string());
+        let mut eu = envhub_core::ProfileConfig {
+            include: vec!["base".to_string()],
+            ..envhub_core::ProfileConfig::default()
+        };
+        eu.env.insert("REGION".to_string(), "eu".to_string());
+        app.profiles.insert("base".to_string(), base);
+        app.profiles.insert("eu".to_string(), eu);
+
+        let config = launch_config("tool", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(config.env.get("REGION").map(String::as_str), Some("eu"));
+        assert_eq!(config.env.get("TOKEN").map(String::as_str), Some("base"));
+
+        app.profiles["base"].include.push("eu".to_string());
+        let err = launch_config("tool", Some(&app), None, InstallMode::User)
+            .err()
+            .expect("include cycle");
+        assert_eq!(err.code, ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn profile_target_takes_precedence_over_app_target() {
+        let mut app = AppConfig {
+            target_binary: "node".to_string(),
+            target_candidates: vec!["nodejs".to_string()],
+            active_profile: Some("v18".to_string()),
+            ..AppConfig::default()
+        };
+        app.profiles.insert(
+            "v18".to_string(),
+            ProfileConfig {
+                target_binary: Some("/opt/node18/bin/node".to_string()),
+                ..ProfileConfig::default()
+            },
+        );
+        app.profiles
+            .insert("plain".to_string(), ProfileConfig::default());
+
+        let config = launch_config("node", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(config.targets, vec!["/opt/node18/bin/node".to_string()]);
+        let config =
+            launch_config("node", Some(&app), Some("plain"), InstallMode::User).expect("config");
+        assert_eq!(
+            config.targets,
+            vec!["node".to_string(), "nodejs".to_string()]
+        );
+
+        app.disabled = true;
+        let config = launch_config("node", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(
+            config.targets,
+            vec!["node".to_string(), "nodejs".to_string()]
+        );
+    }
+
+    #[test]
+    fn launch_config_tries_target_then_candidates() {
+        let app = AppConfig {
+            target_binary: "python3".to_string(),
+            target_candidates: vec!["python".to_string(), " ".to_string()],
+            ..AppConfig::default()
+        };
+        let config = launch_config("py", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(
+            config.targets,
+            vec!["python3".to_string(), "python".to_string()]
+        );
+
+        let app = AppConfig {
+            target_candidates: vec!["python".to_string()],
+            ..AppConfig::default()
+        };
+        let config = launch_config("py", Some(&app), None, InstallMode::User).expect("config");
+        assert_eq!(config.targets, vec!["python".to_string()]);
+    }
+
+    #[test]
+    fn profile_flag_is_stripped_from_forwarded_args() {
+        let args = vec![
+            OsString::from("--envhub-profile"),
+            OsString::from("staging"),
+            OsString::from("chat"),
+            OsString::from("--envhub-profile"),
+        ];
+        let (profile, forwarded) = take_profile_flag(args).expect("flag");
+        assert_eq!(profile.as_deref(), Some("staging"));
+        // Only the leading flag is consumed; later occurrences belong to the target.
+        assert_eq!(
+            forwarded,
+            vec![OsString::from("chat"), OsString::from("--envhub-profile")]
+        );
+
+        let (profile, forwarded) = take_profile_flag(vec![OsString::from("chat")]).expect("flag");
+        assert!(profile.is_none());
+        assert_eq!(forwarded, vec![OsString::from("chat")]);
+
+        let err = take_profile_flag(vec![OsString::from("--envhub-profile")]).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn profile_flag_selects_profile_or_errors() {
+        let mut app = AppConfig {
+            target_binary: "tool".to_string(),
+            active_profile: Some("default".to_string()),
+            ..AppConfig::default()
+        };
+        for name in ["default", "staging"] {
+            let mut profile = envhub_core::ProfileConfig::default();
+            profile.env.insert("STAGE".to_string(), name.to_string());
+            app.profiles.insert(name.to_string(), profile);
+        }
+
+        let config =
+            launch_config("alias", Some(&app), Some("staging"), InstallMode::User).expect("config");
+        assert_eq!(config.env.get("STAGE").map(String::as_str), Some("staging"));
+
+        let err = match launch_config("alias", Some(&app), Some("missing"), InstallMode::User) {
+            Err(err) => err,
+            Ok(_) => panic!("missing profile should fail"),
+        };
+        assert_eq!(err.code, ErrorCode::ProfileNotFound);
+        assert!(err.message.contains("staging"));
+    }
+
+    #[test]
+    fn inline_env_wins_over_env_file() {
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let env_file = dir.path().join("secrets.env");
+        std::fs::write(&env_file, "TOKEN=from-file\nSHARED=from-file\n").expect("env file");
+        let mut config = LaunchConfig::passthrough(vec!["tool".to_string()]);
+        config
+            .env
+            .insert("SHARED".to_string(), "inline".to_string());
+        config.env_file = Some(env_file.to_string_lossy().to_string());
+
+        let env = profile_env(&config).expect("env");
+        assert_eq!(
+            env.get(std::ffi::OsStr::new("TOKEN")),
+            Some(&OsString::from("from-file"))
+        );
+        assert_eq!(
+            env.get(std::ffi::OsStr::new("SHARED")),
+            Some(&OsString::from("inline"))
+        );
+
+        config.env_file = Some(dir.path().join("missing.env").to_string_lossy().to_string());
+        let env = profile_env(&config).expect("missing file is skipped");
+        assert_eq!(env.len(), 1);
+    }
+
+    #[test]
+    fn empty_values_are_injected_not_skipped() {
+        let mut config = LaunchConfig::passthrough(vec!["tool".to_string()]);
+        config.env.insert("EMPTY".to_string(), String::new());
+
+        let env = merge_env(
+            vec![("EMPTY".into(), "inherited".into())],
+            profile_env(&config).expect("env"),
+        );
+        assert_eq!(
+            env.get(std::ffi::OsStr::new("EMPTY")),
+            Some(&OsString::new())
+        );
     }
 
     #[test]
     fn merge_env_overrides_existing_values() {
         let base = vec![(OsString::from("KEY"), OsString::from("OLD"))];
-        let mut overrides = HashMap::new();
-        overrides.insert("KEY".to_string(), "NEW".to_string());
-        let merged = merge_env(base, &overrides);
+        let mut profile = IndexMap::new();
+        profile.insert("KEY".to_string(), "NEW".to_string());
+        let merged = merge_env(base, envhub_core::merge_environments(&[&profile]));
         assert_eq!(
             merged.get(std::ffi::OsStr::new("KEY")),
             Some(&OsString::from("NEW"))
         );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn resolve_target_binary_skips_self() {
-        let self_path = std::env::current_exe().expect("self");
-        let self_dir = self_path.parent().expect("self dir").to_path_buf();
-        let file_name = self_path.file_name().unwrap().to_string_lossy().to_string();
-
-        let original_path = std::env::var_os("PATH");
-        unsafe {
-            std::env::set_var("PATH", &self_dir);
-        }
-        let found = find_executable_in_path(&file_name, &self_path);
-        if let Some(path) = original_path {
-            unsafe {
-                std::env::set_var("PATH", path);
-            }
+    fn path_prepend_goes_before_profile_path_in_order() {
+        let home = PathBuf::from(envhub_core::expand_path("~").expect("home"));
+        let mut env = IndexMap::new();
+        env.insert(
+            OsString::from("PATH"),
+            OsString::from("/profile/bin:/usr/bin"),
+        );
+        let dirs = vec!["~/tools/bin".to_string(), "/opt/sibling/bin".to_string()];
+
+        prepend_path(&mut env, &dirs).expect("prepend");
+        let entries: Vec<PathBuf> = std::env::split_paths(&env[&OsString::from("PATH")]).collect();
+        assert_eq!(
+            entries,
+            [
+                home.join("tools/bin"),
+                PathBuf::from("/opt/sibling/bin"),
+                PathBuf::from("/profile/bin"),
+                PathBuf::from("/usr/bin"),
+            ]
+        );
+
+        let mut empty = IndexMap::new();
+        prepend_path(&mut empty, &["/only".to_string()]).expect("prepend");
+        assert_eq!(empty[&OsString::from("PATH")], OsString::from("/only"));
+
+        // Like target paths, an unset variable is left as written.
+        prepend_path(&mut empty, &["$ENVHUB_SURELY_UNSET_VAR/bin".to_string()]).expect("prepend");
+        assert_eq!(
+            empty[&OsString::from("PATH")],
+            OsString::from("$ENVHUB_SURELY_UNSET_VAR/bin:/only")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn prepend_path_drops_duplicate_entries() {
+        let mut env = IndexMap::new();
+        env.insert(
+            OsString::from("PATH"),
+            OsString::from("/opt/tool/bin:/usr/bin:/opt/tool/bin:/bin:/usr/bin"),
+        );
+        prepend_path(&mut env, &["/usr/bin".to_string(), "/extra".to_string()]).expect("prepend");
+        assert_eq!(
+            env[&OsString::from("PATH")],
+            OsString::from("/usr/bin:/extra:/opt/tool/bin:/bin")
+        );
+
+        let mut chained = IndexMap::new();
+        chained.insert(OsString::from("PATH"), OsString::from("/a:/b:/a:/c:/b"));
+        prepend_path(&mut chained, &[]).expect("dedup");
+        assert_eq!(chained[&OsString::from("PATH")], OsString::from("/a:/b:/c"));
+    }
+
+    #[test]
+    fn isolated_profile_keeps_only_listed_parent_vars() {
+        let parent = vec![
+            (OsString::from("PATH"), OsString::from("/usr/bin")),
+            (OsString::from("SECRET"), OsString::from("leak")),
+            (OsString::from("HOME"), OsString::from("/home/me")),
+        ];
+        assert_eq!(inherited_env(parent.clone(), None), parent);
+
+        let keep = ["PATH".to_string(), "HOME".to_string()];
+        let kept: Vec<OsString> = inherited_env(parent, Some(&keep))
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(kept, [OsString::from("PATH"), OsString::from("HOME")]);
+    }
+
+    #[test]
+    fn merge_env_keeps_order_and_first_duplicate() {
+        let base = vec![
+            (OsString::from("PATH"), OsString::from("/usr/bin")),
+            (OsString::from("DUP"), OsString::from("first")),
+            (OsString::from("HOME"), OsString::from("/home/me")),
+            (OsString::from("DUP"), OsString::from("second")),
+        ];
+        let mut profile = IndexMap::new();
+        profile.insert("HOME".to_string(), "/tmp/home".to_string());
+        profile.insert("EXTRA".to_string(), "1".to_string());
+
+        let merged = merge_env(base, envhub_core::merge_environments(&[&profile]));
+        let pairs: Vec<(&str, &str)> = merged
+            .iter()
+            .map(|(key, value)| (key.to_str().unwrap(), value.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("PATH", "/usr/bin"),
+                ("DUP", "first"),
+                ("HOME", "/tmp/home"),
+                ("EXTRA", "1"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawn_redirects_output_to_profile_files() {
+        let dir = tempfile::TempDir::new().expect("temp dir");
+        let stdout_path = dir.path().join("logs").join("out.log");
+        let stderr_path = dir.path().join("logs").join("err.log");
+        let mut config = LaunchConfig::passthrough(vec!["sh".to_string()]);
+        config.stdout_file = Some(stdout_path.to_string_lossy().to_string());
+        config.stderr_file = Some(stderr_path.to_string_lossy().to_string());
+        assert!(needs_child_supervision(&config));
+
+        let args = vec![
+            OsString::from("-c"),
+            OsString::from("echo out; echo err >&2"),
+        ];
+        for _ in 0..2 {
+            spawn_and_wait(
+                "tool",
+                Path::new("/bin/sh"),
+                args.clone(),
+                IndexMap::new(),
+                &config,
+            )
+            .expect("spawn");
         }
-        assert!(found.is_none());
+
+        let stdout = std::fs::read_to_string(&stdout_path).expect("stdout log");
+        let stderr = std::fs::read_to_string(&stderr_path).expect("stderr log");
+        assert_eq!(stdout, "out\nout\n");
+        assert_eq!(stderr, "err\nerr\n");
+    }
+
+    #[test]
+    fn launcher_failures_map_to_distinct_exit_codes() {
+        let codes = [
+            ErrorCode::MissingArgv0,
+            ErrorCode::SelfReference,
+            ErrorCode::ExecFailed,
+            ErrorCode::TargetNotFound,
+            ErrorCode::Io,
+        ]
+        .map(exit_code_for);
+        assert_eq!(codes, [64, 78, 126, 127, 1]);
+
+        let config = LaunchConfig::passthrough(vec!["missing".to_string()]);
+        let err = spawn_and_wait(
+            "tool",
+            Path::new("/definitely/missing/tool"),
+            Vec::new(),
+            IndexMap::new(),
+            &config,
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::ExecFailed);
+    }
+
+    #[test]
+    fn confirm_run_prompts_on_a_terminal_and_refuses_without_one() {
+        let ask = |profile, interactive, preapproved, answer: &str| {
+            let mut prompt = Vec::new();
+            let result = confirm_run(
+                "deploy",
+                profile,
+                interactive,
+                preapproved,
+                &mut answer.as_bytes(),
+                &mut prompt,
+            );
+            (
+                result.map_err(|err| err.code),
+                String::from_utf8(prompt).unwrap(),
+            )
+        };
+
+        let (result, prompt) = ask(Some("prod"), true, false, "y\n");
+        assert_eq!(result, Ok(()));
+        assert_eq!(prompt, "Run deploy with profile prod? [y/N] ");
+        assert_eq!(ask(None, true, false, " YES \n").0, Ok(()));
+        let (result, prompt) = ask(None, true, false, "n\n");
+        assert_eq!(result, Err(ErrorCode::Cancelled));
+        assert_eq!(prompt, "Run deploy? [y/N] ");
+        assert_eq!(ask(None, true, false, "").0, Err(ErrorCode::Cancelled));
+
+        let (result, prompt) = ask(Some("prod"), false, false, "y\n");
+        assert_eq!(result, Err(ErrorCode::Cancelled));
+        assert!(prompt.is_empty(), "{prompt}");
+        assert_eq!(ask(Some("prod"), false, true, "").0, Ok(()));
+
+        assert!(env_flag(Some("1".into())));
+        assert!(!env_flag(Some("0".into())));
+        assert!(!env_flag(Some("".into())));
+        assert!(!env_flag(None));
     }
 }
@@ -0,0 +1,65 @@
+//! End-to-end check that a shim launched after `set_active_profile` injects
+//! the newly active profile: the launcher re-reads state on every run.
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempfile::TempDir;
+
+/// State file the launcher will read when run with `HOME`/`XDG_CONFIG_HOME`
+/// pointed into `root`.
+fn state_path(root: &Path) -> PathBuf {
+    let config = if cfg!(target_os = "macos") {
+        root.join("home")
+            .join("Library")
+            .join("Application Support")
+    } else {
+        root.join("config")
+    };
+    config.join("envhub").join("config.json")
+}
+
+fn run_shim(root: &Path, shim: &Path) -> String {
+    let output = Command::new(shim)
+        .env("HOME", root.join("home"))
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .output()
+        .expect("run shim");
+    assert!(
+        output.status.success(),
+        "shim failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("utf8 output")
+}
+
+#[test]
+fn shim_uses_profile_activated_after_install() {
+    let root = TempDir::new().expect("temp dir");
+    let path = state_path(root.path());
+
+    envhub_core::register_app_in(&path, "greet", "/bin/sh").expect("register");
+    envhub_core::add_profile_in(&path, "greet", "work").expect("add profile");
+    for profile in ["default", "work"] {
+        envhub_core::set_profile_env_in(&path, "greet", profile, "GREETING", profile)
+            .expect("set env");
+        envhub_core::set_command_args_in(
+            &path,
+            "greet",
+            profile,
+            vec!["-c".to_string(), "printf %s \"$GREETING\"".to_string()],
+        )
+        .expect("set args");
+    }
+
+    let shim = root.path().join("bin").join("greet");
+    std::fs::create_dir_all(shim.parent().unwrap()).expect("bin dir");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_envhub-launcher"), &shim).expect("shim");
+
+    envhub_core::set_active_profile_in(&path, "greet", "default").expect("activate");
+    assert_eq!(run_shim(root.path(), &shim), "default");
+
+    envhub_core::set_active_profile_in(&path, "greet", "work").expect("activate");
+    assert_eq!(run_shim(root.path(), &shim), "work");
+}
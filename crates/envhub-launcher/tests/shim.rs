@@ -0,0 +1,502 @@
+//! Drives the real launcher binary. As a shim, argv[0] picks the app, the
+//! state comes from `ENVHUB_STATE`, and the profile's env reaches the target.
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use tempfile::TempDir;
+
+/// Links `root/bin/<name>` to the launcher binary, the way an installed shim
+/// is.
+fn install_shim(root: &Path, name: &str) -> PathBuf {
+    let shim = root.join("bin").join(name);
+    std::fs::create_dir_all(shim.parent().unwrap()).expect("bin dir");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_envhub-launcher"), &shim).expect("shim");
+    shim
+}
+
+#[test]
+fn shim_injects_profile_env_into_target() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_profile_env_in(&state, "myalias", "default", "ENVHUB_E2E", "injected")
+        .expect("set env");
+    envhub_core::set_profile_env_in(&state, "myalias", "default", "ENVHUB_E2E_EMPTY", "")
+        .expect("set empty env");
+
+    let shim = install_shim(root.path(), "myalias");
+
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .env_remove("ENVHUB_E2E")
+        .env("ENVHUB_E2E_EMPTY", "inherited")
+        .output()
+        .expect("run shim");
+    assert!(
+        output.status.success(),
+        "shim failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    assert!(
+        stdout.lines().any(|line| line == "ENVHUB_E2E=injected"),
+        "variable missing from target env:\n{stdout}"
+    );
+    assert!(
+        stdout.lines().any(|line| line == "ENVHUB_E2E_EMPTY="),
+        "empty variable not injected:\n{stdout}"
+    );
+}
+
+#[test]
+fn timing_is_reported_only_when_enabled() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "timed", "true").expect("register");
+
+    let shim = install_shim(root.path(), "timed");
+
+    let run = |timing: Option<&str>| {
+        let mut command = Command::new(&shim);
+        command.env("ENVHUB_STATE", &state).env("HOME", root.path());
+        match timing {
+            Some(value) => command.env("ENVHUB_TIMING", value),
+            None => command.env_remove("ENVHUB_TIMING"),
+        };
+        let output = command.output().expect("run shim");
+        assert!(output.status.success(), "shim failed");
+        String::from_utf8(output.stderr).expect("utf8 stderr")
+    };
+
+    let stderr = run(Some("1"));
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with("envhub-launcher: timing"))
+        .unwrap_or_else(|| panic!("no timing line:\n{stderr}"));
+    for phase in ["state=", "profile=", "target=", "env=", "total="] {
+        assert!(line.contains(phase), "{phase} missing from {line}");
+    }
+    assert!(!run(None).contains("timing"));
+    assert!(!run(Some("0")).contains("timing"));
+}
+
+#[test]
+fn alias_shim_uses_its_app_config() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_profile_env_in(&state, "myalias", "default", "ENVHUB_E2E", "via-alias")
+        .expect("set env");
+    envhub_core::add_app_alias_in(&state, "myalias", "otheralias").expect("alias");
+
+    let shim = install_shim(root.path(), "otheralias");
+
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .env_remove("ENVHUB_E2E")
+        .output()
+        .expect("run shim");
+    assert!(
+        output.status.success(),
+        "shim failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    assert!(
+        stdout.lines().any(|line| line == "ENVHUB_E2E=via-alias"),
+        "variable missing from target env:\n{stdout}"
+    );
+}
+
+#[test]
+fn isolated_profile_drops_unlisted_parent_vars() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_profile_env_in(&state, "myalias", "default", "ENVHUB_E2E", "injected")
+        .expect("set env");
+    envhub_core::set_profile_isolation_in(&state, "myalias", "default", true, Vec::new())
+        .expect("isolate");
+
+    let shim = install_shim(root.path(), "myalias");
+
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .env("ENVHUB_PARENT_ONLY", "leaked")
+        .output()
+        .expect("run shim");
+    assert!(
+        output.status.success(),
+        "shim failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    let keys: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.split_once('=').map(|(key, _)| key))
+        .collect();
+    assert!(keys.contains(&"ENVHUB_E2E"), "{stdout}");
+    assert!(keys.contains(&"PATH") && keys.contains(&"HOME"), "{stdout}");
+    assert!(!keys.contains(&"ENVHUB_PARENT_ONLY"), "{stdout}");
+    assert!(!keys.contains(&"ENVHUB_STATE"), "{stdout}");
+}
+
+#[test]
+fn self_referencing_target_exits_with_its_own_code() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    let launcher = env!("CARGO_BIN_EXE_envhub-launcher");
+    envhub_core::register_app_in(&state, "myalias", launcher).expect("register");
+
+    let shim = install_shim(root.path(), "myalias");
+
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .output()
+        .expect("run shim");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(78), "{stderr}");
+    assert!(stderr.contains("self_reference"), "{stderr}");
+}
+
+#[test]
+fn supervised_child_killed_by_a_signal_exits_with_128_plus_signal() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "sh").expect("register");
+    envhub_core::set_command_args_in(
+        &state,
+        "myalias",
+        "default",
+        vec!["-c".to_string(), "kill -TERM $$".to_string()],
+    )
+    .expect("args");
+    let mut loaded = envhub_core::load_state_from_path(&state).expect("load");
+    let profile = loaded.apps["myalias"].profiles.get_mut("default").unwrap();
+    profile.stdout_file = Some(root.path().join("out.log").to_string_lossy().to_string());
+    envhub_core::save_state_to_path(&state, &loaded).expect("save");
+
+    let shim = install_shim(root.path(), "myalias");
+
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .output()
+        .expect("run shim");
+    assert_eq!(
+        output.status.code(),
+        Some(128 + 15),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn confirm_before_run_refuses_without_a_terminal_unless_overridden() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_app_confirm_in(&state, "myalias", true).expect("confirm");
+
+    let shim = install_shim(root.path(), "myalias");
+
+    let run = |confirm: Option<&str>| {
+        let mut command = Command::new(&shim);
+        command
+            .env("ENVHUB_STATE", &state)
+            .env("HOME", root.path())
+            .env_remove("ENVHUB_CONFIRM")
+            .stdin(Stdio::null());
+        if let Some(confirm) = confirm {
+            command.env("ENVHUB_CONFIRM", confirm);
+        }
+        command.output().expect("run shim")
+    };
+
+    let output = run(None);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("cancelled"), "{stderr}");
+    assert!(stderr.contains("ENVHUB_CONFIRM=1"), "{stderr}");
+    assert!(output.stdout.is_empty());
+
+    let output = run(Some("1"));
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // The approval covers this launch only, not shims the target runs.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("ENVHUB_CONFIRM"), "{stdout}");
+}
+
+#[test]
+fn migrate_subcommand_upgrades_the_state_and_reports_changes() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    std::fs::write(
+        &state,
+        r#"{"apps": {"tool": {"target_binary": "env", "profiles": {"default": {"OLD": "1"}}}}}"#,
+    )
+    .expect("fixture");
+
+    let migrate = || {
+        Command::new(env!("CARGO_BIN_EXE_envhub-launcher"))
+            .arg("migrate")
+            .env("ENVHUB_STATE", &state)
+            .env("HOME", root.path())
+            .output()
+            .expect("run launcher")
+    };
+    let output = migrate();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    assert!(stdout.contains("from version 0 to 1"), "{stdout}");
+    assert!(
+        stdout.contains("tool/default: moved 1 variable into env"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("fixture.json.pre-v1"), "{stdout}");
+    let migrated = envhub_core::load_state_from_path(&state).expect("load");
+    assert_eq!(migrated.apps["tool"].profiles["default"].env["OLD"], "1");
+
+    let stdout = String::from_utf8(migrate().stdout).expect("utf8 output");
+    assert!(stdout.contains("nothing to migrate"), "{stdout}");
+}
+
+#[test]
+fn selftest_passes_on_a_valid_state_and_fails_on_a_broken_one() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "tool", "true").expect("register");
+
+    let selftest = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_envhub-launcher"))
+            .arg("selftest")
+            .env("ENVHUB_STATE", &state)
+            .env("HOME", root.path())
+            .env("PATH", root.path())
+            .output()
+            .expect("run launcher");
+        let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+        (output.status.code(), stdout)
+    };
+
+    let (code, stdout) = selftest();
+    assert_eq!(code, Some(0), "{stdout}");
+    for check in ["state", "parse", "self", "shadow"] {
+        assert!(
+            stdout
+                .lines()
+                .any(|line| line.starts_with("ok") && line.contains(check)),
+            "{check} missing:\n{stdout}"
+        );
+    }
+    assert!(stdout.contains("1 app(s)"), "{stdout}");
+    assert_eq!(stdout.lines().last(), Some("selftest passed"));
+
+    std::fs::write(&state, "{ not json").expect("corrupt state");
+    let (code, stdout) = selftest();
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.starts_with("FAIL") && line.contains("parse")),
+        "{stdout}"
+    );
+    assert_eq!(stdout.lines().last(), Some("selftest failed"));
+}
+
+#[test]
+fn path_subcommand_prints_state_and_install_dir() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_envhub-launcher"))
+        .arg("path")
+        .env("ENVHUB_STATE", &state)
+        .env("HOME", root.path())
+        .output()
+        .expect("run launcher");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert_eq!(lines[0], format!("state: {}", state.display()));
+    assert!(lines[1].starts_with("install_dir: "), "{stdout}");
+}
+
+#[test]
+fn recorded_launcher_name_is_known_without_the_variable() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    let launcher = root.path().join("bin").join("ehl");
+    let other = root.path().join("other").join("ehl");
+    for copy in [&launcher, &other] {
+        std::fs::create_dir_all(copy.parent().unwrap()).expect("dir");
+        std::fs::copy(env!("CARGO_BIN_EXE_envhub-launcher"), copy).expect("copy launcher");
+    }
+    let shim = root.path().join("bin").join("myalias");
+    let chained = root.path().join("other").join("chained");
+    std::os::unix::fs::symlink(&launcher, &shim).expect("shim");
+    std::os::unix::fs::symlink(&other, &chained).expect("chained shim");
+    envhub_core::register_app_in(&state, "myalias", &chained.to_string_lossy()).expect("register");
+    envhub_core::set_launcher_name_in(&state, "ehl").expect("record name");
+
+    let run = |program: &Path, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .env("ENVHUB_STATE", &state)
+            .env_remove("ENVHUB_LAUNCHER_NAME")
+            .env("HOME", root.path())
+            .output()
+            .expect("run")
+    };
+    let output = run(&launcher, &["--version"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("envhub-launcher "));
+
+    // A shim of another copy of the launcher is refused as a chain.
+    let output = run(&shim, &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("shim chains"), "{stderr}");
+}
+
+#[test]
+fn cache_miss_refreshes_the_cached_target_unless_readonly() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_target_cache_in(&state, "myalias", true).expect("enable cache");
+    let resolved = || {
+        envhub_core::load_state_from_path(&state)
+            .expect("load")
+            .apps["myalias"]
+            .resolved_target
+            .clone()
+            .expect("cached")
+    };
+    let fresh = resolved();
+    let mut stale = envhub_core::load_state_from_path(&state).expect("load");
+    let app = stale.apps.get_mut("myalias").expect("app");
+    app.resolved_target.as_mut().expect("cached").path = "/missing/env".to_string();
+    envhub_core::save_state_to_path(&state, &stale).expect("save");
+
+    let shim = install_shim(root.path(), "myalias");
+    let run = |readonly: &str| {
+        let output = Command::new(&shim)
+            .env("ENVHUB_STATE", &state)
+            .env("ENVHUB_READONLY", readonly)
+            .env("HOME", root.path())
+            .output()
+            .expect("run shim");
+        assert!(
+            output.status.success(),
+            "shim failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+    run("1");
+    assert_eq!(resolved().path, "/missing/env");
+    run("0");
+    assert_eq!(resolved(), fresh);
+}
+
+#[test]
+fn readonly_launch_leaves_state_untouched() {
+    let root = TempDir::new().expect("temp dir");
+    let config_dir = root.path().join("config");
+    let state = config_dir.join("fixture.json");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    let list_files = || {
+        let mut files: Vec<_> = std::fs::read_dir(&config_dir)
+            .expect("config dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        files.sort();
+        files
+    };
+    let files = list_files();
+    let before = std::fs::read(&state).expect("read state");
+    let modified = std::fs::metadata(&state).and_then(|meta| meta.modified());
+
+    let shim = install_shim(root.path(), "myalias");
+    let output = Command::new(&shim)
+        .env("ENVHUB_STATE", &state)
+        .env("ENVHUB_READONLY", "1")
+        .env("HOME", root.path())
+        .output()
+        .expect("run shim");
+    assert!(
+        output.status.success(),
+        "shim failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(std::fs::read(&state).expect("read state"), before);
+    assert_eq!(
+        std::fs::metadata(&state)
+            .and_then(|meta| meta.modified())
+            .ok(),
+        modified.ok()
+    );
+    assert_eq!(list_files(), files);
+}
+
+#[test]
+fn doctor_fails_on_broken_shim() {
+    let root = TempDir::new().expect("temp dir");
+    let state = root.path().join("fixture.json");
+    let bin = root.path().join("bin");
+    std::fs::create_dir_all(&bin).expect("bin dir");
+    envhub_core::register_app_in(&state, "myalias", "env").expect("register");
+    envhub_core::set_app_install_path_in(&state, "myalias", Some(bin.clone())).expect("dir");
+    let doctor = || {
+        Command::new(env!("CARGO_BIN_EXE_envhub-launcher"))
+            .arg("doctor")
+            .env("ENVHUB_STATE", &state)
+            .env("HOME", root.path())
+            .output()
+            .expect("run doctor")
+    };
+
+    let output = doctor();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    std::os::unix::fs::symlink(root.path().join("removed-launcher"), bin.join("myalias"))
+        .expect("broken shim");
+    let output = doctor();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).expect("utf8 output");
+    assert!(
+        stdout
+            .lines()
+            .next()
+            .is_some_and(|line| line.starts_with("[error] myalias: shim ")),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn launcher_binary_carries_build_magic() {
+    let bytes = std::fs::read(env!("CARGO_BIN_EXE_envhub-launcher")).expect("launcher binary");
+    let magic = envhub_core::LAUNCHER_MAGIC;
+    assert!(bytes.windows(magic.len()).any(|window| window == magic));
+}
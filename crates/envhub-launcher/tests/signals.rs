@@ -0,0 +1,109 @@
+//! End-to-end check that a launcher supervising its target (because output
+//! is redirected) passes SIGTERM on to the child instead of orphaning it.
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+/// State file the launcher will read when run with `HOME`/`XDG_CONFIG_HOME`
+/// pointed into `root`.
+fn state_path(root: &Path) -> PathBuf {
+    let config = if cfg!(target_os = "macos") {
+        root.join("home")
+            .join("Library")
+            .join("Application Support")
+    } else {
+        root.join("config")
+    };
+    config.join("envhub").join("config.json")
+}
+
+fn is_alive(pid: &str) -> bool {
+    Command::new("kill")
+        .args(["-0", pid])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn wait_until(timeout: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    done()
+}
+
+#[test]
+fn sigterm_to_supervising_launcher_terminates_child() {
+    let root = TempDir::new().expect("temp dir");
+    let path = state_path(root.path());
+    let pid_file = root.path().join("child.pid");
+
+    envhub_core::register_app_in(&path, "sleeper", "/bin/sh").expect("register");
+    envhub_core::set_command_args_in(
+        &path,
+        "sleeper",
+        "default",
+        vec![
+            "-c".to_string(),
+            format!("echo $$ > '{}'; exec sleep 30", pid_file.display()),
+        ],
+    )
+    .expect("set args");
+    let mut state = envhub_core::load_state_from_path(&path).expect("load");
+    let profile = state
+        .apps
+        .get_mut("sleeper")
+        .and_then(|app| app.profiles.get_mut("default"))
+        .expect("profile");
+    profile.stdout_file = Some(root.path().join("out.log").to_string_lossy().to_string());
+    envhub_core::save_state_to_path(&path, &state).expect("save");
+
+    let shim = root.path().join("bin").join("sleeper");
+    std::fs::create_dir_all(shim.parent().unwrap()).expect("bin dir");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_envhub-launcher"), &shim).expect("shim");
+
+    let mut launcher = Command::new(&shim)
+        .env("HOME", root.path().join("home"))
+        .env("XDG_CONFIG_HOME", root.path().join("config"))
+        .spawn()
+        .expect("spawn shim");
+
+    let mut child_pid = String::new();
+    assert!(
+        wait_until(Duration::from_secs(10), || {
+            child_pid = std::fs::read_to_string(&pid_file)
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            !child_pid.is_empty()
+        }),
+        "child never started"
+    );
+    assert!(is_alive(&child_pid));
+
+    let sent = Command::new("kill")
+        .args(["-TERM", &launcher.id().to_string()])
+        .status()
+        .expect("kill");
+    assert!(sent.success());
+
+    let mut exited = None;
+    assert!(
+        wait_until(Duration::from_secs(10), || {
+            exited = launcher.try_wait().expect("try_wait");
+            exited.is_some()
+        }),
+        "launcher did not exit after SIGTERM"
+    );
+    assert!(!exited.unwrap().success());
+    assert!(!is_alive(&child_pid), "child outlived the launcher");
+}
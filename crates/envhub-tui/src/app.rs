@@ -1,8 +1,14 @@
 use envhub_core::{
-    InstallMode, State, get_launcher_path, install_shim, is_shim_installed, load_state,
-    set_active_profile, set_command_args,
+    ChecksumStatus, CoreError, EnvHistoryEntry, ImportPreview, SortMode, State, default_state_path,
+    ensure_setup, format_command_args, get_launcher_path, import_app_with,
+    install_shim_and_record_returning_state, load_state, load_state_checked, needs_reshim,
+    parse_command_args, preview_import, reshim_all, set_active_profile_all,
+    set_active_profile_returning_state, set_app_disabled, set_app_install_path_returning_state,
+    set_command_args_returning_state, sorted_app_names, summarize_apps, undo_last,
 };
+use std::collections::VecDeque;
 use std::io;
+use std::path::{Path, PathBuf};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
@@ -26,7 +32,12 @@ pub enum InputMode {
     AddApp,
     AddProfile,
     SetEnv,
+    /// A pasted block of `KEY=VALUE` lines for the selected profile.
+    PasteEnv,
     SetCommandArgs,
+    SetInstallPath,
+    ImportApp,
+    ActivateAll,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -43,6 +54,8 @@ pub struct InputState {
     pub first: String,
     pub second: String,
     pub selection_index: usize,
+    /// Switch to the new profile once AddProfile adds it.
+    pub activate_new: bool,
 }
 
 impl InputState {
@@ -54,6 +67,7 @@ impl InputState {
             first: String::new(),
             second: String::new(),
             selection_index: 0,
+            activate_new: false,
         }
     }
 
@@ -64,6 +78,7 @@ impl InputState {
         self.first.clear();
         self.second.clear();
         self.selection_index = 0;
+        self.activate_new = false;
     }
 }
 
@@ -73,6 +88,37 @@ pub struct AppEntry {
     pub active_profile: Option<String>,
     pub profiles: Vec<String>,
     pub is_installed: bool,
+    pub disabled: bool,
+}
+
+/// What an env value being typed becomes at launch, for the input modal.
+#[derive(Debug, Clone)]
+pub struct EnvPreview {
+    /// The value with templates expanded and references resolved, or why it
+    /// can't be (e.g. a reference cycle).
+    pub resolved: Result<String, String>,
+    /// `${NAME}` references that neither the profile nor the environment set.
+    pub unset: Vec<String>,
+    /// Names written as `$NAME`, which the launcher leaves as they are.
+    pub unbraced: Vec<String>,
+}
+
+/// How many status messages the log (`L`) keeps.
+const STATUS_LOG_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// An outcome shown in the status bar and kept in the status log. Prompts
+/// are shown but not logged.
+#[derive(Debug, Clone)]
+pub struct StatusEntry {
+    pub level: StatusLevel,
+    pub message: String,
 }
 
 #[derive(Debug)]
@@ -84,97 +130,196 @@ pub struct App {
     pub page: Page,
     pub focus: Focus,
     pub status: String,
+    /// Recent outcomes, oldest first.
+    pub status_log: VecDeque<StatusEntry>,
+    pub show_status_log: bool,
+    /// Key and its earlier values while the history view is open.
+    pub env_history: Option<(String, Vec<EnvHistoryEntry>)>,
     pub input: InputState,
     pub state: State,
     pub is_launcher_installed: bool,
     pub is_path_configured: bool,
+    /// Show `group/name` profiles under a shared group header.
+    pub group_profiles: bool,
+    pub sort_mode: SortMode,
+    /// Bundle path and preview shown while an import awaits confirmation.
+    pub pending_import: Option<(PathBuf, ImportPreview)>,
+    /// Apps and the profile to activate on them, awaiting confirmation
+    /// because the profile is dangerous in at least one of them.
+    pub pending_activation: Option<(Vec<String>, String)>,
 }
 
 impl App {
     pub fn load() -> io::Result<Self> {
-        let state =
-            load_state().map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
-        Ok(Self::from_state(&state))
+        let (state, checksum) = load_checked()?;
+        let mut app = Self::from_state(&state);
+        if checksum == ChecksumStatus::Mismatch {
+            app.report_warning(EDITED_OUTSIDE_WARNING.to_string());
+        } else if let Ok(stale) = needs_reshim(state.default_install_mode)
+            && !stale.is_empty()
+        {
+            app.report_warning(format!(
+                "{} shim(s) use an older launcher ({}); press R to update",
+                stale.len(),
+                stale.join(", ")
+            ));
+        }
+        Ok(app)
+    }
+
+    pub fn report(&mut self, message: String) {
+        self.push_status(StatusLevel::Info, message);
+    }
+
+    pub fn report_warning(&mut self, message: String) {
+        self.push_status(StatusLevel::Warning, message);
+    }
+
+    pub fn report_error(&mut self, message: String) {
+        self.push_status(StatusLevel::Error, message);
+    }
+
+    fn push_status(&mut self, level: StatusLevel, message: String) {
+        if self.status_log.len() == STATUS_LOG_LIMIT {
+            self.status_log.pop_front();
+        }
+        self.status = message.clone();
+        self.status_log.push_back(StatusEntry { level, message });
+    }
+
+    /// Level of the message currently in the status bar; prompts are `Info`.
+    pub fn status_level(&self) -> StatusLevel {
+        match self.status_log.back() {
+            Some(entry) if entry.message == self.status => entry.level,
+            _ => StatusLevel::Info,
+        }
+    }
+
+    fn apply_import(&mut self) {
+        if let Some((path, preview)) = self.pending_import.take() {
+            match import_app_with(&path, Some(preview.name), true) {
+                Ok(name) => {
+                    self.report(format!("Imported {name}"));
+                    if let Ok(state) = load_state() {
+                        self.update_from_state(state);
+                    }
+                }
+                Err(err) => self.report_error(format!("Import failed: {err}")),
+            }
+        }
+        self.input.reset();
+    }
+
+    pub fn handle_reshim(&mut self) {
+        let mode = self.state.default_install_mode;
+        let launcher_path = match envhub_core::detect_platform(mode) {
+            Ok(platform) => platform
+                .install_dir
+                .join(envhub_core::launcher_file_name_for(
+                    &envhub_core::launcher_name_in(&self.state),
+                )),
+            Err(e) => {
+                self.report_error(format!("Re-shim failed: {}", e));
+                return;
+            }
+        };
+        match reshim_all(mode, &launcher_path) {
+            Ok(updated) if updated.is_empty() => {
+                self.report("All shims are up to date".to_string());
+            }
+            Ok(updated) => {
+                self.report(format!("Updated shims: {}", updated.join(", ")));
+                if let Ok(state) = load_state() {
+                    self.update_from_state(state);
+                }
+            }
+            Err(e) => self.report_error(format!("Re-shim failed: {}", e)),
+        }
     }
 
     pub fn handle_install(&mut self) {
         if let Some(app_name) = self.current_app_name() {
-            if let Some(launcher_path) = get_launcher_path() {
-                match install_shim(&app_name, InstallMode::User, &launcher_path) {
-                    Ok(_) => {
-                        self.status = format!("Installed shim for {}", app_name);
-                        // Update status
-                        if let Ok(state) = load_state() {
-                            self.update_from_state(state);
-                        }
+            if let Some(launcher_path) = locate_launcher() {
+                let mode = self.state.default_install_mode;
+                let setup = match ensure_setup(&launcher_path, mode) {
+                    Ok(setup) => setup,
+                    Err(e) => {
+                        self.report_error(format!("Setup failed: {}", e));
+                        return;
+                    }
+                };
+                self.is_launcher_installed = envhub_core::is_launcher_installed();
+                let installed =
+                    install_shim_and_record_returning_state(&app_name, mode, &setup.launcher_path);
+                match installed {
+                    Ok((_, state)) => {
+                        self.report(
+                            if setup.launcher_installed || setup.path_configured_in.is_some() {
+                                format!("Installed shim for {}. {}", app_name, setup.summary())
+                            } else {
+                                format!("Installed shim for {}", app_name)
+                            },
+                        );
+                        self.update_from_state(state);
                     }
                     Err(e) => {
-                        self.status = format!("Installation failed: {}", e);
+                        self.report_error(format!("Installation failed: {}", e));
                     }
                 }
             } else {
-                self.status = "Launcher not found!".to_string();
+                self.report_error("Launcher not found!".to_string());
             }
         }
     }
-    pub fn from_state(state: &State) -> Self {
-        let mut entries = Vec::new();
-        // Sort keys for consistent order
-        let mut app_names: Vec<_> = state.apps.keys().collect();
-        app_names.sort();
-
-        for name in app_names {
-            if let Some(app) = state.apps.get(name) {
-                let mut profiles: Vec<_> = app.profiles.keys().cloned().collect();
-                profiles.sort();
-
-                entries.push(AppEntry {
-                    name: name.clone(),
-                    active_profile: app.active_profile.clone(),
-                    profiles,
-                    is_installed: is_shim_installed(name, InstallMode::User),
+    pub fn toggle_disabled(&mut self) {
+        let Some(entry) = self.entries.get(self.selected_app) else {
+            return;
+        };
+        let name = entry.name.clone();
+        let disabled = !entry.disabled;
+        match set_app_disabled(&name, disabled) {
+            Ok(()) => {
+                self.report(if disabled {
+                    format!("Disabled {name} (shim passes through)")
+                } else {
+                    format!("Enabled {name}")
                 });
+                if let Ok(state) = load_state() {
+                    self.update_from_state(state);
+                }
             }
+            Err(e) => self.report_error(format!("Failed to toggle {name}: {e}")),
         }
+    }
 
+    pub fn from_state(state: &State) -> Self {
         let mut app = Self {
-            entries,
+            entries: build_entries(state, SortMode::default()),
             selected_app: 0,
             selected_profile: 0,
             selected_env_var: 0,
             page: Page::AppsList,
             focus: Focus::Apps,
             status: "Ready".to_string(),
+            status_log: VecDeque::new(),
+            show_status_log: false,
+            env_history: None,
             input: InputState::new(),
             state: state.clone(),
             is_launcher_installed: envhub_core::is_launcher_installed(),
-            is_path_configured: envhub_core::is_user_path_configured(),
+            is_path_configured: envhub_core::is_install_dir_on_path(state.default_install_mode),
+            group_profiles: true,
+            sort_mode: SortMode::default(),
+            pending_import: None,
+            pending_activation: None,
         };
         app.snap_to_active_profile();
         app
     }
 
     pub fn update_from_state(&mut self, state: State) {
-        let mut entries = Vec::new();
-        let mut app_names: Vec<_> = state.apps.keys().collect();
-        app_names.sort();
-
-        for name in app_names {
-            if let Some(app) = state.apps.get(name) {
-                let mut profiles: Vec<_> = app.profiles.keys().cloned().collect();
-                profiles.sort();
-
-                entries.push(AppEntry {
-                    name: name.clone(),
-                    active_profile: app.active_profile.clone(),
-                    profiles,
-                    is_installed: is_shim_installed(name, InstallMode::User),
-                });
-            }
-        }
-
+        self.entries = build_entries(&state, self.sort_mode);
         self.state = state;
-        self.entries = entries;
         if self.selected_app >= self.entries.len() {
             self.selected_app = self.entries.len().saturating_sub(1);
             self.selected_profile = 0;
@@ -191,6 +336,39 @@ impl App {
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        if self.show_status_log {
+            if matches!(
+                key.code,
+                KeyCode::Esc | KeyCode::Char('L') | KeyCode::Char('q')
+            ) {
+                self.show_status_log = false;
+            }
+            return Ok(false);
+        }
+        if self.env_history.is_some() {
+            if matches!(
+                key.code,
+                KeyCode::Esc | KeyCode::Char('H') | KeyCode::Char('q')
+            ) {
+                self.env_history = None;
+            }
+            return Ok(false);
+        }
+        if let Some((apps, profile)) = self.pending_activation.take() {
+            // Only an explicit `y` switches, so a second Enter can't slip through.
+            match (key.code, apps.as_slice()) {
+                (KeyCode::Char('y'), [app]) => self.apply_activation(app, &profile),
+                (KeyCode::Char('y'), _) => self.apply_activation_all(&profile),
+                (KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q'), [app]) => {
+                    self.status = format!("Kept the active profile of {app}");
+                }
+                (KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q'), _) => {
+                    self.status = "Kept the active profiles".to_string();
+                }
+                _ => self.pending_activation = Some((apps, profile)),
+            }
+            return Ok(false);
+        }
         if self.input.mode != InputMode::Normal {
             return self.handle_input(key);
         }
@@ -198,17 +376,30 @@ impl App {
             KeyCode::Char('q') => return Ok(true),
             KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(true),
             KeyCode::Char('r') => {
-                let state = load_state()
-                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+                let (state, checksum) = load_checked()?;
                 self.update_from_state(state);
-                self.status = "Reloaded".to_string();
+                if checksum == ChecksumStatus::Mismatch {
+                    self.report_warning(EDITED_OUTSIDE_WARNING.to_string());
+                } else {
+                    self.report("Reloaded".to_string());
+                }
             }
-            KeyCode::Esc | KeyCode::Backspace => {
-                if self.page == Page::AppDetail {
-                    self.page = Page::AppsList;
-                    self.focus = Focus::Apps;
-                    self.status = "Apps List".to_string();
+            KeyCode::Char('L') => self.show_status_log = true,
+            // From the list only: undoing a register or import could drop the
+            // app the detail page shows.
+            KeyCode::Char('u') if self.page == Page::AppsList => match undo_last() {
+                Ok(()) => {
+                    if let Ok(state) = load_state() {
+                        self.update_from_state(state);
+                    }
+                    self.report("Undid last change".to_string());
                 }
+                Err(e) => self.report_error(format!("Undo failed: {e}")),
+            },
+            KeyCode::Esc | KeyCode::Backspace if self.page == Page::AppDetail => {
+                self.page = Page::AppsList;
+                self.focus = Focus::Apps;
+                self.status = "Apps List".to_string();
             }
             KeyCode::Char('a') => {
                 if self.page == Page::AppsList {
@@ -224,79 +415,142 @@ impl App {
                     self.status = format!("Add env for {profile}: enter key");
                 }
             }
-            KeyCode::Char('i') => {
-                if self.page == Page::AppsList {
-                    self.handle_install();
+            KeyCode::Char('i') if self.page == Page::AppsList => {
+                self.handle_install();
+            }
+            KeyCode::Char('R') if self.page == Page::AppsList => {
+                self.handle_reshim();
+            }
+            KeyCode::Char('I') if self.page == Page::AppsList => {
+                self.input.mode = InputMode::ImportApp;
+                self.input.step = InputStep::First;
+                self.input.buf.clear();
+                self.status = "Import: enter path to an exported app".to_string();
+            }
+            KeyCode::Char('P') if self.page == Page::AppsList && !self.entries.is_empty() => {
+                self.input.mode = InputMode::SetInstallPath;
+                self.input.step = InputStep::First;
+                self.input.buf = self.current_install_path().unwrap_or_default();
+                self.status = "Set install dir: empty for the default".to_string();
+            }
+            KeyCode::Char('g') if self.page == Page::AppDetail && self.focus == Focus::Profiles => {
+                self.group_profiles = !self.group_profiles;
+                self.status = if self.group_profiles {
+                    "Grouping profiles by prefix".to_string()
+                } else {
+                    "Showing profiles ungrouped".to_string()
+                };
+            }
+            KeyCode::Char('o') if self.page == Page::AppsList => {
+                let selected = self.current_app_name();
+                self.sort_mode = self.sort_mode.next();
+                self.entries = build_entries(&self.state, self.sort_mode);
+                if let Some(name) = selected
+                    && let Some(idx) = self.entries.iter().position(|entry| entry.name == name)
+                {
+                    self.selected_app = idx;
                 }
+                self.status = format!("Sorting apps by {}", self.sort_mode.label());
             }
-            KeyCode::Char('p') => {
-                if self.page == Page::AppDetail {
-                    self.input.mode = InputMode::AddProfile;
-                    self.input.step = InputStep::First;
-                    self.input.buf.clear();
-                    self.status = "Add profile: enter name".to_string();
-                }
-            }
-            KeyCode::Char('d') => {
-                if self.focus == Focus::EnvVars {
-                    // Delete current env var
-                    if let Some((key, _)) = self.current_env_pair() {
-                        if let (Some(app), Some(profile)) =
-                            (self.current_app_name(), self.current_profile_name())
-                        {
-                            match envhub_core::remove_profile_env(&app, &profile, &key) {
-                                Ok(()) => {
-                                    self.status = format!("Removed {key}");
-                                    if let Ok(state) = load_state() {
-                                        self.update_from_state(state);
-                                    }
-                                }
-                                Err(e) => self.status = format!("Failed to remove: {e}"),
+            KeyCode::Char('!') if self.page == Page::AppDetail && self.focus == Focus::Profiles => {
+                if let (Some(app), Some(profile)) =
+                    (self.current_app_name(), self.current_profile_name())
+                {
+                    let dangerous = !self.is_profile_dangerous(&profile);
+                    match envhub_core::set_profile_dangerous(&app, &profile, dangerous) {
+                        Ok(()) => {
+                            self.report(if dangerous {
+                                format!("{app}:{profile} marked dangerous")
+                            } else {
+                                format!("{app}:{profile} no longer marked dangerous")
+                            });
+                            if let Ok(state) = load_state() {
+                                self.update_from_state(state);
                             }
                         }
+                        Err(err) => self.report_error(format!("Failed to mark profile: {err}")),
                     }
                 }
             }
-            KeyCode::Char('e') => {
-                if self.page == Page::AppDetail {
-                    match self.focus {
-                        Focus::EnvVars => {
-                            self.input.mode = InputMode::SetEnv;
-                            self.input.step = InputStep::First;
-                            self.input.buf.clear();
-
-                            // Pre-fill key if editing
-                            if let Some((key, _value)) = self.current_env_pair() {
-                                self.input.first = key.clone();
-                                // SetEnv flow is: Step 1 Enter Key, Step 2 Enter Value.
-                                self.input.buf = key;
-                                self.status = "Edit env: confirm key".to_string();
-                            } else {
-                                let profile = self.current_profile_name().unwrap_or_default();
-                                self.status = format!("Set env for profile {profile}: enter key");
+            KeyCode::Char('V') if self.page == Page::AppDetail => {
+                if let Some(profile) = self.current_profile_name() {
+                    self.input.mode = InputMode::PasteEnv;
+                    self.input.step = InputStep::First;
+                    self.input.buf.clear();
+                    self.status = format!("Paste KEY=VALUE lines into {profile}, Enter to apply");
+                }
+            }
+            KeyCode::Char('S') if self.page == Page::AppsList => {
+                self.input.mode = InputMode::ActivateAll;
+                self.input.step = InputStep::First;
+                self.input.buf.clear();
+                self.status = "Switch all apps: enter profile name".to_string();
+            }
+            KeyCode::Char('x') if self.page == Page::AppsList => {
+                self.toggle_disabled();
+            }
+            KeyCode::Char('p') if self.page == Page::AppDetail => {
+                self.input.mode = InputMode::AddProfile;
+                self.input.step = InputStep::First;
+                self.input.buf.clear();
+                self.status = "Add profile: enter name".to_string();
+            }
+            KeyCode::Char('H') if self.page == Page::AppDetail && self.focus == Focus::EnvVars => {
+                self.show_env_history();
+            }
+            KeyCode::Char('d') if self.focus == Focus::EnvVars => {
+                // Delete current env var
+                if let Some((key, _)) = self.current_env_pair()
+                    && let (Some(app), Some(profile)) =
+                        (self.current_app_name(), self.current_profile_name())
+                {
+                    match envhub_core::remove_profile_env(&app, &profile, &key) {
+                        Ok(()) => {
+                            self.report(format!("Removed {key}"));
+                            if let Ok(state) = load_state() {
+                                self.update_from_state(state);
                             }
                         }
-                        Focus::CommandArgs => {
-                            self.input.mode = InputMode::SetCommandArgs;
-                            self.input.step = InputStep::First;
-                            self.input.buf = self.current_command_args_string();
-                            self.status = "Set command args: space-separated".to_string();
+                        Err(e) => self.report_error(format!("Failed to remove: {e}")),
+                    }
+                }
+            }
+            KeyCode::Char('e') if self.page == Page::AppDetail => {
+                match self.focus {
+                    Focus::EnvVars => {
+                        self.input.mode = InputMode::SetEnv;
+                        self.input.step = InputStep::First;
+                        self.input.buf.clear();
+
+                        // Pre-fill key if editing
+                        if let Some((key, _value)) = self.current_env_pair() {
+                            self.input.first = key.clone();
+                            // SetEnv flow is: Step 1 Enter Key, Step 2 Enter Value.
+                            self.input.buf = key;
+                            self.status = "Edit env: confirm key".to_string();
+                        } else {
+                            let profile = self.current_profile_name().unwrap_or_default();
+                            self.status = format!("Set env for profile {profile}: enter key");
                         }
-                        _ => {}
                     }
+                    Focus::CommandArgs => {
+                        self.input.mode = InputMode::SetCommandArgs;
+                        self.input.step = InputStep::First;
+                        self.input.buf = self.current_command_args_string();
+                        self.status = "Set command args: space-separated".to_string();
+                    }
+                    _ => {}
                 }
             }
             // Tab is less useful now with pages, but maybe switch focus between Profiles and EnvVars later?
             // For now, removing Tab switching or keeping it no-op if on AppsList
-            KeyCode::Tab => {
-                if self.page == Page::AppDetail {
-                    self.focus = match self.focus {
-                        Focus::Profiles => Focus::CommandArgs,
-                        Focus::CommandArgs => Focus::EnvVars,
-                        Focus::EnvVars => Focus::Profiles,
-                        _ => Focus::Profiles,
-                    };
-                }
+            KeyCode::Tab if self.page == Page::AppDetail => {
+                self.focus = match self.focus {
+                    Focus::Profiles => Focus::CommandArgs,
+                    Focus::CommandArgs => Focus::EnvVars,
+                    Focus::EnvVars => Focus::Profiles,
+                    _ => Focus::Profiles,
+                };
             }
             KeyCode::Up => self.move_selection(-1),
             KeyCode::Down => self.move_selection(1),
@@ -334,6 +588,20 @@ impl App {
     }
 
     fn handle_input(&mut self, key: KeyEvent) -> io::Result<bool> {
+        // Import preview: nothing is written until the user confirms.
+        if self.input.mode == InputMode::ImportApp && self.input.step == InputStep::Second {
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('n') => {
+                    self.pending_import = None;
+                    self.input.reset();
+                    self.status = "Import cancelled".to_string();
+                }
+                KeyCode::Enter | KeyCode::Char('y') => self.apply_import(),
+                _ => {}
+            }
+            return Ok(false);
+        }
+
         // Special handling for Clone Profile Selection (Step 2 of AddProfile)
         if self.input.mode == InputMode::AddProfile && self.input.step == InputStep::Second {
             match key.code {
@@ -341,10 +609,8 @@ impl App {
                     self.input.reset();
                     self.status = "Cancelled".to_string();
                 }
-                KeyCode::Up => {
-                    if self.input.selection_index > 0 {
-                        self.input.selection_index -= 1;
-                    }
+                KeyCode::Up if self.input.selection_index > 0 => {
+                    self.input.selection_index -= 1;
                 }
                 KeyCode::Down => {
                     // limit depends on how many profiles + 1 (None)
@@ -357,6 +623,10 @@ impl App {
                 KeyCode::Enter => {
                     self.commit_input()?;
                 }
+                KeyCode::Char('s') => {
+                    self.input.activate_new = true;
+                    self.commit_input()?;
+                }
                 _ => {}
             }
             return Ok(false);
@@ -384,15 +654,40 @@ impl App {
         Ok(false)
     }
 
+    /// Bracketed paste: the whole block arrives at once, so a multi-line
+    /// paste into `PasteEnv` keeps its line breaks instead of committing at
+    /// the first one. Other prompts take a single line.
+    pub fn handle_paste(&mut self, text: &str) {
+        match self.input.mode {
+            InputMode::Normal => {}
+            InputMode::PasteEnv => {
+                let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                self.input.buf.push_str(&text);
+            }
+            _ => {
+                let line = text.lines().find(|line| !line.trim().is_empty());
+                self.input.buf.push_str(line.unwrap_or_default());
+            }
+        }
+    }
+
     fn commit_input(&mut self) -> io::Result<()> {
         let value = self.input.buf.trim().to_string();
 
-        // Skip empty check for AddProfile Step 2 (selection-based) and CommandArgs (allows clear)
+        // Skip empty check for AddProfile Step 2 (selection-based), CommandArgs (allows clear)
+        // and env values (an empty value is injected as KEY=; only the key must be set)
         if value.is_empty()
             && !(self.input.mode == InputMode::AddProfile && self.input.step == InputStep::Second)
+            && !(self.input.mode == InputMode::SetEnv && self.input.step == InputStep::Second)
             && self.input.mode != InputMode::SetCommandArgs
+            && self.input.mode != InputMode::SetInstallPath
         {
-            self.status = "Input cannot be empty".to_string();
+            let message = if self.input.mode == InputMode::SetEnv {
+                "Env key cannot be empty"
+            } else {
+                "Input cannot be empty"
+            };
+            self.report_error(message.to_string());
             return Ok(());
         }
 
@@ -407,14 +702,17 @@ impl App {
                 self.input.second = value;
                 let name = self.input.first.clone();
                 let target = self.input.second.clone();
-                match envhub_core::register_app(&name, &target) {
-                    Ok(()) => {
-                        self.status = format!("registered {name} -> {target}");
-                        if let Ok(state) = load_state() {
-                            self.update_from_state(state);
+                match envhub_core::register_app_returning_state(&name, &target) {
+                    Ok((outcome, state)) => {
+                        match outcome.target_warning {
+                            Some(warning) => self.report_warning(format!(
+                                "registered {name} -> {target} (warning: {warning})"
+                            )),
+                            None => self.report(format!("registered {name} -> {target}")),
                         }
+                        self.update_from_state(state);
                     }
-                    Err(err) => self.status = format!("Failed to register: {err}"),
+                    Err(err) => self.report_error(format!("Failed to register: {err}")),
                 }
                 self.input.reset();
             }
@@ -424,7 +722,7 @@ impl App {
                 let new_profile = value.clone();
                 let profiles = self.current_profiles();
                 if profiles.contains(&new_profile) {
-                    self.status = format!("Profile '{}' already exists", new_profile);
+                    self.report_error(format!("Profile '{}' already exists", new_profile));
                     return Ok(());
                 }
 
@@ -448,28 +746,66 @@ impl App {
                 };
 
                 if let Some(app) = app {
+                    // A copy of a dangerous profile is dangerous too, so it
+                    // still goes through the activation confirmation.
+                    let confirm = self.input.activate_new
+                        && source_profile
+                            .as_deref()
+                            .is_some_and(|src| self.is_profile_dangerous(src));
+                    let activate = self.input.activate_new && !confirm;
                     let res = match source_profile {
-                        Some(src) => envhub_core::clone_profile(&app, &src, &new_profile),
-                        None => envhub_core::add_profile(&app, &new_profile),
+                        Some(src) if activate => {
+                            envhub_core::clone_profile_and_activate_returning_state(
+                                &app,
+                                &src,
+                                &new_profile,
+                                Default::default(),
+                            )
+                        }
+                        Some(src) => envhub_core::clone_profile_returning_state(
+                            &app,
+                            &src,
+                            &new_profile,
+                            Default::default(),
+                        ),
+                        None if activate => envhub_core::add_profile_and_activate_returning_state(
+                            &app,
+                            &new_profile,
+                        ),
+                        None => envhub_core::add_profile_returning_state(&app, &new_profile),
                     };
 
                     match res {
-                        Ok(()) => {
-                            self.status = format!("profile {new_profile} added to {app}");
-                            if let Ok(state) = load_state() {
-                                self.update_from_state(state);
+                        Ok(state) => {
+                            if activate {
+                                self.report(format!(
+                                    "profile {new_profile} added to {app} and activated"
+                                ));
+                            } else {
+                                self.report(format!("profile {new_profile} added to {app}"));
+                            }
+                            self.update_from_state(state);
+                            if confirm {
+                                self.status = format!(
+                                    "{app}:{new_profile} is marked dangerous: y to activate, Esc to keep"
+                                );
+                                self.pending_activation = Some((vec![app], new_profile));
                             }
                         }
-                        Err(err) => self.status = format!("Failed: {err}"),
+                        Err(err) => self.report_error(format!("Failed: {err}")),
                     }
                 }
                 self.input.reset();
             }
             (InputMode::SetEnv, InputStep::First) => {
+                if let Err(err) = envhub_core::validate_env_pair(&value, "") {
+                    self.report_error(err.message);
+                    return Ok(());
+                }
                 self.input.first = value;
                 self.input.buf.clear();
                 self.input.step = InputStep::Second;
-                self.status = "Set env: enter value".to_string();
+                self.status = "Set env: enter value (empty sets KEY=)".to_string();
             }
             (InputMode::SetEnv, InputStep::Second) => {
                 let app = self.current_app_name();
@@ -477,14 +813,59 @@ impl App {
                 let key = self.input.first.clone();
                 let env_value = value;
                 if let (Some(app), Some(profile_name)) = (app, profile_name) {
-                    match envhub_core::set_profile_env(&app, &profile_name, &key, &env_value) {
-                        Ok(()) => {
-                            self.status = format!("env {key} set for {app}:{profile_name}");
-                            if let Ok(state) = load_state() {
-                                self.update_from_state(state);
+                    match envhub_core::set_profile_env_returning_state(
+                        &app,
+                        &profile_name,
+                        &key,
+                        &env_value,
+                    ) {
+                        Ok((previous, state)) => {
+                            // Values may be secrets and the status log keeps
+                            // every message, so only the key is named.
+                            self.report(match previous {
+                                Some(old) if old != env_value => {
+                                    format!("env {key} changed for {app}:{profile_name}")
+                                }
+                                _ if env_value.is_empty() => {
+                                    format!("env {key} set to empty for {app}:{profile_name}")
+                                }
+                                _ => format!("env {key} set for {app}:{profile_name}"),
+                            });
+                            self.update_from_state(state);
+                        }
+                        Err(err) => self.report_error(format!("Failed to set env: {err}")),
+                    }
+                }
+                self.input.reset();
+            }
+            (InputMode::PasteEnv, _) => {
+                let (env, skipped) = envhub_core::parse_env_lines(&self.input.buf);
+                let app = self.current_app_name();
+                let profile_name = self.current_profile_name();
+                if env.is_empty() {
+                    self.report_error("No KEY=VALUE lines found".to_string());
+                } else if let (Some(app), Some(profile_name)) = (app, profile_name) {
+                    match envhub_core::set_profile_env_bulk_returning_state(
+                        &app,
+                        &profile_name,
+                        &env,
+                    ) {
+                        Ok(state) => {
+                            let mut message =
+                                format!("Set {} vars for {app}:{profile_name}", env.len());
+                            if !skipped.is_empty() {
+                                let lines: Vec<String> =
+                                    skipped.iter().map(usize::to_string).collect();
+                                message.push_str(&format!(
+                                    " (skipped {} lines: {})",
+                                    skipped.len(),
+                                    lines.join(", ")
+                                ));
                             }
+                            self.report(message);
+                            self.update_from_state(state);
                         }
-                        Err(err) => self.status = format!("Failed to set env: {err}"),
+                        Err(err) => self.report_error(format!("Failed to set env: {err}")),
                     }
                 }
                 self.input.reset();
@@ -492,20 +873,77 @@ impl App {
             (InputMode::SetCommandArgs, InputStep::First) => {
                 let app = self.current_app_name();
                 let profile = self.current_profile_name();
-                let args = if value.is_empty() {
-                    Vec::new()
-                } else {
-                    value.split_whitespace().map(|s| s.to_string()).collect()
-                };
+                let args = parse_command_args(&value);
                 if let (Some(app), Some(profile)) = (app, profile) {
-                    match set_command_args(&app, &profile, args) {
-                        Ok(()) => {
-                            self.status = format!("Command args updated for {app}:{profile}");
-                            if let Ok(state) = load_state() {
-                                self.update_from_state(state);
-                            }
+                    match set_command_args_returning_state(&app, &profile, args) {
+                        Ok(state) => {
+                            self.report(format!("Command args updated for {app}:{profile}"));
+                            self.update_from_state(state);
+                        }
+                        Err(err) => self.report_error(format!("Failed to set args: {err}")),
+                    }
+                }
+                self.input.reset();
+            }
+            (InputMode::ImportApp, InputStep::First) => {
+                let preview = envhub_core::expand_path(&value).and_then(|path| {
+                    let path = PathBuf::from(path);
+                    preview_import(&path, None, true).map(|preview| (path, preview))
+                });
+                match preview {
+                    Ok((path, preview)) => {
+                        self.status = format!(
+                            "Review import of {}: Enter to apply, Esc to cancel",
+                            preview.name
+                        );
+                        self.pending_import = Some((path, preview));
+                        self.input.step = InputStep::Second;
+                    }
+                    Err(err) => {
+                        self.report_error(format!("Import failed: {err}"));
+                        self.input.reset();
+                    }
+                }
+            }
+            (InputMode::ActivateAll, InputStep::First) => {
+                let having: Vec<String> = self
+                    .state
+                    .apps
+                    .iter()
+                    .filter(|(_, app)| app.profiles.contains_key(&value))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                let dangerous = self.apps_where_dangerous(&having, &value);
+                if dangerous.is_empty() {
+                    self.apply_activation_all(&value);
+                } else {
+                    self.status = format!(
+                        "{value} is marked dangerous in {}: y to switch all, Esc to keep",
+                        dangerous.join(", ")
+                    );
+                    self.pending_activation = Some((having, value));
+                }
+                self.input.reset();
+            }
+            (InputMode::SetInstallPath, InputStep::First) => {
+                if let Some(app) = self.current_app_name() {
+                    let install_path = if value.is_empty() {
+                        Ok(None)
+                    } else {
+                        envhub_core::expand_path(&value).map(|dir| Some(PathBuf::from(dir)))
+                    };
+                    match install_path
+                        .and_then(|dir| set_app_install_path_returning_state(&app, dir))
+                    {
+                        Ok(state) => {
+                            self.report(if value.is_empty() {
+                                format!("{app} installs to the default dir")
+                            } else {
+                                format!("{app} installs to {value}")
+                            });
+                            self.update_from_state(state);
                         }
-                        Err(err) => self.status = format!("Failed to set args: {err}"),
+                        Err(err) => self.report_error(format!("Failed to set install dir: {err}")),
                     }
                 }
                 self.input.reset();
@@ -565,6 +1003,82 @@ impl App {
             .unwrap_or_default()
     }
 
+    /// Opens the history view for the selected env var, from the loaded state.
+    fn show_env_history(&mut self) {
+        let (Some((key, _)), Some(app), Some(profile)) = (
+            self.current_env_pair(),
+            self.current_app_name(),
+            self.current_profile_name(),
+        ) else {
+            return;
+        };
+        let history = self
+            .state
+            .apps
+            .get(&app)
+            .and_then(|app| app.profiles.get(&profile))
+            .and_then(|profile| profile.env_history.get(&key))
+            .cloned()
+            .unwrap_or_default();
+        if history.is_empty() && self.state.env_history_limit == 0 {
+            self.report_warning(
+                "Env history is off; set env_history_limit to keep previous values".to_string(),
+            );
+            return;
+        }
+        self.env_history = Some((key, history));
+    }
+
+    /// The launch-time value of the env value being typed, resolved the way
+    /// the launcher does against the profile's other variables (with its
+    /// includes) and this process's environment. `None` unless the value
+    /// contains a `$` reference.
+    pub fn env_value_preview(&self) -> Option<EnvPreview> {
+        if self.input.mode != InputMode::SetEnv || self.input.step != InputStep::Second {
+            return None;
+        }
+        let value = self.input.buf.trim();
+        let unbraced = unbraced_references(value);
+        if !value.contains("${") && unbraced.is_empty() {
+            return None;
+        }
+        let selected = self
+            .current_app_name()
+            .zip(self.current_profile_name())
+            .and_then(|(app, profile)| Some((self.state.apps.get(&app)?, profile)));
+        // The profile's `env_file` lies beneath its inline env, as in the launcher.
+        let mut env = selected
+            .as_ref()
+            .and_then(|(app, profile)| app.profiles.get(profile)?.env_file.as_deref())
+            .and_then(|path| envhub_core::expand_path(path).ok())
+            .and_then(|path| envhub_core::read_env_file(Path::new(&path)).ok().flatten())
+            .unwrap_or_default();
+        if let Some((app, profile)) = &selected {
+            env.extend(app.layered_env(profile).unwrap_or_default());
+        }
+        env.insert(self.input.first.clone(), value.to_string());
+        let resolved = envhub_core::expand_env_templates(&env)
+            .and_then(|env| {
+                envhub_core::resolve_env_references(&env, |name| std::env::var(name).ok())
+            })
+            .map(|env| env[self.input.first.as_str()].clone())
+            .map_err(|err| err.message);
+        // Unresolvable references are left as written.
+        let unset = match &resolved {
+            Ok(resolved) => resolved
+                .split("${")
+                .skip(1)
+                .filter_map(|rest| rest.split_once('}').map(|(name, _)| name.to_string()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        Some(EnvPreview {
+            resolved,
+            unset,
+            unbraced,
+        })
+    }
+
     pub fn current_env_pair(&self) -> Option<(String, String)> {
         self.current_env_list().get(self.selected_env_var).cloned()
     }
@@ -589,6 +1103,23 @@ impl App {
             .cloned()
     }
 
+    pub fn current_profile_notes(&self) -> Option<String> {
+        let app_name = self.current_app_name()?;
+        let profile = self.current_profile_name()?;
+        self.state
+            .apps
+            .get(&app_name)?
+            .profiles
+            .get(&profile)?
+            .notes
+            .clone()
+    }
+
+    pub fn current_install_path(&self) -> Option<String> {
+        let app_name = self.current_app_name()?;
+        self.state.apps.get(&app_name)?.install_path.clone()
+    }
+
     pub fn current_command_args_string(&self) -> String {
         let Some(app_name) = self.current_app_name() else {
             return String::new();
@@ -600,7 +1131,7 @@ impl App {
             .apps
             .get(&app_name)
             .and_then(|app| app.profiles.get(&profile))
-            .map(|profile| profile.command_args.join(" "))
+            .map(|profile| format_command_args(&profile.command_args))
             .unwrap_or_default()
     }
 
@@ -614,32 +1145,148 @@ impl App {
         let Some(profile) = entry.profiles.get(self.selected_profile) else {
             return Ok(());
         };
-        let result = set_active_profile(&entry.name, profile);
-        match result {
-            Ok(()) => {
-                self.status = format!("Active profile for {} -> {}", entry.name, profile);
+        let (app, profile) = (entry.name.clone(), profile.clone());
+        if self.is_profile_dangerous(&profile) && entry.active_profile.as_ref() != Some(&profile) {
+            self.status =
+                format!("{app}:{profile} is marked dangerous: y to activate, Esc to keep");
+            self.pending_activation = Some((vec![app], profile));
+            return Ok(());
+        }
+        self.apply_activation(&app, &profile);
+        Ok(())
+    }
+
+    fn apply_activation(&mut self, app: &str, profile: &str) {
+        match set_active_profile_returning_state(app, profile) {
+            Ok(state) => {
+                self.report(format!("Active profile for {} -> {}", app, profile));
+                self.update_from_state(state);
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to set profile: {}", err));
+            }
+        }
+    }
+
+    /// Activates `profile` on every app that has it.
+    fn apply_activation_all(&mut self, profile: &str) {
+        match set_active_profile_all(profile) {
+            Ok(results) => {
+                let switched: Vec<&str> = results
+                    .iter()
+                    .filter(|(_, result)| result.is_ok())
+                    .map(|(name, _)| name.as_str())
+                    .collect();
+                // Apps without the profile are left out of the results.
+                let skipped = self
+                    .state
+                    .apps
+                    .values()
+                    .filter(|app| !app.profiles.contains_key(profile))
+                    .count();
+                if switched.is_empty() {
+                    self.report_warning(format!("No app has a profile named {profile}"));
+                } else {
+                    self.report(format!(
+                        "Switched {} to {profile} (skipped {skipped} without it)",
+                        switched.join(", ")
+                    ));
+                }
                 if let Ok(state) = load_state() {
                     self.update_from_state(state);
                 }
             }
-            Err(err) => {
-                self.status = format!("Failed to set profile: {}", err);
-            }
+            Err(err) => self.report_error(format!("Failed to switch profiles: {err}")),
         }
-        Ok(())
+    }
+
+    /// Those of `apps` where `profile` is marked dangerous.
+    pub fn apps_where_dangerous(&self, apps: &[String], profile: &str) -> Vec<String> {
+        apps.iter()
+            .filter(|app| {
+                self.state
+                    .apps
+                    .get(*app)
+                    .and_then(|app| app.profiles.get(profile))
+                    .is_some_and(|profile| profile.dangerous)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `profile` of the selected app asks for confirmation first.
+    pub fn is_profile_dangerous(&self, profile: &str) -> bool {
+        self.current_app_name()
+            .and_then(|app| {
+                self.state
+                    .apps
+                    .get(&app)?
+                    .profiles
+                    .get(profile)
+                    .map(|p| p.dangerous)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Notes of `profile` in `app`, for the activation confirmation.
+    pub fn profile_notes(&self, app: &str, profile: &str) -> Option<String> {
+        self.state
+            .apps
+            .get(app)?
+            .profiles
+            .get(profile)?
+            .notes
+            .clone()
     }
     fn snap_to_active_profile(&mut self) {
         if let Some(entry) = self.entries.get(self.selected_app) {
             self.selected_profile = 0; // Default
-            if let Some(active) = &entry.active_profile {
-                if let Some(idx) = entry.profiles.iter().position(|p| p == active) {
-                    self.selected_profile = idx;
-                }
+            if let Some(active) = &entry.active_profile
+                && let Some(idx) = entry.profiles.iter().position(|p| p == active)
+            {
+                self.selected_profile = idx;
             }
         }
     }
 }
 
+const EDITED_OUTSIDE_WARNING: &str = "Warning: config.json was edited outside envhub";
+
+fn load_checked() -> Result<(State, ChecksumStatus), CoreError> {
+    load_state_checked(&default_state_path()?)
+}
+
+fn build_entries(state: &State, sort_mode: SortMode) -> Vec<AppEntry> {
+    let mut entries: Vec<AppEntry> = summarize_apps(state, state.default_install_mode)
+        .into_iter()
+        .filter_map(|summary| {
+            let app = state.apps.get(&summary.name)?;
+            let mut profiles: Vec<_> = app.profiles.keys().cloned().collect();
+            profiles.sort();
+            Some(AppEntry {
+                name: summary.name,
+                active_profile: summary.active_profile,
+                profiles,
+                is_installed: summary.installed,
+                disabled: app.disabled,
+            })
+        })
+        .collect();
+    let order = sorted_app_names(state, sort_mode);
+    entries.sort_by_key(|entry| order.iter().position(|name| *name == entry.name));
+    entries
+}
+
+/// Finds a launcher to install from: the one on PATH, or the copy shipped next
+/// to this binary.
+fn locate_launcher() -> Option<PathBuf> {
+    get_launcher_path().or_else(|| {
+        let exe = std::env::current_exe().ok()?;
+        let sibling = exe.parent()?.join(envhub_core::launcher_file_name());
+        sibling.exists().then_some(sibling)
+    })
+}
+
 fn next_index(current: usize, len: usize, delta: isize) -> usize {
     if len == 0 {
         return 0;
@@ -653,3 +1300,17 @@ fn next_index(current: usize, len: usize, delta: isize) -> usize {
     }
     next as usize
 }
+
+/// Names referenced as `$NAME` rather than `${NAME}` in `value`.
+fn unbraced_references(value: &str) -> Vec<String> {
+    value
+        .split('$')
+        .skip(1)
+        .map(|rest| {
+            rest.chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect::<String>()
+        })
+        .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+        .collect()
+}
@@ -16,10 +16,11 @@ use app::App;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() -> Result<(), CoreError> {
-    // Check for version/help flags
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        match args[1].as_str() {
+    let mut export = false;
+    let mut include_secrets = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
             "--version" | "-v" => {
                 println!("envhub {}", VERSION);
                 return Ok(());
@@ -28,20 +29,62 @@ fn main() -> Result<(), CoreError> {
                 print_help();
                 return Ok(());
             }
-            _ => {
-                eprintln!("Unknown option: {}", args[1]);
-                eprintln!("Run 'envhub --help' for usage information.");
-                return Err(CoreError::new(
-                    envhub_core::ErrorCode::InvalidState,
-                    "Invalid argument".to_string(),
-                ));
-            }
+            "--export-json" | "--json" => export = true,
+            "--include-secrets" => include_secrets = true,
+            "--state" => match args.next() {
+                Some(path) => use_state_file(&path),
+                None => return Err(usage_error("--state needs a path")),
+            },
+            _ => match arg.strip_prefix("--state=") {
+                Some(path) => use_state_file(path),
+                None => return Err(usage_error(&format!("Unknown option: {arg}"))),
+            },
         }
     }
+    if include_secrets && !export {
+        return Err(usage_error("--include-secrets only applies to --export-json"));
+    }
+    if export {
+        return export_json(include_secrets);
+    }
 
     run_tui().map_err(|err| CoreError::new(envhub_core::ErrorCode::Io, err.to_string()))
 }
 
+/// Points this session at `path` by way of `ENVHUB_STATE`, which every core
+/// call without an explicit path already honours.
+fn use_state_file(path: &str) {
+    // SAFETY: called while parsing arguments, before any other thread exists.
+    unsafe { std::env::set_var("ENVHUB_STATE", path) };
+}
+
+fn usage_error(message: &str) -> CoreError {
+    eprintln!("{message}");
+    eprintln!("Run 'envhub --help' for usage information.");
+    CoreError::new(
+        envhub_core::ErrorCode::InvalidState,
+        "Invalid argument".to_string(),
+    )
+}
+
+/// Prints the state as JSON and exits, without touching the terminal, so the
+/// same binary works on headless machines and in pipes. Secrets are stripped
+/// as for a share (`envhub_core::strip_secrets`) unless `include_secrets`.
+fn export_json(include_secrets: bool) -> Result<(), CoreError> {
+    let mut state = envhub_core::load_state()?;
+    if !include_secrets {
+        envhub_core::strip_secrets(&mut state);
+    }
+    let json = serde_json::to_string_pretty(&state).map_err(|err| {
+        CoreError::new(
+            envhub_core::ErrorCode::Json,
+            format!("Failed to serialize state: {err}"),
+        )
+    })?;
+    println!("{json}");
+    Ok(())
+}
+
 fn print_help() {
     println!("envhub {}", VERSION);
     println!();
@@ -57,15 +100,31 @@ fn print_help() {
     println!("OPTIONS:");
     println!("  -h, --help       Show this help message");
     println!("  -v, --version    Show version information");
+    println!("  --export-json    Print the configuration as JSON, without secret values,");
+    println!("                   and exit (alias: --json)");
+    println!("  --include-secrets");
+    println!("                   Keep secret values in the --export-json output");
+    println!("  --state <PATH>   Use PATH as the config file for this session");
+    println!("                   (same as setting ENVHUB_STATE; shims keep their own)");
     println!();
     println!("KEYBOARD SHORTCUTS (in TUI):");
     println!("  q                Quit");
     println!("  a                Add app (on Apps List) / Add env var (on Env Vars)");
     println!("  p                Add profile (on App Detail)");
+    println!("  g                Toggle grouping of 'group/name' profiles");
     println!("  i                Install shim for selected app");
+    println!("  P                Set a custom install dir for selected app");
+    println!("  I                Import an exported app (previews changes first)");
+    println!("  R                Update shims that use an older launcher");
+    println!("  x                Disable/enable env injection for selected app");
+    println!("  o                Sort apps by name / recently added");
+    println!("  S                Activate a profile on every app that has it");
     println!("  e                Edit selected environment variable");
+    println!("  V                Paste KEY=VALUE lines into the selected profile");
     println!("  d                Delete selected environment variable");
+    println!("  u                Undo last change (one level)");
     println!("  r                Reload configuration");
+    println!("  L                Show recent status messages");
     println!("  Enter            Enter app detail / Activate profile");
     println!("  Esc              Go back / Cancel");
     println!("  Tab              Switch focus between Profiles and Env Vars");
@@ -77,7 +136,11 @@ fn print_help() {
 fn run_tui() -> io::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+    crossterm::execute!(
+        stdout,
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -101,12 +164,12 @@ fn run_tui() -> io::Result<()> {
         let timeout = Duration::from_millis(200);
         let waited = timeout.saturating_sub(last_tick.elapsed());
         if event::poll(waited)? {
-            if let Event::Key(key) = event::read()? {
+            match event::read()? {
                 // Global exit on Ctrl+C is handled in handle_key but standard convention is good too.
                 // handle_key returns true if we should quit
-                if app.handle_key(key)? {
-                    break;
-                }
+                Event::Key(key) if app.handle_key(key)? => break,
+                Event::Paste(text) => app.handle_paste(&text),
+                _ => {}
             }
         }
         if last_tick.elapsed() >= timeout {
@@ -117,7 +180,8 @@ fn run_tui() -> io::Result<()> {
     disable_raw_mode()?;
     crossterm::execute!(
         terminal.backend_mut(),
-        crossterm::terminal::LeaveAlternateScreen
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::event::DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
     Ok(())
@@ -9,7 +9,9 @@ use ratatui::{
     },
 };
 
-use crate::app::{App, Focus, InputMode, InputStep, Page};
+use envhub_core::EnvHistoryEntry;
+
+use crate::app::{App, Focus, InputMode, InputStep, Page, StatusLevel};
 
 // Theme configuration
 struct Theme {
@@ -60,6 +62,15 @@ pub fn render(frame: &mut Frame, app: &App) {
     if app.input.mode != InputMode::Normal {
         render_input_modal(frame, area, app);
     }
+    if app.show_status_log {
+        render_status_log(frame, area, app);
+    }
+    if let Some((key, history)) = &app.env_history {
+        render_env_history(frame, area, key, history);
+    }
+    if let Some((apps, profile)) = &app.pending_activation {
+        render_confirm_activation(frame, area, app, apps, profile);
+    }
 }
 
 fn render_content_for_page(frame: &mut Frame, area: Rect, app: &App) {
@@ -121,6 +132,21 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
             .add_modifier(Modifier::BOLD),
     ));
 
+    // The state file's `_meta` block, shown as is; edited outside the TUI.
+    let meta = &app.state.meta;
+    let about = match (&meta.description, &meta.maintainer) {
+        (Some(description), Some(maintainer)) => Some(format!("{description} ({maintainer})")),
+        (Some(description), None) => Some(description.clone()),
+        (None, Some(maintainer)) => Some(format!("maintained by {maintainer}")),
+        (None, None) => None,
+    };
+    if let Some(about) = about {
+        title_text.push(Span::styled(
+            format!("  {about}"),
+            Style::default().fg(THEME.text_dim),
+        ));
+    }
+
     let instructions = match app.page {
         Page::AppsList => Line::from(vec![
             Span::styled(
@@ -137,6 +163,34 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw("dd App | "),
+            Span::styled(
+                "L",
+                Style::default()
+                    .fg(THEME.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("og | "),
+            Span::styled(
+                "I",
+                Style::default()
+                    .fg(THEME.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("mport | "),
+            Span::styled(
+                "u",
+                Style::default()
+                    .fg(THEME.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" Undo | "),
+            Span::styled(
+                "P",
+                Style::default()
+                    .fg(THEME.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" Install Dir | "),
             Span::styled(
                 "Enter",
                 Style::default()
@@ -168,6 +222,20 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
                         .add_modifier(Modifier::BOLD),
                 ),
                 Span::raw(" Activate | "),
+                Span::styled(
+                    "!",
+                    Style::default()
+                        .fg(THEME.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" Danger | "),
+                Span::styled(
+                    "g",
+                    Style::default()
+                        .fg(THEME.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" Group | "),
                 Span::styled(
                     "Tab",
                     Style::default()
@@ -228,6 +296,13 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
                         .add_modifier(Modifier::BOLD),
                 ),
                 Span::raw(" Del | "),
+                Span::styled(
+                    "H",
+                    Style::default()
+                        .fg(THEME.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" History | "),
                 Span::styled(
                     "Tab",
                     Style::default()
@@ -304,19 +379,30 @@ fn render_apps_list(frame: &mut Frame, area: Rect, app: &App) {
                 Span::styled(" [ ]", Style::default().fg(THEME.text_dim))
             };
 
-            let content = Line::from(vec![
+            let mut spans = vec![
                 Span::styled(marker, marker_style),
                 Span::raw(&entry.name),
                 install_marker,
                 Span::styled(subtext, Style::default().fg(THEME.text_dim)),
-            ]);
+            ];
+            if entry.disabled {
+                spans.push(Span::raw(" [disabled]"));
+                for span in spans.iter_mut() {
+                    span.style = Style::default().fg(THEME.text_dim);
+                }
+            }
+            let content = Line::from(spans);
 
             ListItem::new(content)
         })
         .collect();
 
+    let title = format!(
+        "Applications (i: install, o: sort by {})",
+        app.sort_mode.label()
+    );
     let list = List::new(items)
-        .block(draw_block("Applications (i: install)", focus))
+        .block(draw_block(&title, focus))
         .highlight_style(
             Style::default()
                 .bg(Color::DarkGray)
@@ -329,32 +415,106 @@ fn render_apps_list(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_stateful_widget(list, area, &mut state);
 }
 
+/// One line of the profiles panel: either a group header (from the
+/// `group/name` prefix convention) or a selectable profile.
+enum ProfileRow {
+    Group(String),
+    Profile { index: usize, label: String },
+}
+
+fn profile_rows(profiles: &[String], grouped: bool) -> Vec<ProfileRow> {
+    let mut rows = Vec::new();
+    let mut current_group: Option<&str> = None;
+    for (index, profile) in profiles.iter().enumerate() {
+        let split = if grouped {
+            profile.split_once('/')
+        } else {
+            None
+        };
+        match split {
+            Some((group, rest)) => {
+                if current_group != Some(group) {
+                    rows.push(ProfileRow::Group(group.to_string()));
+                    current_group = Some(group);
+                }
+                rows.push(ProfileRow::Profile {
+                    index,
+                    label: format!("  {rest}"),
+                });
+            }
+            None => {
+                current_group = None;
+                rows.push(ProfileRow::Profile {
+                    index,
+                    label: profile.clone(),
+                });
+            }
+        }
+    }
+    rows
+}
+
 fn render_profiles_list(frame: &mut Frame, area: Rect, app: &App) {
     let focus = app.focus == Focus::Profiles;
     let current_app = app.entries.get(app.selected_app);
     let active_profile = current_app.and_then(|a| a.active_profile.as_ref());
+    let profiles = app.current_profiles();
+    let rows = profile_rows(&profiles, app.group_profiles);
 
-    let items: Vec<ListItem> = app
-        .current_profiles()
-        .into_iter()
-        .map(|profile| {
-            let is_active = Some(&profile) == active_profile;
-            let icon = if is_active { "✓ " } else { "  " };
-            let style = if is_active {
-                Style::default().fg(THEME.success)
-            } else {
-                Style::default()
-            };
+    let selected_row = rows
+        .iter()
+        .position(|row| matches!(row, ProfileRow::Profile { index, .. } if *index == app.selected_profile))
+        .unwrap_or(0);
 
-            ListItem::new(Line::from(vec![
-                Span::styled(icon, style),
-                Span::raw(profile),
-            ]))
+    // Page through the rows so the selection is always on screen.
+    let visible = area.height.saturating_sub(2).max(1) as usize;
+    let page = selected_row / visible;
+    let pages = rows.len().div_ceil(visible).max(1);
+
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| match row {
+            ProfileRow::Group(group) => ListItem::new(Line::from(Span::styled(
+                format!("{group}/"),
+                Style::default()
+                    .fg(THEME.text_dim)
+                    .add_modifier(Modifier::BOLD),
+            ))),
+            ProfileRow::Profile { index, label } => {
+                let is_active = profiles.get(*index) == active_profile;
+                let icon = if is_active { "✓ " } else { "  " };
+                let style = if is_active {
+                    Style::default().fg(THEME.success)
+                } else {
+                    Style::default()
+                };
+
+                let mut spans = vec![Span::styled(icon, style), Span::raw(label.clone())];
+                if profiles
+                    .get(*index)
+                    .is_some_and(|profile| app.is_profile_dangerous(profile))
+                {
+                    spans.push(Span::styled(" ⚠", Style::default().fg(THEME.error)));
+                }
+                ListItem::new(Line::from(spans))
+            }
         })
         .collect();
 
+    let title = if pages > 1 {
+        format!("Profiles ({}/{pages})", page + 1)
+    } else {
+        "Profiles".to_string()
+    };
+    let mut block = draw_block(&title, focus);
+    if let Some(notes) = app.current_profile_notes() {
+        block = block.title_bottom(Line::from(Span::styled(
+            format!(" {} ", notes.lines().next().unwrap_or_default()),
+            Style::default().fg(THEME.accent),
+        )));
+    }
     let list = List::new(items)
-        .block(draw_block("Profiles", focus))
+        .block(block)
         .highlight_style(
             Style::default()
                 .bg(Color::DarkGray)
@@ -362,8 +522,8 @@ fn render_profiles_list(frame: &mut Frame, area: Rect, app: &App) {
         )
         .highlight_symbol("▎ ");
 
-    let mut state = ListState::default();
-    state.select(Some(app.selected_profile));
+    let mut state = ListState::default().with_offset(page * visible);
+    state.select(Some(selected_row));
     frame.render_stateful_widget(list, area, &mut state);
 }
 
@@ -476,11 +636,7 @@ fn get_env_rows(app: &App) -> (Vec<Row<'_>>, Option<String>) {
 }
 
 fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
-    let status_style = if app.status.starts_with("Failed") {
-        Style::default().fg(THEME.error)
-    } else {
-        Style::default().fg(THEME.text)
-    };
+    let status_style = Style::default().fg(level_color(app.status_level()));
 
     let text = Span::styled(format!(" {}", app.status), status_style);
 
@@ -493,6 +649,190 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
     );
 }
 
+fn level_color(level: StatusLevel) -> Color {
+    match level {
+        StatusLevel::Info => THEME.text,
+        StatusLevel::Warning => THEME.accent,
+        StatusLevel::Error => THEME.error,
+    }
+}
+
+fn render_status_log(frame: &mut Frame, area: Rect, app: &App) {
+    let modal_area = centered_rect(80, 60, area);
+    frame.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(Span::styled(
+            " Status Log (newest first, Esc to close) ",
+            Style::default()
+                .fg(THEME.accent)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(THEME.accent));
+
+    if app.status_log.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                "Nothing has happened yet",
+                Style::default().fg(THEME.text_dim),
+            ))
+            .block(block),
+            modal_area,
+        );
+        return;
+    }
+    let items: Vec<ListItem> = app
+        .status_log
+        .iter()
+        .rev()
+        .map(|entry| {
+            ListItem::new(Span::styled(
+                entry.message.clone(),
+                Style::default().fg(level_color(entry.level)),
+            ))
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(block), modal_area);
+}
+
+fn render_env_history(frame: &mut Frame, area: Rect, key: &str, history: &[EnvHistoryEntry]) {
+    let modal_area = centered_rect(80, 60, area);
+    frame.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" Earlier values of {key} (oldest first, Esc to close) "),
+            Style::default()
+                .fg(THEME.accent)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(THEME.accent));
+
+    if history.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                "No earlier values recorded",
+                Style::default().fg(THEME.text_dim),
+            ))
+            .block(block),
+            modal_area,
+        );
+        return;
+    }
+    let items: Vec<ListItem> = history
+        .iter()
+        .map(|entry| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}  ", entry.replaced_at),
+                    Style::default().fg(THEME.text_dim),
+                ),
+                Span::styled(entry.value.clone(), Style::default().fg(THEME.text)),
+            ]))
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(block), modal_area);
+}
+
+fn render_confirm_activation(
+    frame: &mut Frame,
+    area: Rect,
+    app: &App,
+    apps: &[String],
+    profile: &str,
+) {
+    let modal_area = centered_rect(60, 40, area);
+    frame.render_widget(Clear, modal_area);
+
+    let target = match apps {
+        [app_name] => app_name.clone(),
+        _ => format!("{} apps", apps.len()),
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" ⚠ Activate {profile} for {target}? "),
+            Style::default()
+                .fg(THEME.error)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(THEME.error));
+
+    let mut lines = Vec::new();
+    if let [app_name] = apps {
+        lines.push(Line::from(Span::styled(
+            "This profile is marked dangerous.",
+            Style::default().fg(THEME.text),
+        )));
+        lines.push(Line::default());
+        match app.profile_notes(app_name, profile) {
+            Some(notes) => lines.extend(notes.lines().map(|line| {
+                Line::from(Span::styled(
+                    line.to_string(),
+                    Style::default().fg(THEME.accent),
+                ))
+            })),
+            None => lines.push(Line::from(Span::styled(
+                "No notes for this profile.",
+                Style::default().fg(THEME.text_dim),
+            ))),
+        }
+    } else {
+        lines.push(Line::from(Span::styled(
+            format!("This switches {}.", apps.join(", ")),
+            Style::default().fg(THEME.text),
+        )));
+        lines.push(Line::from(Span::styled(
+            "The profile is marked dangerous in:",
+            Style::default().fg(THEME.text),
+        )));
+        for app_name in app.apps_where_dangerous(apps, profile) {
+            lines.push(Line::from(Span::styled(
+                format!("  {app_name}"),
+                Style::default()
+                    .fg(THEME.error)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            if let Some(notes) = app.profile_notes(&app_name, profile) {
+                lines.extend(notes.lines().map(|line| {
+                    Line::from(Span::styled(
+                        format!("    {line}"),
+                        Style::default().fg(THEME.accent),
+                    ))
+                }));
+            }
+        }
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(vec![
+        Span::styled(
+            "y",
+            Style::default()
+                .fg(THEME.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" Activate | "),
+        Span::styled(
+            "Esc",
+            Style::default()
+                .fg(THEME.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" Keep current"),
+    ]));
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        modal_area,
+    );
+}
+
 fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
     let modal_area = centered_rect(70, 35, area);
 
@@ -502,7 +842,11 @@ fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
         InputMode::AddApp => " Add Command Alias (App) ",
         InputMode::AddProfile => " Add Profile ",
         InputMode::SetEnv => " Set Environment Variable ",
+        InputMode::PasteEnv => " Paste Environment Variables ",
         InputMode::SetCommandArgs => " Set Command Args ",
+        InputMode::SetInstallPath => " Set Install Dir ",
+        InputMode::ImportApp => " Import App ",
+        InputMode::ActivateAll => " Switch All Apps ",
         InputMode::Normal => "",
     };
 
@@ -530,6 +874,29 @@ fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
         .split(inner_area);
 
     match (app.input.mode, app.input.step) {
+        (InputMode::ImportApp, InputStep::Second) => {
+            let mut lines = Vec::new();
+            if let Some((_, preview)) = &app.pending_import {
+                for conflict in &preview.conflicts {
+                    lines.push(Line::from(Span::styled(
+                        format!("! {conflict}"),
+                        Style::default().fg(THEME.error),
+                    )));
+                }
+                for change in preview.diff.to_string().lines() {
+                    let color = match change.trim_start().chars().next() {
+                        Some('+') => THEME.success,
+                        Some('-') => THEME.error,
+                        _ => THEME.text,
+                    };
+                    lines.push(Line::from(Span::styled(
+                        change.to_string(),
+                        Style::default().fg(color),
+                    )));
+                }
+            }
+            frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), layout[0]);
+        }
         (InputMode::AddProfile, InputStep::Second) => {
             // Render selection list
             let mut items = vec![ListItem::new(Span::raw("(None) - Empty Profile"))];
@@ -581,10 +948,26 @@ fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
                 (InputMode::AddProfile, InputStep::First) => ("New profile name:", None),
                 (InputMode::SetEnv, InputStep::First) => ("Variable KEY:", None),
                 (InputMode::SetEnv, InputStep::Second) => ("Variable VALUE:", None),
+                (InputMode::PasteEnv, _) => (
+                    "Paste KEY=VALUE lines (e.g. from a .env file):",
+                    Some("Tip: Lines without '=' are skipped; quotes are removed"),
+                ),
                 (InputMode::SetCommandArgs, InputStep::First) => (
                     "Command args (space-separated, empty to clear):",
                     None,
                 ),
+                (InputMode::ImportApp, InputStep::First) => (
+                    "Path to an exported app (.json):",
+                    Some("Tip: You can review the changes before anything is applied"),
+                ),
+                (InputMode::ActivateAll, InputStep::First) => (
+                    "Profile to activate on every app that has it:",
+                    Some("Tip: Apps without that profile are left as they are"),
+                ),
+                (InputMode::SetInstallPath, InputStep::First) => (
+                    "Shim install directory (empty for the default):",
+                    Some("Tip: The directory must be on your PATH"),
+                ),
                 _ => ("", None),
             };
 
@@ -602,20 +985,72 @@ fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
             // Add prompt
             lines.push(Line::from(Span::styled(prompt, Style::default().fg(THEME.text_dim))));
 
-            // Add input line
-            lines.push(Line::from(vec![
-                Span::raw(" > "),
-                Span::styled(
-                    &app.input.buf,
-                    Style::default().fg(THEME.text).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    "_",
-                    Style::default()
-                        .fg(THEME.accent)
-                        .add_modifier(Modifier::SLOW_BLINK),
-                ), // Cursor
-            ]));
+            // Add input lines; only a paste into PasteEnv spans several
+            let input_lines: Vec<&str> = app.input.buf.split('\n').collect();
+            let last = input_lines.len() - 1;
+            for (index, text) in input_lines.into_iter().enumerate() {
+                let mut spans = vec![
+                    Span::raw(if index == 0 { " > " } else { "   " }),
+                    Span::styled(
+                        text,
+                        Style::default().fg(THEME.text).add_modifier(Modifier::BOLD),
+                    ),
+                ];
+                if index == last {
+                    spans.push(Span::styled(
+                        "_",
+                        Style::default()
+                            .fg(THEME.accent)
+                            .add_modifier(Modifier::SLOW_BLINK),
+                    )); // Cursor
+                }
+                lines.push(Line::from(spans));
+            }
+
+            // Live check of the variable as it is typed
+            if app.input.mode == InputMode::SetEnv && !app.input.buf.trim().is_empty() {
+                let check = match app.input.step {
+                    InputStep::First => envhub_core::validate_env_pair(app.input.buf.trim(), ""),
+                    InputStep::Second => {
+                        envhub_core::validate_env_pair(&app.input.first, app.input.buf.trim())
+                    }
+                };
+                lines.push(match check {
+                    Ok(()) => {
+                        Line::from(Span::styled(" ✓ valid", Style::default().fg(THEME.success)))
+                    }
+                    Err(err) => Line::from(Span::styled(
+                        format!(" ✗ {}", err.message),
+                        Style::default().fg(THEME.error),
+                    )),
+                });
+            }
+
+            // What `${VAR}` references in the value resolve to at launch
+            if let Some(preview) = app.env_value_preview() {
+                lines.push(match &preview.resolved {
+                    Ok(resolved) => Line::from(vec![
+                        Span::styled(" → ", Style::default().fg(THEME.text_dim)),
+                        Span::styled(resolved.clone(), Style::default().fg(THEME.text)),
+                    ]),
+                    Err(message) => Line::from(Span::styled(
+                        format!(" ✗ {message}"),
+                        Style::default().fg(THEME.error),
+                    )),
+                });
+                if !preview.unset.is_empty() {
+                    lines.push(Line::from(Span::styled(
+                        format!(" ! not set: {}", preview.unset.join(", ")),
+                        Style::default().fg(THEME.accent),
+                    )));
+                }
+                for name in &preview.unbraced {
+                    lines.push(Line::from(Span::styled(
+                        format!(" ! ${name} is not expanded; write ${{{name}}}"),
+                        Style::default().fg(THEME.accent),
+                    )));
+                }
+            }
 
             frame.render_widget(
                 Paragraph::new(lines).wrap(Wrap { trim: false }),
@@ -624,12 +1059,17 @@ fn render_input_modal(frame: &mut Frame, area: Rect, app: &App) {
         }
     }
 
-    let hints = Line::from(vec![
+    let mut hints = vec![
         Span::styled("Enter", Style::default().fg(THEME.primary)),
         Span::raw(" Confirm  "),
-        Span::styled("Esc", Style::default().fg(THEME.error)),
-        Span::raw(" Cancel"),
-    ]);
+    ];
+    if app.input.mode == InputMode::AddProfile && app.input.step == InputStep::Second {
+        hints.push(Span::styled("s", Style::default().fg(THEME.primary)));
+        hints.push(Span::raw(" Add & switch  "));
+    }
+    hints.push(Span::styled("Esc", Style::default().fg(THEME.error)));
+    hints.push(Span::raw(" Cancel"));
+    let hints = Line::from(hints);
 
     frame.render_widget(
         Paragraph::new(hints).alignment(Alignment::Center),
@@ -28,7 +28,19 @@ fn build_launcher() {
     let launcher_src_dir = workspace_dir.join("crates/envhub-launcher/src");
     let launcher_manifest = workspace_dir.join("crates/envhub-launcher/Cargo.toml");
 
-    let needs_rebuild = launcher_needs_rebuild(&launcher_path, &launcher_src_dir, &launcher_manifest);
+    // ENVHUB_SKIP_LAUNCHER_BUILD=1 reuses whatever launcher was built last,
+    // for frontend-only iterations.
+    let skip_rebuild = std::env::var("ENVHUB_SKIP_LAUNCHER_BUILD")
+        .is_ok_and(|value| !value.is_empty() && value != "0");
+    if skip_rebuild && !launcher_path.exists() {
+        panic!(
+            "ENVHUB_SKIP_LAUNCHER_BUILD is set but no launcher exists at {}; \
+             unset it once to build the launcher",
+            launcher_path.display()
+        );
+    }
+    let needs_rebuild = !skip_rebuild
+        && launcher_needs_rebuild(&launcher_path, &launcher_src_dir, &launcher_manifest);
     if needs_rebuild {
         let mut cmd = Command::new("cargo");
         cmd.current_dir(workspace_dir)
@@ -63,6 +75,7 @@ fn build_launcher() {
 
     println!("cargo:rerun-if-changed=../crates/envhub-launcher/src");
     println!("cargo:rerun-if-changed=../crates/envhub-launcher/Cargo.toml");
+    println!("cargo:rerun-if-env-changed=ENVHUB_SKIP_LAUNCHER_BUILD");
 }
 
 fn launcher_needs_rebuild(launcher_path: &Path, src_dir: &Path, manifest: &Path) -> bool {
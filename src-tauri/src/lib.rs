@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use envhub_core::{InstallMode, State};
+use envhub_core::{
+    AppSummary, CoreError, Diagnostic, InstallMode, InstallPlan, SetupResult, ShimLocation, State,
+};
 use serde::Serialize;
 use tauri::path::BaseDirectory;
 use tauri::Manager;
@@ -18,8 +20,49 @@ fn get_config() -> Result<State, String> {
 }
 
 #[tauri::command]
-fn save_config(state: State) -> Result<(), String> {
-    envhub_core::save_state(&state).map_err(|e| e.to_string())
+fn save_config(state: State) -> Result<(), CoreError> {
+    envhub_core::save_state_validated(&state)
+}
+
+/// The state's `default_install_mode`, or `User` when the state can't be read.
+fn install_mode() -> InstallMode {
+    envhub_core::default_install_mode().unwrap_or_default()
+}
+
+#[tauri::command]
+fn get_default_install_mode() -> Result<InstallMode, CoreError> {
+    envhub_core::default_install_mode()
+}
+
+#[tauri::command]
+fn set_default_install_mode(mode: InstallMode) -> Result<(), CoreError> {
+    envhub_core::set_default_install_mode(mode)
+}
+
+#[derive(Serialize)]
+struct AppsPage {
+    apps: Vec<AppSummary>,
+    /// Apps matching the filter, across all pages.
+    total: usize,
+}
+
+/// One page of app summaries for the virtualized list, optionally filtered by
+/// a case-insensitive substring of the name or target.
+#[tauri::command]
+fn get_apps_page(offset: usize, limit: usize, filter: Option<String>) -> Result<AppsPage, String> {
+    let mut apps = envhub_core::list_apps_detailed(install_mode()).map_err(|e| e.to_string())?;
+    if let Some(filter) = filter
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty())
+    {
+        apps.retain(|app| {
+            app.name.to_lowercase().contains(&filter)
+                || app.target_binary.to_lowercase().contains(&filter)
+        });
+    }
+    let total = apps.len();
+    let apps = apps.into_iter().skip(offset).take(limit).collect();
+    Ok(AppsPage { apps, total })
 }
 
 #[derive(Serialize)]
@@ -28,55 +71,79 @@ struct AppInstallStatus {
 }
 
 fn bundled_launcher_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let file_name = if cfg!(windows) {
-        "envhub-launcher.exe"
-    } else {
-        "envhub-launcher"
-    };
     app.path()
-        .resolve(file_name, BaseDirectory::Resource)
+        .resolve(envhub_core::launcher_file_name(), BaseDirectory::Resource)
         .map_err(|e| e.to_string())
 }
 
+/// Installs the bundled launcher if it isn't there yet. Shell rc files are
+/// only touched by the explicit `ensure_setup` command.
 fn ensure_launcher_installed(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let platform = envhub_core::detect_platform(InstallMode::User)
-        .map_err(|e| e.to_string())?;
-    let launcher_name = if cfg!(windows) {
-        "envhub-launcher.exe"
-    } else {
-        "envhub-launcher"
-    };
-    let installed_path = platform.install_dir.join(launcher_name);
-
+    let mode = install_mode();
+    let platform = envhub_core::detect_platform(mode).map_err(|e| e.to_string())?;
+    let file_name = envhub_core::launcher_file_name_for(&envhub_core::launcher_name());
+    let installed_path = platform.install_dir.join(file_name);
     if installed_path.exists() {
         return Ok(installed_path);
     }
-
     let bundled_path = bundled_launcher_path(app)?;
-    envhub_core::install_launcher(InstallMode::User, &bundled_path)
-        .map_err(|e| e.to_string())
+    envhub_core::install_launcher(mode, &bundled_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ensure_setup(app: tauri::AppHandle) -> Result<SetupResult, String> {
+    let bundled_path = bundled_launcher_path(&app)?;
+    envhub_core::ensure_setup(&bundled_path, install_mode()).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn get_app_install_status(app_names: Vec<String>) -> Result<AppInstallStatus, String> {
     let mut app_installed = HashMap::new();
 
+    let mode = install_mode();
     for name in app_names {
-        let installed = envhub_core::is_shim_installed(&name, InstallMode::User);
+        let installed = envhub_core::is_shim_installed(&name, mode);
         app_installed.insert(name, installed);
     }
 
     Ok(AppInstallStatus { app_installed })
 }
 
+/// Like `get_app_install_status`, with each shim's path and whether its
+/// directory is on `PATH`, for "installed at ~/.envhub/bin/claudex (on PATH ✓)".
 #[tauri::command]
-fn install_app_shim(app: tauri::AppHandle, app_name: String) -> Result<(), String> {
+fn get_app_install_locations(app_names: Vec<String>) -> Result<Vec<ShimLocation>, CoreError> {
+    envhub_core::shim_locations(&app_names, install_mode())
+}
+
+#[tauri::command]
+fn install_app_shim(
+    app: tauri::AppHandle,
+    app_name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
     let launcher_path = ensure_launcher_installed(&app)?;
-    envhub_core::install_shim(&app_name, InstallMode::User, &launcher_path)
+    let force = force.unwrap_or(false);
+    envhub_core::install_shim_with(&app_name, install_mode(), &launcher_path, force)
         .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
+/// Lets the UI confirm before `install_app_shim` replaces an unrelated file.
+#[tauri::command]
+fn plan_app_shim(app: tauri::AppHandle, app_name: String) -> Result<InstallPlan, String> {
+    let launcher_path = bundled_launcher_path(&app)?;
+    envhub_core::plan_install_shim(&app_name, install_mode(), &launcher_path)
+        .map_err(|e| e.to_string())
+}
+
+/// Findings for the health panel, most severe first. Each carries a `code`
+/// the frontend maps to a fix, e.g. `path_not_configured` to `ensure_setup`.
+#[tauri::command]
+fn run_doctor() -> Vec<Diagnostic> {
+    envhub_core::diagnose()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -84,10 +151,35 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             get_config,
+            get_apps_page,
             save_config,
             get_app_install_status,
-            install_app_shim
+            get_app_install_locations,
+            install_app_shim,
+            plan_app_shim,
+            ensure_setup,
+            get_default_install_mode,
+            set_default_install_mode,
+            run_doctor
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use envhub_core::DiagnosticCode;
+
+    #[test]
+    fn run_doctor_reports_the_launcher_status() {
+        let found = run_doctor();
+        assert!(
+            found.iter().any(|diagnostic| matches!(
+                diagnostic.code,
+                DiagnosticCode::LauncherInstalled | DiagnosticCode::LauncherMissing
+            )),
+            "{found:?}"
+        );
+    }
+}